@@ -1,16 +1,28 @@
 fn main() {
-    println!("cargo:rerun-if-changed=./src/types.d.ts");
+    println!("cargo:rerun-if-changed=build.rs");
 
-    let types = std::fs::read_to_string("./src/types.d.ts").expect("Can't read types.d.ts");
-    let out_path = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
-    std::fs::write(
-        out_path.join("types.rs"),
-        format!(
-            r######"use wasm_bindgen::prelude::*;
-#[wasm_bindgen(typescript_custom_section)]
-const TS_TYPES: &str = r###"{types}"###;
-"######
-        ),
-    )
-    .expect("Can't write types.rs");
+    #[cfg(feature = "ffi")]
+    generate_c_header();
+}
+
+/// 生成 C 头文件，平时只写入 `OUT_DIR` 以检查接口能否导出，
+/// 设置 `AMLL_LYRIC_UPDATE_HEADER` 环境变量时才会更新源码中的 `include/amll_lyric.h`
+#[cfg(feature = "ffi")]
+fn generate_c_header() {
+    println!("cargo:rerun-if-changed=./src/ffi.rs");
+    println!("cargo:rerun-if-changed=./cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=AMLL_LYRIC_UPDATE_HEADER");
+
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let config = cbindgen::Config::from_root_or_default(&crate_dir);
+    let bindings = cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("Can't generate C header");
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    bindings.write_to_file(std::path::Path::new(&out_dir).join("amll_lyric.h"));
+    if std::env::var_os("AMLL_LYRIC_UPDATE_HEADER").is_some() {
+        bindings.write_to_file(std::path::Path::new(&crate_dir).join("include/amll_lyric.h"));
+    }
 }
@@ -0,0 +1,199 @@
+#ifndef AMLL_LYRIC_H
+#define AMLL_LYRIC_H
+
+/* 此文件由 cbindgen 自动生成，请勿手动修改 */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * 歌词格式
+ *
+ * 接口以 `uint32_t` 接收格式，无法识别的值会返回错误结果。
+ */
+enum AmllLyricFormat
+#if defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+  : uint32_t
+#endif // defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+ {
+  /**
+   * LyRiC 格式
+   */
+  AMLL_LYRIC_FORMAT_LRC = 0,
+  /**
+   * ESLyric 逐词歌词格式
+   */
+  AMLL_LYRIC_FORMAT_ESLRC = 1,
+  /**
+   * 网易云音乐逐词歌词格式
+   */
+  AMLL_LYRIC_FORMAT_YRC = 2,
+  /**
+   * QQ 音乐逐词歌词格式
+   */
+  AMLL_LYRIC_FORMAT_QRC = 3,
+  /**
+   * Lyricify Syllable 逐词歌词格式
+   */
+  AMLL_LYRIC_FORMAT_LYS = 4,
+  /**
+   * TTML 歌词格式
+   */
+  AMLL_LYRIC_FORMAT_TTML = 5,
+  /**
+   * ASS 字幕格式，仅支持生成
+   */
+  AMLL_LYRIC_FORMAT_ASS = 6,
+};
+#ifndef __cplusplus
+#if __STDC_VERSION__ >= 202311L
+typedef enum AmllLyricFormat AmllLyricFormat;
+#else
+typedef uint32_t AmllLyricFormat;
+#endif // __STDC_VERSION__ >= 202311L
+#endif // __cplusplus
+
+/**
+ * 接口调用的结果，需要使用 [`amll_lyric_free`] 释放
+ */
+typedef struct AmllLyricResult AmllLyricResult;
+
+/**
+ * 一个歌词单词
+ */
+typedef struct AmllLyricWord {
+  uint64_t start_time;
+  uint64_t end_time;
+  /**
+   * 单词文本，作为输入时可以为空指针，视为空字符串
+   */
+  const char *word;
+  /**
+   * 单词音译，作为输入时可以为空指针，视为空字符串
+   */
+  const char *roman_word;
+} AmllLyricWord;
+
+/**
+ * 一个歌词行
+ */
+typedef struct AmllLyricLine {
+  /**
+   * 单词数组，长度为 `words_len`
+   */
+  const struct AmllLyricWord *words;
+  size_t words_len;
+  /**
+   * 翻译文本，作为输入时可以为空指针，视为空字符串
+   */
+  const char *translated_lyric;
+  /**
+   * 音译文本，作为输入时可以为空指针，视为空字符串
+   */
+  const char *roman_lyric;
+  bool is_bg;
+  bool is_duet;
+  uint64_t start_time;
+  uint64_t end_time;
+} AmllLyricLine;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * 解析歌词
+ *
+ * `format` 为 [`AmllLyricFormat`] 中的值，
+ * `src` 为长度为 `len` 字节的 UTF-8 文本，不需要以 NUL 结尾。
+ * 成功时可以通过 [`amll_lyric_lines`] 获取歌词行。
+ *
+ * # Safety
+ *
+ * `src` 必须指向至少 `len` 字节的可读内存
+ */
+struct AmllLyricResult *amll_lyric_parse(uint32_t format, const char *src, size_t len);
+
+/**
+ * 将歌词行生成为指定格式的歌词文本
+ *
+ * `format` 为 [`AmllLyricFormat`] 中的值，成功时可以通过 [`amll_lyric_text`] 获取生成的文本。
+ *
+ * # Safety
+ *
+ * `lines` 必须指向至少 `len` 个歌词行，每个歌词行中的指针都必须有效
+ */
+struct AmllLyricResult *amll_lyric_stringify(uint32_t format,
+                                             const struct AmllLyricLine *lines,
+                                             size_t len);
+
+/**
+ * 解密 EQRC 歌词数据
+ *
+ * 如果 `is_hex` 为 `true`，`data` 会被视为十六进制文本，否则视为原始的二进制数据。
+ * 成功时可以通过 [`amll_lyric_text`] 获取解密得到的 QRC 歌词文本。
+ *
+ * # Safety
+ *
+ * `data` 必须指向至少 `len` 字节的可读内存
+ */
+struct AmllLyricResult *amll_lyric_decrypt_eqrc(const uint8_t *data,
+                                                size_t len,
+                                                bool is_hex);
+
+/**
+ * 结果是否成功，空指针视为失败
+ *
+ * # Safety
+ *
+ * `result` 必须为空指针或由本库返回且尚未释放的结果对象
+ */
+bool amll_lyric_is_ok(const struct AmllLyricResult *result);
+
+/**
+ * 获取结果中的错误信息，结果成功时返回空指针
+ *
+ * # Safety
+ *
+ * `result` 必须为空指针或由本库返回且尚未释放的结果对象
+ */
+const char *amll_lyric_error(const struct AmllLyricResult *result);
+
+/**
+ * 获取结果中生成或解密得到的文本，结果中没有文本时返回空指针
+ *
+ * # Safety
+ *
+ * `result` 必须为空指针或由本库返回且尚未释放的结果对象
+ */
+const char *amll_lyric_text(const struct AmllLyricResult *result);
+
+/**
+ * 获取结果中解析得到的歌词行数组，数组长度写入 `len`
+ *
+ * 结果中没有歌词行时返回空指针，且 `len` 会被设为 0。
+ *
+ * # Safety
+ *
+ * `result` 必须为空指针或由本库返回且尚未释放的结果对象，`len` 必须为空指针或可写的指针
+ */
+const struct AmllLyricLine *amll_lyric_lines(const struct AmllLyricResult *result,
+                                             size_t *len);
+
+/**
+ * 释放结果对象，传入空指针时不做任何事
+ *
+ * # Safety
+ *
+ * `result` 必须为空指针或由本库返回且尚未释放的结果对象
+ */
+void amll_lyric_free(struct AmllLyricResult *result);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* AMLL_LYRIC_H */
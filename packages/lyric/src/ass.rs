@@ -35,7 +35,8 @@ pub fn stringify_ass(lines: &[LyricLine]) -> String {
         "Formats: Marked, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n",
     );
 
-    for line in lines {
+    // ASS 只能表达整行的演唱者，中途切换演唱者的歌词行需要拆分
+    for line in lines.iter().flat_map(LyricLine::split_by_agent) {
         result.push_str("Dialogue: 0,");
 
         // 防止开始时间为 0 的空格影响行开始时间的计算
@@ -135,9 +136,19 @@ pub fn stringify_ass(lines: &[LyricLine]) -> String {
     result
 }
 
-#[cfg(target_arch = "wasm32")]
-#[wasm_bindgen(js_name = "stringifyAss", skip_typescript)]
-pub fn stringify_ass_js(lrc: JsValue) -> String {
-    let lines: Vec<LyricLine> = serde_wasm_bindgen::from_value(lrc).unwrap();
-    stringify_ass(&lines)
+/// 将歌词数组转换为 ASS 字幕格式的字符串
+///
+/// 注意导出会损失 10 毫秒以内的精度
+///
+/// 主唱名称会变为 `v1`，对唱会变为 `v2`
+///
+/// 如果是背景歌词则会在名称后面加上后缀 `-bg`
+#[cfg(all(target_arch = "wasm32", feature = "serde"))]
+#[wasm_bindgen(js_name = "stringifyAss", return_description = "ASS 字幕格式的字符串")]
+pub fn stringify_ass_js(
+    #[wasm_bindgen(unchecked_param_type = "LyricLine[]", param_description = "歌词数组")]
+    lines: JsValue,
+) -> Result<String, JsError> {
+    let lines: Vec<LyricLine> = crate::utils::from_js(lines)?;
+    Ok(stringify_ass(&lines))
 }
@@ -0,0 +1,454 @@
+//! AMLL 歌词格式转换命令行工具
+//!
+//! 用法示例：
+//!
+//! ```text
+//! amll-lyric --to ttml song.yrc
+//! amll-lyric --to ttml --translation trans/ --out-dir out/ lyrics/
+//! amll-lyric --from eqrc --to lrc --stdout song.qrc
+//! ```
+//!
+//! 任意文件转换失败时会输出错误信息，并在处理完所有文件后以非零状态码退出。
+
+use std::{
+    ffi::{OsStr, OsString},
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use amll_lyric::{
+    LyricLine, LyricLineOwned,
+    ttml::{TTMLLyric, TTMLLyricOwned},
+};
+use anyhow::{Context, bail};
+use clap::{Parser, ValueEnum};
+
+/// 匹配翻译歌词行时允许的最大时间误差，单位为毫秒
+const TRANSLATION_TOLERANCE: u64 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Lrc,
+    Eslrc,
+    Yrc,
+    Qrc,
+    Lys,
+    Ttml,
+    /// 加密的 QRC 歌词，可以是原始二进制数据或十六进制文本，仅支持作为输入
+    Eqrc,
+    /// ASS 字幕，仅支持作为输出
+    Ass,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        Some(match ext.as_str() {
+            "lrc" => Self::Lrc,
+            "eslrc" => Self::Eslrc,
+            "yrc" => Self::Yrc,
+            "qrc" => Self::Qrc,
+            "lys" => Self::Lys,
+            "ttml" => Self::Ttml,
+            "eqrc" => Self::Eqrc,
+            "ass" => Self::Ass,
+            _ => return None,
+        })
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Lrc => "lrc",
+            Self::Eslrc => "eslrc",
+            Self::Yrc => "yrc",
+            Self::Qrc => "qrc",
+            Self::Lys => "lys",
+            Self::Ttml => "ttml",
+            Self::Eqrc => "eqrc",
+            Self::Ass => "ass",
+        }
+    }
+}
+
+/// 在 AMLL 支持的各种歌词格式之间转换
+#[derive(Debug, Parser)]
+#[command(name = "amll-lyric", version)]
+struct Args {
+    /// 需要转换的歌词文件或文件夹，文件夹内所有支持的歌词文件都会被转换
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    /// 输入格式，默认根据文件扩展名判断
+    #[arg(short, long, value_enum)]
+    from: Option<Format>,
+    /// 输出格式
+    #[arg(short, long, value_enum)]
+    to: Format,
+    /// 输出文件夹，默认与输入文件放在同一文件夹内
+    #[arg(short, long)]
+    out_dir: Option<PathBuf>,
+    /// 将结果输出到标准输出，而不是写入文件
+    #[arg(long, conflicts_with = "out_dir")]
+    stdout: bool,
+    /// 递归处理子文件夹
+    #[arg(short, long)]
+    recursive: bool,
+    /// 合并到歌词中的 LRC 翻译文件；输入为文件夹时，
+    /// 此处也应为文件夹，会使用其中与歌词文件同名的 `.lrc` 文件
+    #[arg(long)]
+    translation: Option<PathBuf>,
+    /// 歌词时间偏移，单位为毫秒，正数代表延后
+    #[arg(long, default_value_t = 0, allow_negative_numbers = true)]
+    offset: i64,
+}
+
+/// 一个需要转换的歌词文件
+struct Job {
+    input: PathBuf,
+    format: Format,
+    translation: Option<PathBuf>,
+}
+
+fn collect_dir(
+    dir: &Path,
+    args: &Args,
+    translation_dir: Option<&Path>,
+    jobs: &mut Vec<Job>,
+) -> anyhow::Result<()> {
+    let mut entries = fs::read_dir(dir)
+        .with_context(|| format!("failed to read directory {}", dir.display()))?
+        .map(|entry| entry.map(|x| x.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("failed to read directory {}", dir.display()))?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            if args.recursive {
+                let translation_dir = translation_dir.and_then(|x| Some(x.join(path.file_name()?)));
+                collect_dir(&path, args, translation_dir.as_deref(), jobs)?;
+            }
+            continue;
+        }
+        // 文件夹中的文件只处理能识别的格式，`--from` 只用于覆盖格式判断
+        let Some(detected) = Format::from_path(&path) else {
+            continue;
+        };
+        if detected == Format::Ass {
+            continue;
+        }
+        let translation = translation_dir
+            .and_then(|x| Some(x.join(file_name(path.file_stem()?, "lrc"))))
+            .filter(|x| x.is_file());
+        jobs.push(Job {
+            format: args.from.unwrap_or(detected),
+            input: path,
+            translation,
+        });
+    }
+    Ok(())
+}
+
+/// 将文件名主干和扩展名拼接为文件名
+///
+/// 与 [`Path::with_extension`] 不同，主干中的 `.` 会被保留，例如 `Song ft. X`
+fn file_name(stem: &OsStr, extension: &str) -> OsString {
+    let mut name = stem.to_owned();
+    name.push(".");
+    name.push(extension);
+    name
+}
+
+fn collect_jobs(args: &Args) -> anyhow::Result<Vec<Job>> {
+    let mut jobs = Vec::new();
+    for input in &args.inputs {
+        if input.is_dir() {
+            if let Some(translation) = &args.translation
+                && !translation.is_dir()
+            {
+                bail!(
+                    "translation {} must be a directory when the input is a directory",
+                    translation.display()
+                );
+            }
+            collect_dir(input, args, args.translation.as_deref(), &mut jobs)?;
+        } else {
+            let Some(format) = args.from.or_else(|| Format::from_path(input)) else {
+                bail!(
+                    "can't detect the lyric format of {}, please specify it with --from",
+                    input.display()
+                );
+            };
+            jobs.push(Job {
+                input: input.clone(),
+                format,
+                translation: args.translation.clone(),
+            });
+        }
+    }
+    Ok(jobs)
+}
+
+/// 解密 EQRC 歌词，同时支持原始二进制数据和十六进制文本
+fn decrypt_eqrc(data: Vec<u8>) -> anyhow::Result<String> {
+    let text = std::str::from_utf8(&data).ok().map(str::trim);
+    let decrypted = match text {
+        Some(hex) if !hex.is_empty() && hex.bytes().all(|x| x.is_ascii_hexdigit()) => {
+            amll_lyric::eqrc::decrypt_qrc_hex(hex)
+        }
+        _ => {
+            let mut data = data;
+            amll_lyric::eqrc::decrypt_qrc_raw(&mut data)
+        }
+    };
+    if decrypted.is_empty() {
+        bail!("failed to decrypt EQRC data");
+    }
+    // 解密后的歌词通常被包裹在 XML 的 LyricContent 属性中
+    let content = decrypted
+        .split_once("LyricContent=\"")
+        .and_then(|(_, rest)| rest.split_once('"'))
+        .map(|(content, _)| {
+            content
+                .replace("&quot;", "\"")
+                .replace("&apos;", "'")
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&amp;", "&")
+        });
+    Ok(content.unwrap_or(decrypted))
+}
+
+fn parse(data: Vec<u8>, format: Format) -> anyhow::Result<TTMLLyricOwned> {
+    let text = match format {
+        Format::Eqrc => decrypt_eqrc(data)?,
+        Format::Ttml => {
+            let lyric = amll_lyric::ttml::parse_ttml(data.as_slice())?;
+            return Ok(lyric.into());
+        }
+        _ => String::from_utf8(data).context("lyric file is not valid UTF-8")?,
+    };
+    let lines: Vec<LyricLine> = match format {
+        Format::Lrc => amll_lyric::lrc::parse_lrc(&text),
+        Format::Eslrc => amll_lyric::eslrc::parse_eslrc(&text),
+        Format::Yrc => amll_lyric::yrc::parse_yrc(&text),
+        Format::Qrc | Format::Eqrc => amll_lyric::qrc::parse_qrc(&text),
+        Format::Lys => amll_lyric::lys::parse_lys(&text),
+        Format::Ass => bail!("ASS is only supported as an output format"),
+        Format::Ttml => unreachable!(),
+    };
+    if lines.is_empty() && !text.trim().is_empty() {
+        bail!(
+            "no lyric lines found, is it really a {} file?",
+            format.extension()
+        );
+    }
+    Ok(TTMLLyricOwned {
+        lines: lines.into_iter().map(|x| x.to_owned()).collect(),
+        metadata: Vec::new(),
+    })
+}
+
+fn stringify(lyric: &TTMLLyricOwned, format: Format) -> anyhow::Result<String> {
+    let lyric: TTMLLyric = lyric.to_ref();
+    let lines = &lyric.lines;
+    Ok(match format {
+        Format::Lrc => amll_lyric::lrc::stringify_lrc(lines),
+        Format::Eslrc => amll_lyric::eslrc::stringify_eslrc(lines),
+        Format::Yrc => amll_lyric::yrc::stringify_yrc(lines),
+        Format::Qrc => amll_lyric::qrc::stringify_qrc(lines),
+        Format::Lys => amll_lyric::lys::stringify_lys(lines),
+        Format::Ttml => amll_lyric::ttml::stringify_ttml(&lyric)?,
+        Format::Ass => amll_lyric::ass::stringify_ass(lines),
+        Format::Eqrc => bail!("EQRC is only supported as an input format"),
+    })
+}
+
+/// 将 LRC 翻译按时间合并到开始时间最接近的非背景歌词行中
+fn merge_translation(lines: &mut [LyricLineOwned], path: &Path) -> anyhow::Result<()> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("failed to read translation {}", path.display()))?;
+    for translation in amll_lyric::lrc::parse_lrc(&text) {
+        let text = translation.to_line();
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let target = lines
+            .iter_mut()
+            .filter(|x| !x.is_bg)
+            .min_by_key(|x| x.start_time.abs_diff(translation.start_time))
+            .filter(|x| x.start_time.abs_diff(translation.start_time) <= TRANSLATION_TOLERANCE);
+        if let Some(line) = target {
+            line.translated_lyric = text.to_owned();
+        }
+    }
+    Ok(())
+}
+
+fn apply_offset(lines: &mut [LyricLineOwned], offset: i64) {
+    let shift = |time: &mut u64| *time = time.saturating_add_signed(offset);
+    for line in lines {
+        shift(&mut line.start_time);
+        shift(&mut line.end_time);
+        for word in line.words.iter_mut() {
+            shift(&mut word.start_time);
+            shift(&mut word.end_time);
+        }
+    }
+}
+
+fn convert(job: &Job, args: &Args) -> anyhow::Result<()> {
+    let data = fs::read(&job.input).context("failed to read lyric file")?;
+    let mut lyric = parse(data, job.format)?;
+    if let Some(translation) = &job.translation {
+        merge_translation(&mut lyric.lines, translation)?;
+    }
+    if args.offset != 0 {
+        apply_offset(&mut lyric.lines, args.offset);
+    }
+    let output = stringify(&lyric, args.to)?;
+
+    if args.stdout {
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(output.as_bytes())?;
+        if !output.ends_with('\n') {
+            stdout.write_all(b"\n")?;
+        }
+        return Ok(());
+    }
+
+    let dir = match &args.out_dir {
+        Some(dir) => dir.as_path(),
+        None => job.input.parent().unwrap_or(Path::new(".")),
+    };
+    let Some(stem) = job.input.file_stem() else {
+        bail!("invalid input file name");
+    };
+    let output_path = dir.join(file_name(stem, args.to.extension()));
+    if output_path == job.input {
+        bail!("output file would overwrite the input file, please specify --out-dir");
+    }
+    fs::create_dir_all(dir)
+        .with_context(|| format!("failed to create directory {}", dir.display()))?;
+    fs::write(&output_path, output)
+        .with_context(|| format!("failed to write {}", output_path.display()))?;
+    eprintln!("{} -> {}", job.input.display(), output_path.display());
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let jobs = match collect_jobs(&args) {
+        Ok(jobs) => jobs,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if jobs.is_empty() {
+        eprintln!("error: no lyric files found");
+        return ExitCode::FAILURE;
+    }
+
+    let mut failed = 0;
+    for job in &jobs {
+        if let Err(err) = convert(job, &args) {
+            eprintln!("error: {}: {err:#}", job.input.display());
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        eprintln!("{failed} of {} files failed to convert", jobs.len());
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+#[test]
+fn test_convert_with_translation_and_offset() {
+    let dir = std::env::temp_dir().join(format!("amll-lyric-cli-test-{}", std::process::id()));
+    let out_dir = dir.join("out");
+    fs::create_dir_all(dir.join("trans")).unwrap();
+    fs::write(
+        dir.join("song.lrc"),
+        "[00:01.00]Hello world\n[00:03.50]Second line\n",
+    )
+    .unwrap();
+    fs::write(dir.join("trans/song.lrc"), "[00:01.10]你好世界\n").unwrap();
+    fs::write(dir.join("Song ft. X.lrc"), "[00:02.00]Featuring\n").unwrap();
+    fs::write(dir.join("trans/Song ft. X.lrc"), "[00:02.00]客串\n").unwrap();
+    fs::write(dir.join("notes.txt"), "not a lyric").unwrap();
+
+    let args = Args::parse_from([
+        "amll-lyric".as_ref(),
+        "--to".as_ref(),
+        "lrc".as_ref(),
+        "--offset".as_ref(),
+        "-500".as_ref(),
+        "--translation".as_ref(),
+        dir.join("trans").as_os_str(),
+        "--out-dir".as_ref(),
+        out_dir.as_os_str(),
+        dir.as_os_str(),
+    ]);
+    let jobs = collect_jobs(&args).unwrap();
+    assert_eq!(jobs.len(), 2);
+    assert!(jobs.iter().all(|x| x.format == Format::Lrc));
+    // 文件名中的 `.` 不会被当作扩展名截断
+    assert_eq!(
+        jobs[0].translation.as_deref(),
+        Some(dir.join("trans/Song ft. X.lrc").as_path())
+    );
+    for job in &jobs {
+        convert(job, &args).unwrap();
+    }
+    assert_eq!(
+        fs::read_to_string(out_dir.join("Song ft. X.lrc")).unwrap(),
+        "[00:01.500]Featuring\n"
+    );
+    let output = fs::read_to_string(out_dir.join("song.lrc")).unwrap();
+    assert_eq!(
+        output.lines().collect::<Vec<_>>(),
+        ["[00:00.500]Hello world", "[00:03.000]Second line"]
+    );
+
+    let args = Args::parse_from([
+        "amll-lyric".as_ref(),
+        "--to".as_ref(),
+        "ttml".as_ref(),
+        "--translation".as_ref(),
+        dir.join("trans/song.lrc").as_os_str(),
+        "--out-dir".as_ref(),
+        out_dir.as_os_str(),
+        dir.join("song.lrc").as_os_str(),
+    ]);
+    let jobs = collect_jobs(&args).unwrap();
+    convert(&jobs[0], &args).unwrap();
+    let output = fs::read(out_dir.join("song.ttml")).unwrap();
+    let lyric = amll_lyric::ttml::parse_ttml(output.as_slice()).unwrap();
+    assert_eq!(lyric.lines.len(), 2);
+    assert_eq!(lyric.lines[0].start_time, 1000);
+    assert_eq!(lyric.lines[0].translated_lyric, "你好世界");
+    assert_eq!(lyric.lines[1].translated_lyric, "");
+
+    // 无法解析的输入会报错而不是输出空文件
+    let args = Args::parse_from([
+        "amll-lyric",
+        "--from",
+        "yrc",
+        "--to",
+        "lrc",
+        "--stdout",
+        "x",
+    ]);
+    let job = Job {
+        input: dir.join("notes.txt"),
+        format: Format::Yrc,
+        translation: None,
+    };
+    assert!(convert(&job, &args).is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,782 @@
+//! 歌词编辑操作
+//!
+//! 为歌词编辑器提供常用的编辑操作，例如拆分和合并单词或歌词行、在歌词行之间移动单词、
+//! 调整单词边界和整体平移时间等。
+//!
+//! 所有操作都通过 [`LyricEditor::apply`] 执行，执行后会自动根据单词时间更新歌词行的
+//! `start_time` 和 `end_time`。每次操作都会记录被修改的歌词行在修改前后的内容，
+//! 因此可以任意撤销和重做。
+
+use std::{fmt::Display, ops::Range};
+
+use crate::{LyricLineOwned, LyricWordOwned};
+
+/// 一个编辑操作
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditOperation {
+    /// 在单词的第 `at` 个字符处将单词拆分为两个单词
+    ///
+    /// `time` 为两个单词的分界时间，为 `None` 时按字符数量比例计算。
+    /// 单词的音译会保留在前一个单词上，注音按开始时间分到所在的单词上，
+    /// 空拍数量则按两个单词的时长比例分配。
+    SplitWord {
+        line: usize,
+        word: usize,
+        at: usize,
+        time: Option<u64>,
+    },
+    /// 将单词和它的后一个单词合并为一个单词
+    ///
+    /// 两个单词的空拍数量相加，任一单词为不雅用语时合并后的单词也是，
+    /// 自定义属性合并到一起，同名的属性以后一个单词的值为准。
+    MergeWords { line: usize, word: usize },
+    /// 从指定单词开始将歌词行拆分为两行
+    ///
+    /// 新歌词行会继承背景和对唱属性，翻译和音译保留在前一行上。
+    SplitLine { line: usize, word: usize },
+    /// 将歌词行和它的后一行合并为一行，翻译和音译以空格连接
+    MergeLines { line: usize },
+    /// 将单词移动到另一个歌词行中，按开始时间插入到合适的位置
+    ///
+    /// 单词全部被移走的歌词行会被删除。
+    MoveWord {
+        line: usize,
+        word: usize,
+        to_line: usize,
+    },
+    /// 将单词的结束时间移动 `delta` 毫秒
+    ///
+    /// 如果后一个单词的开始时间与该单词的结束时间相同，会一并移动，
+    /// 移动范围会被限制在该单词的开始时间和后一个单词的结束时间之间。
+    NudgeWordBoundary {
+        line: usize,
+        word: usize,
+        delta: i64,
+    },
+    /// 设置单词的开始和结束时间
+    RetimeWord {
+        line: usize,
+        word: usize,
+        start_time: u64,
+        end_time: u64,
+    },
+    /// 将范围内所有歌词行和单词的时间平移 `delta` 毫秒，最早不会早于 0
+    ShiftLines { lines: Range<usize>, delta: i64 },
+    /// 切换歌词行是否为背景歌词
+    ToggleBackground { line: usize },
+    /// 切换歌词行是否为对唱歌词
+    ToggleDuet { line: usize },
+    /// 设置歌词行的翻译
+    SetTranslation { line: usize, translation: String },
+}
+
+/// 编辑操作失败的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+    /// 歌词行下标超出范围
+    LineOutOfRange(usize),
+    /// 单词下标超出范围
+    WordOutOfRange { line: usize, word: usize },
+    /// 拆分位置无效
+    InvalidSplitPosition { line: usize, word: usize, at: usize },
+    /// 时间范围无效
+    InvalidTimeRange { start_time: u64, end_time: u64 },
+}
+
+impl Display for EditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LineOutOfRange(line) => write!(f, "line {line} is out of range"),
+            Self::WordOutOfRange { line, word } => {
+                write!(f, "word {word} of line {line} is out of range")
+            }
+            Self::InvalidSplitPosition { line, word, at } => {
+                write!(f, "can't split word {word} of line {line} at {at}")
+            }
+            Self::InvalidTimeRange {
+                start_time,
+                end_time,
+            } => write!(f, "invalid time range {start_time}..{end_time}"),
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+/// 一次编辑对歌词行的修改，记录了修改前后的歌词行用于撤销和重做
+#[derive(Debug, Clone)]
+struct Change {
+    /// 被修改的第一个歌词行的下标
+    index: usize,
+    /// 修改前的歌词行
+    removed: Vec<LyricLineOwned>,
+    /// 修改后的歌词行
+    inserted: Vec<LyricLineOwned>,
+}
+
+impl Change {
+    fn revert(&self, lines: &mut Vec<LyricLineOwned>) {
+        lines.splice(
+            self.index..self.index + self.inserted.len(),
+            self.removed.iter().cloned(),
+        );
+    }
+
+    fn reapply(&self, lines: &mut Vec<LyricLineOwned>) {
+        lines.splice(
+            self.index..self.index + self.removed.len(),
+            self.inserted.iter().cloned(),
+        );
+    }
+}
+
+/// 带有撤销和重做历史的歌词编辑器
+#[derive(Debug, Clone, Default)]
+pub struct LyricEditor {
+    lines: Vec<LyricLineOwned>,
+    undo_stack: Vec<Change>,
+    redo_stack: Vec<Change>,
+}
+
+/// 根据单词时间更新歌词行的时间，没有单词的歌词行保持不变
+fn update_line_time(line: &mut LyricLineOwned) {
+    if let (Some(start_time), Some(end_time)) = (
+        line.words.iter().map(|w| w.start_time).min(),
+        line.words.iter().map(|w| w.end_time).max(),
+    ) {
+        line.start_time = start_time;
+        line.end_time = end_time;
+    }
+}
+
+fn shift_time(time: u64, delta: i64) -> u64 {
+    time.saturating_add_signed(delta)
+}
+
+fn join_text(a: &str, b: &str) -> String {
+    match (a.is_empty(), b.is_empty()) {
+        (_, true) => a.to_owned(),
+        (true, false) => b.to_owned(),
+        (false, false) => format!("{a} {b}"),
+    }
+}
+
+impl LyricEditor {
+    pub fn new(lines: Vec<LyricLineOwned>) -> Self {
+        Self {
+            lines,
+            ..Default::default()
+        }
+    }
+
+    /// 当前的歌词行
+    pub fn lines(&self) -> &[LyricLineOwned] {
+        &self.lines
+    }
+
+    /// 取出编辑后的歌词行
+    pub fn into_lines(self) -> Vec<LyricLineOwned> {
+        self.lines
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// 清空撤销和重做历史
+    pub fn clear_history(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    /// 撤销上一次编辑，没有可撤销的编辑时返回 `false`
+    pub fn undo(&mut self) -> bool {
+        let Some(change) = self.undo_stack.pop() else {
+            return false;
+        };
+        change.revert(&mut self.lines);
+        self.redo_stack.push(change);
+        true
+    }
+
+    /// 重做上一次撤销的编辑，没有可重做的编辑时返回 `false`
+    pub fn redo(&mut self) -> bool {
+        let Some(change) = self.redo_stack.pop() else {
+            return false;
+        };
+        change.reapply(&mut self.lines);
+        self.undo_stack.push(change);
+        true
+    }
+
+    /// 执行一个编辑操作并记录到撤销历史中，同时清空重做历史
+    ///
+    /// 操作失败时歌词不会有任何改变。
+    pub fn apply(&mut self, op: EditOperation) -> Result<(), EditError> {
+        let range = self.affected_lines(&op)?;
+        let mut edited = self.lines[range.clone()].to_vec();
+        Self::edit(&mut edited, range.start, op)?;
+        for line in edited.iter_mut() {
+            update_line_time(line);
+        }
+        let removed = self.lines.splice(range.clone(), edited.clone()).collect();
+        self.undo_stack.push(Change {
+            index: range.start,
+            removed,
+            inserted: edited,
+        });
+        self.redo_stack.clear();
+        Ok(())
+    }
+
+    fn check_line(&self, line: usize) -> Result<&LyricLineOwned, EditError> {
+        self.lines.get(line).ok_or(EditError::LineOutOfRange(line))
+    }
+
+    fn check_word(&self, line: usize, word: usize) -> Result<&LyricWordOwned, EditError> {
+        self.check_line(line)?
+            .words
+            .get(word)
+            .ok_or(EditError::WordOutOfRange { line, word })
+    }
+
+    /// 检查操作的参数并返回会被修改的歌词行范围
+    fn affected_lines(&self, op: &EditOperation) -> Result<Range<usize>, EditError> {
+        match *op {
+            EditOperation::SplitWord { line, word, at, .. } => {
+                let len = self.check_word(line, word)?.word.chars().count();
+                if at == 0 || at >= len {
+                    return Err(EditError::InvalidSplitPosition { line, word, at });
+                }
+                Ok(line..line + 1)
+            }
+            EditOperation::MergeWords { line, word }
+            | EditOperation::NudgeWordBoundary { line, word, .. } => {
+                self.check_word(line, word)?;
+                if matches!(op, EditOperation::MergeWords { .. }) {
+                    self.check_word(line, word + 1)?;
+                }
+                Ok(line..line + 1)
+            }
+            EditOperation::SplitLine { line, word } => {
+                self.check_word(line, word)?;
+                if word == 0 {
+                    return Err(EditError::InvalidSplitPosition { line, word, at: 0 });
+                }
+                Ok(line..line + 1)
+            }
+            EditOperation::MergeLines { line } => {
+                self.check_line(line)?;
+                self.check_line(line + 1)?;
+                Ok(line..line + 2)
+            }
+            EditOperation::MoveWord {
+                line,
+                word,
+                to_line,
+            } => {
+                self.check_word(line, word)?;
+                self.check_line(to_line)?;
+                Ok(line.min(to_line)..line.max(to_line) + 1)
+            }
+            EditOperation::RetimeWord {
+                line,
+                word,
+                start_time,
+                end_time,
+            } => {
+                self.check_word(line, word)?;
+                if start_time > end_time {
+                    return Err(EditError::InvalidTimeRange {
+                        start_time,
+                        end_time,
+                    });
+                }
+                Ok(line..line + 1)
+            }
+            EditOperation::ShiftLines { ref lines, .. } => {
+                if lines.start > lines.end {
+                    return Err(EditError::LineOutOfRange(lines.start));
+                }
+                if lines.end > self.lines.len() {
+                    return Err(EditError::LineOutOfRange(lines.end - 1));
+                }
+                Ok(lines.clone())
+            }
+            EditOperation::ToggleBackground { line }
+            | EditOperation::ToggleDuet { line }
+            | EditOperation::SetTranslation { line, .. } => {
+                self.check_line(line)?;
+                Ok(line..line + 1)
+            }
+        }
+    }
+
+    /// 在已经检查过参数的歌词行副本上执行操作，`offset` 为副本第一行在原歌词中的下标
+    fn edit(
+        lines: &mut Vec<LyricLineOwned>,
+        offset: usize,
+        op: EditOperation,
+    ) -> Result<(), EditError> {
+        match op {
+            EditOperation::SplitWord { word, at, time, .. } => {
+                let words = &mut lines[0].words;
+                let original = &words[word];
+                let len = original.word.chars().count() as u128;
+                // LRC 单词的结束时间可能为 u64::MAX，需要在更宽的类型上计算比例
+                let duration = original.end_time.saturating_sub(original.start_time) as u128;
+                let time = time.unwrap_or_else(|| {
+                    let offset = (duration * at as u128 / len) as u64;
+                    original
+                        .start_time
+                        .saturating_add(offset)
+                        .min(original.end_time)
+                });
+                if time < original.start_time || time > original.end_time {
+                    return Err(EditError::InvalidTimeRange {
+                        start_time: original.start_time,
+                        end_time: time,
+                    });
+                }
+                let split_at = original
+                    .word
+                    .char_indices()
+                    .nth(at)
+                    .map(|(i, _)| i)
+                    .unwrap_or(original.word.len());
+                let first_beats = match duration {
+                    0 => original.empty_beat,
+                    duration => {
+                        let first_duration = (time - original.start_time) as u128;
+                        (original.empty_beat as u128 * first_duration / duration) as u32
+                    }
+                };
+                let (first_ruby, second_ruby) = original
+                    .ruby
+                    .iter()
+                    .cloned()
+                    .partition(|ruby| ruby.start_time < time);
+                let second = LyricWordOwned {
+                    start_time: time,
+                    end_time: original.end_time,
+                    word: original.word[split_at..].to_owned(),
+                    roman_word: String::new(),
+                    obscene: original.obscene,
+                    empty_beat: original.empty_beat - first_beats,
+                    attributes: original.attributes.clone(),
+                    ruby: second_ruby,
+                    agent: original.agent.clone(),
+                };
+                let first = &mut words[word];
+                first.word.truncate(split_at);
+                first.end_time = time;
+                first.empty_beat = first_beats;
+                first.ruby = first_ruby;
+                words.insert(word + 1, second);
+            }
+            EditOperation::MergeWords { word, .. } => {
+                let words = &mut lines[0].words;
+                let next = words.remove(word + 1);
+                let first = &mut words[word];
+                first.start_time = first.start_time.min(next.start_time);
+                first.end_time = first.end_time.max(next.end_time);
+                first.word.push_str(&next.word);
+                first.roman_word = join_text(&first.roman_word, &next.roman_word);
+                first.ruby.extend(next.ruby);
+                first.obscene |= next.obscene;
+                first.empty_beat = first.empty_beat.saturating_add(next.empty_beat);
+                for (key, value) in next.attributes {
+                    match first.attributes.iter_mut().find(|(k, _)| *k == key) {
+                        Some((_, old)) => *old = value,
+                        None => first.attributes.push((key, value)),
+                    }
+                }
+            }
+            EditOperation::SplitLine { word, .. } => {
+                let line = &mut lines[0];
+                let new_line = LyricLineOwned {
+                    words: line.words.split_off(word),
+                    is_bg: line.is_bg,
+                    is_duet: line.is_duet,
+                    ..Default::default()
+                };
+                lines.push(new_line);
+            }
+            EditOperation::MergeLines { .. } => {
+                let next = lines.pop().unwrap_or_default();
+                let line = &mut lines[0];
+                line.words.extend(next.words);
+                line.translated_lyric = join_text(&line.translated_lyric, &next.translated_lyric);
+                line.roman_lyric = join_text(&line.roman_lyric, &next.roman_lyric);
+            }
+            EditOperation::MoveWord {
+                line,
+                word,
+                to_line,
+            } => {
+                let moved = lines[line - offset].words.remove(word);
+                let target = &mut lines[to_line - offset].words;
+                let index = target.partition_point(|w| w.start_time <= moved.start_time);
+                target.insert(index, moved);
+                // 没有单词的歌词行无法根据单词更新时间，直接删除以免留下过时的时间
+                if lines[line - offset].words.is_empty() {
+                    lines.remove(line - offset);
+                }
+            }
+            EditOperation::NudgeWordBoundary { word, delta, .. } => {
+                let words = &mut lines[0].words;
+                let current = &words[word];
+                let next = words.get(word + 1);
+                let linked = next.is_some_and(|x| x.start_time == current.end_time);
+                let max_time = match next {
+                    Some(next) if linked => next.end_time,
+                    _ => u64::MAX,
+                };
+                let time = shift_time(current.end_time, delta).clamp(current.start_time, max_time);
+                words[word].end_time = time;
+                if linked {
+                    words[word + 1].start_time = time;
+                }
+            }
+            EditOperation::RetimeWord {
+                word,
+                start_time,
+                end_time,
+                ..
+            } => {
+                let word = &mut lines[0].words[word];
+                word.start_time = start_time;
+                word.end_time = end_time;
+            }
+            EditOperation::ShiftLines { delta, .. } => {
+                for line in lines.iter_mut() {
+                    line.start_time = shift_time(line.start_time, delta);
+                    line.end_time = shift_time(line.end_time, delta);
+                    for word in line.words.iter_mut() {
+                        word.start_time = shift_time(word.start_time, delta);
+                        word.end_time = shift_time(word.end_time, delta);
+                    }
+                }
+            }
+            EditOperation::ToggleBackground { .. } => lines[0].is_bg = !lines[0].is_bg,
+            EditOperation::ToggleDuet { .. } => lines[0].is_duet = !lines[0].is_duet,
+            EditOperation::SetTranslation { translation, .. } => {
+                lines[0].translated_lyric = translation;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+fn test_line(words: &[(&str, u64, u64)]) -> LyricLineOwned {
+    let mut line = LyricLineOwned {
+        words: words
+            .iter()
+            .map(|&(word, start_time, end_time)| LyricWordOwned {
+                start_time,
+                end_time,
+                word: word.to_owned(),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    };
+    update_line_time(&mut line);
+    line
+}
+
+#[cfg(test)]
+fn line_words(line: &LyricLineOwned) -> Vec<(&str, u64, u64)> {
+    line.words
+        .iter()
+        .map(|w| (w.word.as_str(), w.start_time, w.end_time))
+        .collect()
+}
+
+#[test]
+fn test_split_and_merge_words() {
+    let mut editor = LyricEditor::new(vec![test_line(&[
+        ("hello", 0, 1000),
+        ("world", 1000, 2000),
+    ])]);
+    editor
+        .apply(EditOperation::SplitWord {
+            line: 0,
+            word: 0,
+            at: 3,
+            time: None,
+        })
+        .unwrap();
+    assert_eq!(
+        line_words(&editor.lines()[0]),
+        vec![("hel", 0, 600), ("lo", 600, 1000), ("world", 1000, 2000)]
+    );
+    editor
+        .apply(EditOperation::MergeWords { line: 0, word: 1 })
+        .unwrap();
+    assert_eq!(
+        line_words(&editor.lines()[0]),
+        vec![("hel", 0, 600), ("loworld", 600, 2000)]
+    );
+    assert_eq!(
+        editor.apply(EditOperation::SplitWord {
+            line: 0,
+            word: 0,
+            at: 3,
+            time: None,
+        }),
+        Err(EditError::InvalidSplitPosition {
+            line: 0,
+            word: 0,
+            at: 3
+        })
+    );
+
+    assert!(editor.undo());
+    assert!(editor.undo());
+    assert!(!editor.undo());
+    assert_eq!(
+        line_words(&editor.lines()[0]),
+        vec![("hello", 0, 1000), ("world", 1000, 2000)]
+    );
+    assert!(editor.redo());
+    assert_eq!(editor.lines()[0].words.len(), 3);
+}
+
+#[test]
+fn test_split_word_ruby_and_unbounded_end() {
+    let mut line = test_line(&[("今日は", 1000, 2500), ("line", 3000, u64::MAX)]);
+    let ruby = |word: &str, start_time, end_time| crate::LyricRubyOwned {
+        start_time,
+        end_time,
+        word: word.to_owned(),
+    };
+    line.words[0].ruby = vec![ruby("きょう", 1000, 2000), ruby("は", 2000, 2500)];
+    line.words[0].empty_beat = 3;
+    let mut editor = LyricEditor::new(vec![line]);
+    editor
+        .apply(EditOperation::SplitWord {
+            line: 0,
+            word: 0,
+            at: 2,
+            time: Some(2000),
+        })
+        .unwrap();
+    let words = &editor.lines()[0].words;
+    assert_eq!(words[0].ruby, vec![ruby("きょう", 1000, 2000)]);
+    assert_eq!(words[1].ruby, vec![ruby("は", 2000, 2500)]);
+    assert_eq!((words[0].empty_beat, words[1].empty_beat), (2, 1));
+
+    // LRC 单词的结束时间为 u64::MAX，按比例计算分界时间时不应溢出
+    editor
+        .apply(EditOperation::SplitWord {
+            line: 0,
+            word: 2,
+            at: 2,
+            time: None,
+        })
+        .unwrap();
+    assert_eq!(
+        line_words(&editor.lines()[0])[2..],
+        [
+            ("li", 3000, 3000 + (u64::MAX - 3000) / 2),
+            ("ne", 3000 + (u64::MAX - 3000) / 2, u64::MAX)
+        ]
+    );
+}
+
+#[test]
+fn test_split_and_merge_lines() {
+    let mut line = test_line(&[("a", 0, 100), ("b", 100, 200), ("c", 300, 500)]);
+    line.translated_lyric = "translation".into();
+    line.is_duet = true;
+    let mut editor = LyricEditor::new(vec![line.clone()]);
+
+    editor
+        .apply(EditOperation::SplitLine { line: 0, word: 2 })
+        .unwrap();
+    let lines = editor.lines();
+    assert_eq!(lines.len(), 2);
+    assert_eq!((lines[0].start_time, lines[0].end_time), (0, 200));
+    assert_eq!((lines[1].start_time, lines[1].end_time), (300, 500));
+    assert!(lines[1].is_duet);
+    assert_eq!(lines[1].translated_lyric, "");
+
+    editor
+        .apply(EditOperation::SetTranslation {
+            line: 1,
+            translation: "more".into(),
+        })
+        .unwrap();
+    editor.apply(EditOperation::MergeLines { line: 0 }).unwrap();
+    assert_eq!(editor.lines().len(), 1);
+    assert_eq!(editor.lines()[0].translated_lyric, "translation more");
+    assert_eq!(editor.lines()[0].end_time, 500);
+
+    while editor.undo() {}
+    assert_eq!(editor.lines(), &[line]);
+}
+
+#[test]
+fn test_move_and_retime() {
+    let mut editor = LyricEditor::new(vec![
+        test_line(&[("a", 0, 100), ("b", 100, 200)]),
+        test_line(&[("c", 300, 400)]),
+    ]);
+    editor
+        .apply(EditOperation::MoveWord {
+            line: 0,
+            word: 1,
+            to_line: 1,
+        })
+        .unwrap();
+    assert_eq!(editor.lines()[0].end_time, 100);
+    assert_eq!(
+        line_words(&editor.lines()[1]),
+        vec![("b", 100, 200), ("c", 300, 400)]
+    );
+    assert_eq!(editor.lines()[1].start_time, 100);
+
+    editor
+        .apply(EditOperation::NudgeWordBoundary {
+            line: 1,
+            word: 0,
+            delta: 1000,
+        })
+        .unwrap();
+    assert_eq!(editor.lines()[1].words[0].end_time, 1200);
+    editor.undo();
+
+    editor
+        .apply(EditOperation::ShiftLines {
+            lines: 0..2,
+            delta: -50,
+        })
+        .unwrap();
+    assert_eq!(line_words(&editor.lines()[0]), vec![("a", 0, 50)]);
+    assert_eq!(editor.lines()[1].end_time, 350);
+
+    editor
+        .apply(EditOperation::ToggleBackground { line: 1 })
+        .unwrap();
+    assert!(editor.lines()[1].is_bg);
+    assert_eq!(
+        editor.apply(EditOperation::RetimeWord {
+            line: 1,
+            word: 0,
+            start_time: 10,
+            end_time: 5,
+        }),
+        Err(EditError::InvalidTimeRange {
+            start_time: 10,
+            end_time: 5
+        })
+    );
+    assert_eq!(
+        editor.apply(EditOperation::ToggleDuet { line: 2 }),
+        Err(EditError::LineOutOfRange(2))
+    );
+}
+
+#[test]
+fn test_nudge_linked_boundary() {
+    let mut editor = LyricEditor::new(vec![test_line(&[("a", 0, 100), ("b", 100, 200)])]);
+    editor
+        .apply(EditOperation::NudgeWordBoundary {
+            line: 0,
+            word: 0,
+            delta: 50,
+        })
+        .unwrap();
+    assert_eq!(
+        line_words(&editor.lines()[0]),
+        vec![("a", 0, 150), ("b", 150, 200)]
+    );
+    editor
+        .apply(EditOperation::NudgeWordBoundary {
+            line: 0,
+            word: 0,
+            delta: 500,
+        })
+        .unwrap();
+    assert_eq!(
+        line_words(&editor.lines()[0]),
+        vec![("a", 0, 200), ("b", 200, 200)]
+    );
+}
+
+#[test]
+fn test_merge_word_properties() {
+    let mut line = test_line(&[("a", 0, 100), ("b", 100, 200)]);
+    line.words[0].empty_beat = 1;
+    line.words[0].attributes = vec![("x-style".into(), "whisper".into())];
+    line.words[1].empty_beat = 2;
+    line.words[1].obscene = true;
+    line.words[1].attributes = vec![
+        ("x-style".into(), "shout".into()),
+        ("x-singer".into(), "b".into()),
+    ];
+    let mut editor = LyricEditor::new(vec![line]);
+    editor
+        .apply(EditOperation::MergeWords { line: 0, word: 0 })
+        .unwrap();
+    let word = &editor.lines()[0].words[0];
+    assert_eq!(word.word, "ab");
+    assert_eq!(word.empty_beat, 3);
+    assert!(word.obscene);
+    assert_eq!(
+        word.attributes,
+        vec![
+            ("x-style".to_owned(), "shout".to_owned()),
+            ("x-singer".to_owned(), "b".to_owned())
+        ]
+    );
+}
+
+#[test]
+fn test_move_last_word() {
+    let lines = vec![
+        test_line(&[("a", 0, 100)]),
+        test_line(&[("b", 300, 400)]),
+        test_line(&[("c", 500, 600)]),
+    ];
+    let mut editor = LyricEditor::new(lines.clone());
+    editor
+        .apply(EditOperation::MoveWord {
+            line: 1,
+            word: 0,
+            to_line: 0,
+        })
+        .unwrap();
+    let edited = editor.lines();
+    assert_eq!(edited.len(), 2);
+    assert_eq!(line_words(&edited[0]), vec![("a", 0, 100), ("b", 300, 400)]);
+    assert_eq!((edited[0].start_time, edited[0].end_time), (0, 400));
+    assert_eq!(line_words(&edited[1]), vec![("c", 500, 600)]);
+
+    editor
+        .apply(EditOperation::MoveWord {
+            line: 0,
+            word: 0,
+            to_line: 1,
+        })
+        .unwrap();
+    editor
+        .apply(EditOperation::MoveWord {
+            line: 0,
+            word: 0,
+            to_line: 1,
+        })
+        .unwrap();
+    let edited = editor.lines();
+    assert_eq!(edited.len(), 1);
+    assert_eq!((edited[0].start_time, edited[0].end_time), (0, 600));
+
+    while editor.undo() {}
+    assert_eq!(editor.lines(), &lines[..]);
+}
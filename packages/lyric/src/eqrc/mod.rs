@@ -12,7 +12,7 @@ const DEC_KEY: &[u8; 24] = b"!@#)(*$%123ZXC!@!@#)(NHL";
 static CIPHER: LazyLock<qdec::TripleQDES> = LazyLock::new(|| qdec::TripleQDES::new(DEC_KEY, true));
 
 fn decode_hex(s: &str) -> Vec<u8> {
-    if s.len() % 2 == 0 {
+    if s.len().is_multiple_of(2) {
         (0..s.len())
             .step_by(2)
             .filter_map(|i| s.get(i..i + 2).map(|sub| u8::from_str_radix(sub, 16).ok()))
@@ -42,10 +42,23 @@ pub fn decrypt_qrc_raw(data: &mut [u8]) -> String {
     String::from_utf8_lossy(&decompressed).to_string()
 }
 
+/// 解密十六进制字符串格式的 Qrc 歌词数据
+///
+/// 解密后可去头尾 XML 数据后通过调用 `parseQrc` 解析歌词行
 #[cfg(target_arch = "wasm32")]
-#[wasm_bindgen(js_name = "decryptQrcHex", skip_typescript)]
-pub fn decrypt_qrc_hex_js(hex_data: &str) -> String {
-    decrypt_qrc_hex(hex_data)
+#[wasm_bindgen(
+    js_name = "decryptQrcHex",
+    return_description = "被解密出来的歌词字符串，是前后有 XML 混合的 QRC 歌词"
+)]
+pub fn decrypt_qrc_hex_js(
+    #[wasm_bindgen(param_description = "十六进制格式的字符串，代表被加密的歌词数据")]
+    hex_data: &str,
+) -> Result<String, JsError> {
+    let result = decrypt_qrc_hex(hex_data);
+    if result.is_empty() && !hex_data.trim().is_empty() {
+        return Err(JsError::new("failed to decrypt QRC data"));
+    }
+    Ok(result)
 }
 
 pub fn decrypt_qrc_hex(hex_data: &str) -> String {
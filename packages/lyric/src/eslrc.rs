@@ -26,7 +26,7 @@ pub fn parse_line(src: &str) -> IResult<&str, LyricLine<'_>> {
             start_time,
             end_time,
             word: word.into(),
-            roman_word: std::borrow::Cow::Borrowed(""),
+            ..Default::default()
         });
         src = s;
         start_time = end_time;
@@ -73,15 +73,29 @@ pub fn stringify_eslrc(lines: &[LyricLine]) -> String {
     result
 }
 
+/// 解析 ESLyric 格式的歌词字符串
 #[cfg(all(target_arch = "wasm32", feature = "serde"))]
-#[wasm_bindgen(js_name = "parseEslrc", skip_typescript)]
-pub fn parse_eslrc_js(src: &str) -> wasm_bindgen::JsValue {
-    serde_wasm_bindgen::to_value(&parse_eslrc(src)).unwrap()
+#[wasm_bindgen(
+    js_name = "parseEslrc",
+    unchecked_return_type = "LyricLine[]",
+    return_description = "成功解析出来的歌词"
+)]
+pub fn parse_eslrc_js(
+    #[wasm_bindgen(param_description = "歌词字符串")] src: &str,
+) -> Result<JsValue, JsError> {
+    crate::utils::to_js(&parse_eslrc(src))
 }
 
+/// 将歌词数组转换为 ESLyric 格式的字符串
 #[cfg(all(target_arch = "wasm32", feature = "serde"))]
-#[wasm_bindgen(js_name = "stringifyEslrc", skip_typescript)]
-pub fn stringify_eslrc_js(lrc: wasm_bindgen::JsValue) -> String {
-    let lines: Vec<LyricLine> = serde_wasm_bindgen::from_value(lrc).unwrap();
-    stringify_eslrc(&lines)
+#[wasm_bindgen(
+    js_name = "stringifyEslrc",
+    return_description = "ESLyric 格式的字符串"
+)]
+pub fn stringify_eslrc_js(
+    #[wasm_bindgen(unchecked_param_type = "LyricLine[]", param_description = "歌词数组")]
+    lines: JsValue,
+) -> Result<String, JsError> {
+    let lines: Vec<LyricLine> = crate::utils::from_js(lines)?;
+    Ok(stringify_eslrc(&lines))
 }
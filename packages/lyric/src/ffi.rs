@@ -0,0 +1,519 @@
+//! 供 C/C++ 等原生程序使用的 C ABI 接口
+//!
+//! 对应的头文件位于 `include/amll_lyric.h`，由 cbindgen 生成，修改接口后需要设置
+//! `AMLL_LYRIC_UPDATE_HEADER` 环境变量并启用 `ffi` 功能构建一次以更新头文件。
+//!
+//! 所有接口都会返回一个不透明的 [`AmllLyricResult`] 对象，其中可能包含解析出的歌词行、
+//! 生成的文本或错误信息，使用完毕后必须调用 [`amll_lyric_free`] 释放。
+//! 从结果对象中取得的所有指针（歌词行、单词、字符串）都由结果对象持有，
+//! 在结果对象被释放后即失效，调用方不应自行释放它们。
+//!
+//! 所有字符串均为以 NUL 结尾的 UTF-8 字符串，歌词文本中的 NUL 字符会被移除。
+//!
+//! 接口内部的 panic 只有在 `panic = "unwind"` 时才能被捕获并转换为错误结果，
+//! 工作区的 `release` 配置使用 `panic = "abort"`，发布供原生程序使用的动态库时
+//! 应当使用 `release-ffi` 配置构建，否则 panic 会直接中止宿主进程。
+
+use std::{
+    ffi::{CStr, CString, c_char},
+    panic::{AssertUnwindSafe, catch_unwind},
+    ptr::null,
+};
+
+use crate::{LyricLine, LyricWord};
+
+/// 歌词格式
+///
+/// 接口以 `uint32_t` 接收格式，无法识别的值会返回错误结果。
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmllLyricFormat {
+    /// LyRiC 格式
+    Lrc = 0,
+    /// ESLyric 逐词歌词格式
+    Eslrc = 1,
+    /// 网易云音乐逐词歌词格式
+    Yrc = 2,
+    /// QQ 音乐逐词歌词格式
+    Qrc = 3,
+    /// Lyricify Syllable 逐词歌词格式
+    Lys = 4,
+    /// TTML 歌词格式
+    Ttml = 5,
+    /// ASS 字幕格式，仅支持生成
+    Ass = 6,
+}
+
+impl TryFrom<u32> for AmllLyricFormat {
+    type Error = String;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => Self::Lrc,
+            1 => Self::Eslrc,
+            2 => Self::Yrc,
+            3 => Self::Qrc,
+            4 => Self::Lys,
+            5 => Self::Ttml,
+            6 => Self::Ass,
+            _ => return Err(format!("unknown lyric format {value}")),
+        })
+    }
+}
+
+/// 一个歌词单词
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct AmllLyricWord {
+    pub start_time: u64,
+    pub end_time: u64,
+    /// 单词文本，作为输入时可以为空指针，视为空字符串
+    pub word: *const c_char,
+    /// 单词音译，作为输入时可以为空指针，视为空字符串
+    pub roman_word: *const c_char,
+}
+
+/// 一个歌词行
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct AmllLyricLine {
+    /// 单词数组，长度为 `words_len`
+    pub words: *const AmllLyricWord,
+    pub words_len: usize,
+    /// 翻译文本，作为输入时可以为空指针，视为空字符串
+    pub translated_lyric: *const c_char,
+    /// 音译文本，作为输入时可以为空指针，视为空字符串
+    pub roman_lyric: *const c_char,
+    pub is_bg: bool,
+    pub is_duet: bool,
+    pub start_time: u64,
+    pub end_time: u64,
+}
+
+enum ResultKind {
+    Lines {
+        lines: Vec<AmllLyricLine>,
+        // 以下字段持有 `lines` 中指针指向的数据
+        _words: Vec<AmllLyricWord>,
+        _strings: Vec<CString>,
+    },
+    Text(CString),
+    Error(CString),
+}
+
+/// 接口调用的结果，需要使用 [`amll_lyric_free`] 释放
+pub struct AmllLyricResult(ResultKind);
+
+fn to_cstring(text: &str) -> CString {
+    CString::new(text.replace('\0', "")).unwrap_or_default()
+}
+
+impl AmllLyricResult {
+    fn error(message: impl AsRef<str>) -> *mut Self {
+        Box::into_raw(Box::new(Self(ResultKind::Error(to_cstring(
+            message.as_ref(),
+        )))))
+    }
+
+    fn text(text: &str) -> *mut Self {
+        Box::into_raw(Box::new(Self(ResultKind::Text(to_cstring(text)))))
+    }
+
+    fn lines(lines: &[LyricLine]) -> *mut Self {
+        let mut strings = Vec::new();
+        let mut string = |text: &str| {
+            let text = to_cstring(text);
+            // CString 的数据存放在堆上，移动 CString 不会使指针失效
+            let ptr = text.as_ptr();
+            strings.push(text);
+            ptr
+        };
+
+        let mut words = Vec::with_capacity(lines.iter().map(|x| x.words.len()).sum());
+        let mut line_info = Vec::with_capacity(lines.len());
+        for line in lines {
+            line_info.push((
+                words.len(),
+                string(&line.translated_lyric),
+                string(&line.roman_lyric),
+            ));
+            for word in &line.words {
+                words.push(AmllLyricWord {
+                    start_time: word.start_time,
+                    end_time: word.end_time,
+                    word: string(&word.word),
+                    roman_word: string(&word.roman_word),
+                });
+            }
+        }
+        // 所有单词都放入数组后再取指针，避免数组扩容导致指针失效
+        let lines = lines
+            .iter()
+            .zip(line_info)
+            .map(
+                |(line, (offset, translated_lyric, roman_lyric))| AmllLyricLine {
+                    words: words[offset..].as_ptr(),
+                    words_len: line.words.len(),
+                    translated_lyric,
+                    roman_lyric,
+                    is_bg: line.is_bg,
+                    is_duet: line.is_duet,
+                    start_time: line.start_time,
+                    end_time: line.end_time,
+                },
+            )
+            .collect();
+
+        Box::into_raw(Box::new(Self(ResultKind::Lines {
+            lines,
+            _words: words,
+            _strings: strings,
+        })))
+    }
+}
+
+/// 捕获接口内部的 panic，将其转换为错误结果
+///
+/// 以 `panic = "abort"` 构建时 panic 无法被捕获，参见模块文档
+fn guard(f: impl FnOnce() -> *mut AmllLyricResult) -> *mut AmllLyricResult {
+    catch_unwind(AssertUnwindSafe(f))
+        .unwrap_or_else(|_| AmllLyricResult::error("internal error while processing lyric"))
+}
+
+/// 将调用方传入的字符串转换为 Rust 字符串，空指针视为空字符串
+///
+/// # Safety
+///
+/// `ptr` 必须为空指针或以 NUL 结尾的字符串
+unsafe fn c_str<'a>(ptr: *const c_char) -> Result<&'a str, std::str::Utf8Error> {
+    if ptr.is_null() {
+        Ok("")
+    } else {
+        unsafe { CStr::from_ptr(ptr) }.to_str()
+    }
+}
+
+/// # Safety
+///
+/// `ptr` 必须为空指针或指向至少 `len` 个元素的数组
+unsafe fn slice<'a, T>(ptr: *const T, len: usize) -> Option<&'a [T]> {
+    if len == 0 {
+        Some(&[])
+    } else if ptr.is_null() {
+        None
+    } else {
+        Some(unsafe { std::slice::from_raw_parts(ptr, len) })
+    }
+}
+
+fn parse_lines(format: AmllLyricFormat, src: &str) -> Result<Vec<LyricLine<'_>>, String> {
+    Ok(match format {
+        AmllLyricFormat::Lrc => crate::lrc::parse_lrc(src),
+        AmllLyricFormat::Eslrc => crate::eslrc::parse_eslrc(src),
+        AmllLyricFormat::Yrc => crate::yrc::parse_yrc(src),
+        AmllLyricFormat::Qrc => crate::qrc::parse_qrc(src),
+        AmllLyricFormat::Lys => crate::lys::parse_lys(src),
+        AmllLyricFormat::Ttml => {
+            crate::ttml::parse_ttml(src.as_bytes())
+                .map_err(|err| err.to_string())?
+                .lines
+        }
+        AmllLyricFormat::Ass => return Err("ASS is only supported as an output format".into()),
+    })
+}
+
+fn stringify_lines(format: AmllLyricFormat, lines: &[LyricLine]) -> Result<String, String> {
+    Ok(match format {
+        AmllLyricFormat::Lrc => crate::lrc::stringify_lrc(lines),
+        AmllLyricFormat::Eslrc => crate::eslrc::stringify_eslrc(lines),
+        AmllLyricFormat::Yrc => crate::yrc::stringify_yrc(lines),
+        AmllLyricFormat::Qrc => crate::qrc::stringify_qrc(lines),
+        AmllLyricFormat::Lys => crate::lys::stringify_lys(lines),
+        AmllLyricFormat::Ttml => crate::ttml::stringify_ttml(&crate::ttml::TTMLLyric {
+            lines: lines.to_vec(),
+            metadata: Vec::new(),
+        })
+        .map_err(|err| err.to_string())?,
+        AmllLyricFormat::Ass => crate::ass::stringify_ass(lines),
+    })
+}
+
+/// 解析歌词
+///
+/// `format` 为 [`AmllLyricFormat`] 中的值，
+/// `src` 为长度为 `len` 字节的 UTF-8 文本，不需要以 NUL 结尾。
+/// 成功时可以通过 [`amll_lyric_lines`] 获取歌词行。
+///
+/// # Safety
+///
+/// `src` 必须指向至少 `len` 字节的可读内存
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn amll_lyric_parse(
+    format: u32,
+    src: *const c_char,
+    len: usize,
+) -> *mut AmllLyricResult {
+    guard(|| {
+        let format = match AmllLyricFormat::try_from(format) {
+            Ok(format) => format,
+            Err(err) => return AmllLyricResult::error(err),
+        };
+        let Some(src) = (unsafe { slice(src.cast::<u8>(), len) }) else {
+            return AmllLyricResult::error("source is a null pointer");
+        };
+        let Ok(src) = std::str::from_utf8(src) else {
+            return AmllLyricResult::error("source is not valid UTF-8");
+        };
+        match parse_lines(format, src) {
+            Ok(lines) => AmllLyricResult::lines(&lines),
+            Err(err) => AmllLyricResult::error(err),
+        }
+    })
+}
+
+/// 将歌词行生成为指定格式的歌词文本
+///
+/// `format` 为 [`AmllLyricFormat`] 中的值，成功时可以通过 [`amll_lyric_text`] 获取生成的文本。
+///
+/// # Safety
+///
+/// `lines` 必须指向至少 `len` 个歌词行，每个歌词行中的指针都必须有效
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn amll_lyric_stringify(
+    format: u32,
+    lines: *const AmllLyricLine,
+    len: usize,
+) -> *mut AmllLyricResult {
+    guard(|| {
+        let format = match AmllLyricFormat::try_from(format) {
+            Ok(format) => format,
+            Err(err) => return AmllLyricResult::error(err),
+        };
+        let Some(lines) = (unsafe { slice(lines, len) }) else {
+            return AmllLyricResult::error("lines is a null pointer");
+        };
+        let lines = lines
+            .iter()
+            .map(|line| unsafe {
+                let words = slice(line.words, line.words_len).ok_or("words is a null pointer")?;
+                let words = words
+                    .iter()
+                    .map(|word| {
+                        Ok(LyricWord {
+                            start_time: word.start_time,
+                            end_time: word.end_time,
+                            word: c_str(word.word)?.into(),
+                            roman_word: c_str(word.roman_word)?.into(),
+                            ..Default::default()
+                        })
+                    })
+                    .collect::<Result<Vec<_>, std::str::Utf8Error>>()
+                    .map_err(|_| "text is not valid UTF-8")?;
+                Ok(LyricLine {
+                    words,
+                    translated_lyric: c_str(line.translated_lyric)
+                        .map_err(|_| "text is not valid UTF-8")?
+                        .into(),
+                    roman_lyric: c_str(line.roman_lyric)
+                        .map_err(|_| "text is not valid UTF-8")?
+                        .into(),
+                    is_bg: line.is_bg,
+                    is_duet: line.is_duet,
+                    agent: Default::default(),
+                    start_time: line.start_time,
+                    end_time: line.end_time,
+                })
+            })
+            .collect::<Result<Vec<_>, &str>>();
+        let lines = match lines {
+            Ok(lines) => lines,
+            Err(err) => return AmllLyricResult::error(err),
+        };
+        match stringify_lines(format, &lines) {
+            Ok(text) => AmllLyricResult::text(&text),
+            Err(err) => AmllLyricResult::error(err),
+        }
+    })
+}
+
+/// 解密 EQRC 歌词数据
+///
+/// 如果 `is_hex` 为 `true`，`data` 会被视为十六进制文本，否则视为原始的二进制数据。
+/// 成功时可以通过 [`amll_lyric_text`] 获取解密得到的 QRC 歌词文本。
+///
+/// # Safety
+///
+/// `data` 必须指向至少 `len` 字节的可读内存
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn amll_lyric_decrypt_eqrc(
+    data: *const u8,
+    len: usize,
+    is_hex: bool,
+) -> *mut AmllLyricResult {
+    guard(|| {
+        let Some(data) = (unsafe { slice(data, len) }) else {
+            return AmllLyricResult::error("data is a null pointer");
+        };
+        let text = if is_hex {
+            match std::str::from_utf8(data) {
+                Ok(hex) => crate::eqrc::decrypt_qrc_hex(hex.trim()),
+                Err(_) => return AmllLyricResult::error("hex data is not valid UTF-8"),
+            }
+        } else {
+            crate::eqrc::decrypt_qrc_raw(&mut data.to_vec())
+        };
+        if text.is_empty() {
+            AmllLyricResult::error("failed to decrypt EQRC data")
+        } else {
+            AmllLyricResult::text(&text)
+        }
+    })
+}
+
+/// 结果是否成功，空指针视为失败
+///
+/// # Safety
+///
+/// `result` 必须为空指针或由本库返回且尚未释放的结果对象
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn amll_lyric_is_ok(result: *const AmllLyricResult) -> bool {
+    unsafe { result.as_ref() }.is_some_and(|x| !matches!(x.0, ResultKind::Error(_)))
+}
+
+/// 获取结果中的错误信息，结果成功时返回空指针
+///
+/// # Safety
+///
+/// `result` 必须为空指针或由本库返回且尚未释放的结果对象
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn amll_lyric_error(result: *const AmllLyricResult) -> *const c_char {
+    match unsafe { result.as_ref() } {
+        Some(AmllLyricResult(ResultKind::Error(message))) => message.as_ptr(),
+        _ => null(),
+    }
+}
+
+/// 获取结果中生成或解密得到的文本，结果中没有文本时返回空指针
+///
+/// # Safety
+///
+/// `result` 必须为空指针或由本库返回且尚未释放的结果对象
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn amll_lyric_text(result: *const AmllLyricResult) -> *const c_char {
+    match unsafe { result.as_ref() } {
+        Some(AmllLyricResult(ResultKind::Text(text))) => text.as_ptr(),
+        _ => null(),
+    }
+}
+
+/// 获取结果中解析得到的歌词行数组，数组长度写入 `len`
+///
+/// 结果中没有歌词行时返回空指针，且 `len` 会被设为 0。
+///
+/// # Safety
+///
+/// `result` 必须为空指针或由本库返回且尚未释放的结果对象，`len` 必须为空指针或可写的指针
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn amll_lyric_lines(
+    result: *const AmllLyricResult,
+    len: *mut usize,
+) -> *const AmllLyricLine {
+    let lines = match unsafe { result.as_ref() } {
+        Some(AmllLyricResult(ResultKind::Lines { lines, .. })) => Some(lines),
+        _ => None,
+    };
+    if let Some(len) = unsafe { len.as_mut() } {
+        *len = lines.map_or(0, |x| x.len());
+    }
+    lines.map_or(null(), |x| x.as_ptr())
+}
+
+/// 释放结果对象，传入空指针时不做任何事
+///
+/// # Safety
+///
+/// `result` 必须为空指针或由本库返回且尚未释放的结果对象
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn amll_lyric_free(result: *mut AmllLyricResult) {
+    if !result.is_null() {
+        drop(unsafe { Box::from_raw(result) });
+    }
+}
+
+#[test]
+fn test_ffi_parse_and_stringify() {
+    let src = "[0,1000]Hello (0,500)world(500,500)\n";
+    unsafe {
+        let result = amll_lyric_parse(AmllLyricFormat::Qrc as u32, src.as_ptr().cast(), src.len());
+        assert!(amll_lyric_is_ok(result));
+        assert!(amll_lyric_error(result).is_null());
+
+        let mut len = 0;
+        let lines = amll_lyric_lines(result, &mut len);
+        assert_eq!(len, 1);
+        let line = &*lines;
+        assert_eq!(line.words_len, 2);
+        let words = std::slice::from_raw_parts(line.words, line.words_len);
+        assert_eq!(CStr::from_ptr(words[1].word).to_str(), Ok("world"));
+        assert_eq!(words[1].start_time, 500);
+
+        let output = amll_lyric_stringify(AmllLyricFormat::Lrc as u32, lines, len);
+        assert!(amll_lyric_is_ok(output));
+        assert_eq!(
+            CStr::from_ptr(amll_lyric_text(output)).to_str(),
+            Ok("[00:00.000]Hello world\n")
+        );
+        amll_lyric_free(output);
+        amll_lyric_free(result);
+    }
+}
+
+#[test]
+fn test_ffi_errors() {
+    // 结束标签与开始标签不匹配的 TTML 歌词
+    let src = "<tt><body><div><p begin=\"00:01.000\" end=\"00:02.000\">Hello</div></p></body></tt>";
+    unsafe {
+        let result = amll_lyric_parse(AmllLyricFormat::Ttml as u32, src.as_ptr().cast(), src.len());
+        assert!(!amll_lyric_is_ok(result));
+        assert!(!amll_lyric_error(result).is_null());
+        let mut len = 1;
+        assert!(amll_lyric_lines(result, &mut len).is_null());
+        assert_eq!(len, 0);
+        amll_lyric_free(result);
+
+        // ASS 只支持生成
+        let src = "[Script Info]";
+        let result = amll_lyric_parse(AmllLyricFormat::Ass as u32, src.as_ptr().cast(), src.len());
+        assert!(!amll_lyric_is_ok(result));
+        amll_lyric_free(result);
+
+        let src = b"[00:01.00]\xff\xfe";
+        let result = amll_lyric_parse(AmllLyricFormat::Lrc as u32, src.as_ptr().cast(), src.len());
+        assert!(!amll_lyric_is_ok(result));
+        amll_lyric_free(result);
+
+        let result = amll_lyric_parse(AmllLyricFormat::Lrc as u32, null(), 10);
+        assert!(!amll_lyric_is_ok(result));
+        amll_lyric_free(result);
+
+        // 无法识别的格式
+        let src = "[00:01.00]Hello";
+        let result = amll_lyric_parse(100, src.as_ptr().cast(), src.len());
+        assert!(!amll_lyric_is_ok(result));
+        assert_eq!(
+            CStr::from_ptr(amll_lyric_error(result)).to_str(),
+            Ok("unknown lyric format 100")
+        );
+        amll_lyric_free(result);
+        let result = amll_lyric_stringify(u32::MAX, null(), 0);
+        assert!(!amll_lyric_is_ok(result));
+        amll_lyric_free(result);
+
+        let result = amll_lyric_decrypt_eqrc(b"00".as_ptr(), 2, true);
+        assert!(!amll_lyric_is_ok(result));
+        amll_lyric_free(result);
+
+        assert!(!amll_lyric_is_ok(null()));
+        amll_lyric_free(std::ptr::null_mut());
+    }
+}
@@ -0,0 +1,391 @@
+//! 间奏检测
+//!
+//! AMLL 会在较长的无歌词时段显示间奏动画，此模块统一计算普通歌词行之间的空白时段，
+//! 并可以将超过阈值的空白时段作为间奏标记行插入歌词中。
+//!
+//! 空白的歌词行（例如 LRC 中只有时间戳的行）本身就是间奏的标记，
+//! 因此计算时会被忽略；LRC 解析器将歌词行的结束时间设为下一行的开始时间，
+//! 所以紧跟在空白行后面的空白时段也能被正确识别。
+//!
+//! 此外还提供了对“纯音乐，请欣赏”一类占位歌词的识别。
+
+use crate::{LyricLine, utils::MAX_TIME};
+
+/// 间奏检测的选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterludeOptions {
+    /// 空白时段至少持续多少毫秒才会被视为间奏，默认为 4000 毫秒
+    pub min_duration: u64,
+    /// 是否检测第一行歌词之前的前奏，默认为 `true`
+    pub include_intro: bool,
+    /// 歌曲的总时长，提供后会检测最后一行歌词之后的尾奏
+    pub duration: Option<u64>,
+}
+
+impl Default for InterludeOptions {
+    fn default() -> Self {
+        Self {
+            min_duration: 4000,
+            include_intro: true,
+            duration: None,
+        }
+    }
+}
+
+/// 一个间奏时段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interlude {
+    /// 间奏的开始时间
+    pub start_time: u64,
+    /// 间奏的结束时间
+    pub end_time: u64,
+    /// 间奏之前最后结束的歌词行在原歌词数组中的下标，前奏为 `None`
+    pub after_line: Option<usize>,
+    /// 间奏之后第一个开始的歌词行在原歌词数组中的下标，尾奏为 `None`
+    pub before_line: Option<usize>,
+}
+
+impl Interlude {
+    /// 间奏的持续时间
+    pub fn duration(&self) -> u64 {
+        self.end_time - self.start_time
+    }
+}
+
+/// 常见的纯音乐占位歌词，比较时忽略大小写、空白和标点
+const INSTRUMENTAL_PLACEHOLDERS: &[&str] = &[
+    "纯音乐",
+    "純音樂",
+    "纯音乐请欣赏",
+    "純音樂請欣賞",
+    "此歌曲为没有填词的纯音乐请您欣赏",
+    "instrumental",
+    "pureinstrumental",
+    "puremusic",
+    "インストゥルメンタル",
+];
+
+/// 该歌词行是否有可显示的文字
+fn has_text(line: &LyricLine<'_>) -> bool {
+    line.words.iter().any(|w| !w.word.trim().is_empty())
+}
+
+/// 歌词行的实际结束时间
+///
+/// 兼容结束时间未设置或被设置为无穷大的歌词行，例如 LRC 歌词的最后一行，
+/// 无穷大的结束时间会被替换为 `fallback`，即下一行的开始时间或歌曲的总时长。
+/// 返回值不会早于歌词行的开始时间，即使开始时间已经超过了 [`MAX_TIME`]
+fn effective_end_time(line: &LyricLine<'_>, fallback: Option<u64>) -> u64 {
+    let word_end = line.words.iter().map(|w| w.end_time).max().unwrap_or(0);
+    let end = if line.end_time > line.start_time {
+        line.end_time
+    } else {
+        word_end
+    };
+    let end = match fallback {
+        Some(fallback) if end >= MAX_TIME => fallback,
+        _ => end,
+    };
+    end.min(MAX_TIME).max(line.start_time)
+}
+
+/// 该歌词行是否是纯音乐的占位歌词
+///
+/// 例如“纯音乐，请欣赏”、“此歌曲为没有填词的纯音乐，请您欣赏”或 “Instrumental”
+pub fn is_instrumental_placeholder(line: &LyricLine<'_>) -> bool {
+    let text: String = line
+        .words
+        .iter()
+        .flat_map(|w| w.word.chars())
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect();
+    !text.is_empty()
+        && INSTRUMENTAL_PLACEHOLDERS
+            .iter()
+            .any(|placeholder| text == *placeholder)
+}
+
+/// 该歌词是否为纯音乐
+///
+/// 存在有文字的普通歌词行，且它们都是纯音乐占位歌词时返回 `true`。
+///
+/// 只有一行且只有一个单词的歌词不会仅因此被视为纯音乐，
+/// 因为只有一行的 LRC 歌词同样只有一个单词。
+pub fn is_instrumental(lines: &[LyricLine<'_>]) -> bool {
+    let mut lines = lines.iter().filter(|x| !x.is_bg && has_text(x)).peekable();
+    lines.peek().is_some() && lines.all(is_instrumental_placeholder)
+}
+
+/// 计算歌词中所有超过阈值的间奏时段，按时间顺序返回
+///
+/// 只有有文字的普通歌词行参与计算，背景歌词行和空白歌词行都会被忽略。
+/// 纯音乐歌词不会返回任何间奏。
+pub fn find_interludes(lines: &[LyricLine<'_>], options: &InterludeOptions) -> Vec<Interlude> {
+    if is_instrumental(lines) {
+        return Vec::new();
+    }
+
+    let mut foreground: Vec<usize> = (0..lines.len())
+        .filter(|&i| !lines[i].is_bg && has_text(&lines[i]))
+        .collect();
+    foreground.sort_by_key(|&i| lines[i].start_time);
+    // 空白歌词行也是间奏的边界，用于确定结束时间无穷大的歌词行实际在何时结束
+    let mut starts: Vec<u64> = lines
+        .iter()
+        .filter(|x| !x.is_bg)
+        .map(|x| x.start_time)
+        .collect();
+    starts.sort_unstable();
+
+    let min_duration = options.min_duration.max(1);
+    let mut result = Vec::new();
+    // 已经处理过的歌词行中最晚的结束时间和对应的歌词行
+    let mut last: Option<(u64, usize)> = None;
+    for &i in &foreground {
+        let start_time = lines[i].start_time;
+        let gap_start = match last {
+            Some((end, _)) => end,
+            None if options.include_intro => 0,
+            None => start_time,
+        };
+        if start_time >= gap_start.saturating_add(min_duration) {
+            result.push(Interlude {
+                start_time: gap_start,
+                end_time: start_time,
+                after_line: last.map(|(_, line)| line),
+                before_line: Some(i),
+            });
+        }
+        let next_start = starts.get(starts.partition_point(|&x| x <= start_time));
+        let end_time = effective_end_time(&lines[i], next_start.copied().or(options.duration));
+        if last.is_none_or(|(end, _)| end_time >= end) {
+            last = Some((end_time, i));
+        }
+    }
+
+    if let (Some(duration), Some((end, line))) = (options.duration, last)
+        && duration >= end.saturating_add(min_duration)
+    {
+        result.push(Interlude {
+            start_time: end,
+            end_time: duration,
+            after_line: Some(line),
+            before_line: None,
+        });
+    }
+    result
+}
+
+/// 将检测到的间奏作为标记行插入歌词中，返回插入的标记行数量
+///
+/// 标记行是一个没有任何单词的普通歌词行，开始和结束时间即为间奏的时间范围，
+/// 插入位置在间奏之后的第一个歌词行之前（尾奏则在末尾）。
+/// 以 LRC 格式输出时，标记行会被写成只有时间戳的空行。
+pub fn insert_interlude_markers(
+    lines: &mut Vec<LyricLine<'_>>,
+    options: &InterludeOptions,
+) -> usize {
+    let interludes = find_interludes(lines, options);
+    let mut markers: Vec<(usize, LyricLine<'_>)> = interludes
+        .iter()
+        .map(|interlude| {
+            (
+                interlude.before_line.unwrap_or(lines.len()),
+                LyricLine {
+                    start_time: interlude.start_time,
+                    end_time: interlude.end_time,
+                    ..Default::default()
+                },
+            )
+        })
+        .collect();
+    // 从后往前插入，这样前面的下标不会失效
+    markers.sort_by_key(|(index, _)| *index);
+    let count = markers.len();
+    for (index, marker) in markers.into_iter().rev() {
+        lines.insert(index, marker);
+    }
+    count
+}
+
+#[cfg(test)]
+fn test_line(text: &'static str, start_time: u64, end_time: u64) -> LyricLine<'static> {
+    use crate::LyricWord;
+    use std::borrow::Cow;
+
+    LyricLine {
+        words: vec![LyricWord {
+            start_time,
+            end_time,
+            word: Cow::Borrowed(text),
+            ..Default::default()
+        }],
+        start_time,
+        end_time,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_find_interludes() {
+    let mut bg = test_line("(ooh)", 3000, 12000);
+    bg.is_bg = true;
+    let lines = vec![
+        test_line("first", 5000, 8000),
+        bg,
+        test_line("second", 9000, 10000),
+        test_line("third", 20000, 22000),
+    ];
+    let interludes = find_interludes(&lines, &InterludeOptions::default());
+    assert_eq!(
+        interludes,
+        vec![
+            Interlude {
+                start_time: 0,
+                end_time: 5000,
+                after_line: None,
+                before_line: Some(0),
+            },
+            Interlude {
+                start_time: 10000,
+                end_time: 20000,
+                after_line: Some(2),
+                before_line: Some(3),
+            },
+        ]
+    );
+
+    let options = InterludeOptions {
+        include_intro: false,
+        duration: Some(30000),
+        ..Default::default()
+    };
+    let interludes = find_interludes(&lines, &options);
+    assert_eq!(interludes.len(), 2);
+    assert_eq!(interludes[1].start_time, 22000);
+    assert_eq!(interludes[1].before_line, None);
+}
+
+#[test]
+#[cfg(feature = "lrc")]
+fn test_find_interludes_lrc() {
+    let lines = crate::lrc::parse_lrc(
+        "[00:01.00]line one\n[00:03.00]\n[00:15.00]line two\n[00:17.00]line three\n",
+    );
+    let interludes = find_interludes(&lines, &InterludeOptions::default());
+    assert_eq!(interludes.len(), 1);
+    assert_eq!(interludes[0].start_time, 3000);
+    assert_eq!(interludes[0].end_time, 15000);
+}
+
+#[test]
+fn test_find_interludes_unbounded_end() {
+    // 结束时间无穷大的歌词行在下一行开始时结束，最后一行则在歌曲结束时结束
+    let lines = vec![
+        test_line("first", 1000, u64::MAX),
+        LyricLine {
+            start_time: 3000,
+            ..Default::default()
+        },
+        test_line("second", 10000, 12000),
+        test_line("third", 13000, u64::MAX),
+    ];
+    let options = InterludeOptions {
+        duration: Some(30000),
+        ..Default::default()
+    };
+    let interludes = find_interludes(&lines, &options);
+    assert_eq!(interludes.len(), 1);
+    assert_eq!(
+        (interludes[0].start_time, interludes[0].end_time),
+        (3000, 10000)
+    );
+    assert_eq!(interludes[0].after_line, Some(0));
+
+    // LRC 歌词以空白行标记最后一行的结束，之后的尾奏也能被识别
+    #[cfg(feature = "lrc")]
+    {
+        let lines = crate::lrc::parse_lrc("[00:01.00]line one\n[00:03.00]line two\n[00:05.00]\n");
+        let interludes = find_interludes(&lines, &options);
+        assert_eq!(interludes.len(), 1);
+        assert_eq!(interludes[0].start_time, 5000);
+        assert_eq!(interludes[0].end_time, 30000);
+    }
+
+    let options = InterludeOptions {
+        min_duration: u64::MAX,
+        duration: Some(30000),
+        ..Default::default()
+    };
+    assert!(find_interludes(&lines, &options).is_empty());
+}
+
+#[test]
+fn test_find_interludes_after_max_time() {
+    let lines = vec![
+        test_line("first", 1000, 2000),
+        test_line("late", MAX_TIME + 1000, MAX_TIME + 2000),
+        test_line("later", MAX_TIME + 3000, u64::MAX),
+    ];
+    let interludes = find_interludes(&lines, &InterludeOptions::default());
+    assert_eq!(interludes.len(), 1);
+    assert_eq!(
+        (interludes[0].start_time, interludes[0].end_time),
+        (2000, MAX_TIME + 1000)
+    );
+}
+
+#[test]
+fn test_insert_interlude_markers() {
+    let mut lines = vec![
+        test_line("first", 1000, 2000),
+        test_line("second", 10000, 12000),
+    ];
+    let count = insert_interlude_markers(&mut lines, &InterludeOptions::default());
+    assert_eq!(count, 1);
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].words.is_empty());
+    assert_eq!((lines[1].start_time, lines[1].end_time), (2000, 10000));
+    assert_eq!(lines[2].words[0].word, "second");
+}
+
+#[test]
+fn test_instrumental() {
+    assert!(is_instrumental_placeholder(&test_line(
+        "纯音乐，请欣赏",
+        0,
+        1000
+    )));
+    assert!(is_instrumental_placeholder(&test_line(
+        "此歌曲为没有填词的纯音乐，请您欣赏",
+        0,
+        1000
+    )));
+    assert!(is_instrumental_placeholder(&test_line(
+        "[Instrumental]",
+        0,
+        1000
+    )));
+    assert!(!is_instrumental_placeholder(&test_line(
+        "纯音乐不是这首歌",
+        0,
+        1000
+    )));
+
+    let lines = vec![test_line("纯音乐，请欣赏", 0, 1000)];
+    assert!(is_instrumental(&lines));
+    assert!(find_interludes(&lines, &InterludeOptions::default()).is_empty());
+    assert!(!is_instrumental(&[
+        test_line("hello", 0, 1000),
+        test_line("world", 1000, 2000)
+    ]));
+
+    // 只有一行普通歌词的 LRC 歌词仍然会检测前奏
+    let lines = vec![test_line("hello world", 10000, u64::MAX)];
+    assert!(!is_instrumental(&lines));
+    assert_eq!(
+        find_interludes(&lines, &InterludeOptions::default()).len(),
+        1
+    );
+}
@@ -0,0 +1,585 @@
+//! AMLL JSON 歌词文档格式
+//!
+//! 这是一个带有版本号的 JSON 歌词交换格式，用于在 Rust 模块、WASM 绑定和其他工具之间
+//! 传递完整的歌词信息（歌词行、元数据和演唱者），所有时间均为以毫秒为单位的非负整数。
+//!
+//! 一个版本 1 的文档示例如下：
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "metadata": [{ "key": "musicName", "values": ["Song"] }],
+//!   "agents": [{ "id": "v1", "type": "person" }],
+//!   "lines": [
+//!     {
+//!       "startTime": 0,
+//!       "endTime": 1000,
+//!       "words": [{ "startTime": 0, "endTime": 1000, "word": "Hello" }],
+//!       "translatedLyric": "你好",
+//!       "agent": "v1"
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! 完整的结构定义可以通过 [`json_schema`] 生成，仓库中的
+//! `schema/lyric-document.schema.json` 即由此生成。
+//!
+//! [`parse_json`] 也能读取引入版本号之前的旧格式，即直接序列化的 `LyricLine` 数组或
+//! `TTMLLyric` 对象（时间可以是浮点数，`null` 视为无穷大），并自动迁移为当前版本。
+
+use std::fmt::Display;
+
+use schemars::JsonSchema;
+use serde::*;
+
+use crate::{LyricLine, LyricLineOwned, LyricRubyOwned, LyricWordOwned, utils::MAX_TIME};
+
+/// 当前的文档格式版本
+pub const CURRENT_VERSION: u32 = 1;
+
+/// 一个 AMLL JSON 歌词文档
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(title = "AMLL Lyric Document")]
+pub struct LyricDocument {
+    /// 文档格式版本，当前为 1
+    pub version: u32,
+    /// 元数据，同一个键可以有多个值
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub metadata: Vec<MetadataEntry>,
+    /// 演唱者列表
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub agents: Vec<Agent>,
+    /// 所有歌词行
+    pub lines: Vec<DocumentLine>,
+}
+
+/// 一个元数据条目
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct MetadataEntry {
+    pub key: String,
+    pub values: Vec<String>,
+}
+
+/// 演唱者的类型，与 TTML 中 `ttm:agent` 的 `type` 属性一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentKind {
+    #[default]
+    Person,
+    Character,
+    Group,
+    Organization,
+    Other,
+}
+
+/// 一个演唱者
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Agent {
+    /// 演唱者的唯一标识，歌词行通过此标识引用演唱者
+    pub id: String,
+    #[serde(default, rename = "type")]
+    pub kind: AgentKind,
+    /// 演唱者的名称
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// 一个歌词单词
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentWord {
+    pub start_time: u64,
+    pub end_time: u64,
+    pub word: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub roman_word: String,
+    /// 是否为不雅用语
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub obscene: bool,
+    /// 空拍数量
+    #[serde(default, skip_serializing_if = "crate::utils::is_zero")]
+    pub empty_beat: u32,
+    /// 其他自定义的单词属性
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<WordAttribute>,
+    /// 单词的注音片段
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ruby: Vec<DocumentRuby>,
+    /// 演唱该单词的演唱者标识，和所在歌词行相同时省略
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+}
+
+/// 单词注音的一个片段
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentRuby {
+    pub start_time: u64,
+    pub end_time: u64,
+    pub word: String,
+}
+
+/// 一个自定义的单词属性
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct WordAttribute {
+    pub key: String,
+    pub value: String,
+}
+
+/// 一个歌词行
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentLine {
+    pub start_time: u64,
+    pub end_time: u64,
+    pub words: Vec<DocumentWord>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub translated_lyric: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub roman_lyric: String,
+    /// 是否为背景歌词行
+    #[serde(default, rename = "isBG", skip_serializing_if = "std::ops::Not::not")]
+    pub is_bg: bool,
+    /// 是否为对唱歌词行（即歌词行靠右对齐）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_duet: bool,
+    /// 演唱该行的演唱者标识，对应 `agents` 中的 `id`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+}
+
+/// 解析 JSON 歌词文档时发生的错误
+#[derive(Debug)]
+pub enum JsonError {
+    /// JSON 语法错误或结构不符合格式定义
+    Invalid(serde_json::Error),
+    /// 文档的版本比当前支持的版本更新
+    UnsupportedVersion(u64),
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Invalid(err) => write!(f, "invalid lyric document: {err}"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported lyric document version {version}, the latest supported version is {CURRENT_VERSION}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Invalid(err) => Some(err),
+            Self::UnsupportedVersion(_) => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for JsonError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Invalid(err)
+    }
+}
+
+impl From<&LyricLine<'_>> for DocumentLine {
+    fn from(line: &LyricLine<'_>) -> Self {
+        Self {
+            start_time: line.start_time,
+            end_time: line.end_time,
+            words: line
+                .words
+                .iter()
+                .map(|word| DocumentWord {
+                    start_time: word.start_time,
+                    end_time: word.end_time,
+                    word: word.word.to_string(),
+                    roman_word: word.roman_word.to_string(),
+                    obscene: word.obscene,
+                    empty_beat: word.empty_beat,
+                    attributes: word
+                        .attributes
+                        .iter()
+                        .map(|(key, value)| WordAttribute {
+                            key: key.to_string(),
+                            value: value.to_string(),
+                        })
+                        .collect(),
+                    ruby: word
+                        .ruby
+                        .iter()
+                        .map(|ruby| DocumentRuby {
+                            start_time: ruby.start_time,
+                            end_time: ruby.end_time,
+                            word: ruby.word.to_string(),
+                        })
+                        .collect(),
+                    agent: (!word.agent.is_empty()).then(|| word.agent.to_string()),
+                })
+                .collect(),
+            translated_lyric: line.translated_lyric.to_string(),
+            roman_lyric: line.roman_lyric.to_string(),
+            is_bg: line.is_bg,
+            is_duet: line.is_duet,
+            agent: Some(line.agent().to_owned()),
+        }
+    }
+}
+
+impl From<&DocumentLine> for LyricLineOwned {
+    fn from(line: &DocumentLine) -> Self {
+        Self {
+            words: line
+                .words
+                .iter()
+                .map(|word| LyricWordOwned {
+                    start_time: word.start_time,
+                    end_time: word.end_time,
+                    word: word.word.clone(),
+                    roman_word: word.roman_word.clone(),
+                    obscene: word.obscene,
+                    empty_beat: word.empty_beat,
+                    attributes: word
+                        .attributes
+                        .iter()
+                        .map(|x| (x.key.clone(), x.value.clone()))
+                        .collect(),
+                    ruby: word
+                        .ruby
+                        .iter()
+                        .map(|ruby| LyricRubyOwned {
+                            start_time: ruby.start_time,
+                            end_time: ruby.end_time,
+                            word: ruby.word.clone(),
+                        })
+                        .collect(),
+                    agent: word.agent.clone().unwrap_or_default(),
+                })
+                .collect(),
+            translated_lyric: line.translated_lyric.clone(),
+            roman_lyric: line.roman_lyric.clone(),
+            is_bg: line.is_bg,
+            is_duet: line.is_duet,
+            // 默认的演唱者不需要记录在歌词行上
+            agent: line
+                .agent
+                .clone()
+                .filter(|x| x != crate::line_agent("", line.is_duet))
+                .unwrap_or_default(),
+            start_time: line.start_time,
+            end_time: line.end_time,
+        }
+    }
+}
+
+impl LyricDocument {
+    /// 从歌词行和元数据创建文档
+    ///
+    /// 演唱者会根据歌词行是否为对唱生成，普通歌词行为 `v1`，对唱歌词行为 `v2`，
+    /// 与 TTML 导出时的规则一致，歌词行和单词上引用的其他演唱者也会被加入演唱者列表。
+    pub fn from_lines(lines: &[LyricLine<'_>], metadata: Vec<MetadataEntry>) -> Self {
+        let mut agents = vec![Agent {
+            id: "v1".into(),
+            kind: AgentKind::Person,
+            name: None,
+        }];
+        if lines.iter().any(|x| x.is_duet) {
+            agents.push(Agent {
+                id: "v2".into(),
+                kind: AgentKind::Person,
+                name: None,
+            });
+        }
+        let line_agents = lines.iter().map(|x| x.agent.as_ref());
+        let word_agents = lines
+            .iter()
+            .flat_map(|x| x.words.iter().map(|w| w.agent.as_ref()));
+        for agent in line_agents.chain(word_agents) {
+            if !agent.is_empty() && agents.iter().all(|x| x.id != agent) {
+                agents.push(Agent {
+                    id: agent.to_string(),
+                    kind: AgentKind::Other,
+                    name: None,
+                });
+            }
+        }
+        Self {
+            version: CURRENT_VERSION,
+            metadata,
+            agents,
+            lines: lines.iter().map(DocumentLine::from).collect(),
+        }
+    }
+
+    /// 将文档中的歌词行转换为所有权歌词行
+    pub fn to_lines(&self) -> Vec<LyricLineOwned> {
+        self.lines.iter().map(LyricLineOwned::from).collect()
+    }
+}
+
+#[cfg(feature = "ttml")]
+impl From<&crate::ttml::TTMLLyric<'_>> for LyricDocument {
+    fn from(lyric: &crate::ttml::TTMLLyric<'_>) -> Self {
+        Self::from_lines(
+            &lyric.lines,
+            lyric
+                .metadata
+                .iter()
+                .map(|(key, values)| MetadataEntry {
+                    key: key.to_string(),
+                    values: values.iter().map(|x| x.to_string()).collect(),
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(feature = "ttml")]
+impl From<&LyricDocument> for crate::ttml::TTMLLyricOwned {
+    fn from(document: &LyricDocument) -> Self {
+        Self {
+            lines: document.to_lines(),
+            metadata: document
+                .metadata
+                .iter()
+                .map(|x| (x.key.clone(), x.values.clone()))
+                .collect(),
+        }
+    }
+}
+
+/// 旧格式中的时间，可能是浮点数，`null` 为 JavaScript 中的 `Infinity` 序列化后的结果
+fn legacy_time<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    Ok(match Option::<f64>::deserialize(deserializer)? {
+        Some(time) if time.is_finite() => time.round().clamp(0.0, MAX_TIME as f64) as u64,
+        Some(time) if time < 0.0 => 0,
+        _ => MAX_TIME,
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LegacyWord {
+    #[serde(default, deserialize_with = "legacy_time")]
+    start_time: u64,
+    #[serde(default, deserialize_with = "legacy_time")]
+    end_time: u64,
+    #[serde(default)]
+    word: String,
+    #[serde(default)]
+    roman_word: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LegacyLine {
+    #[serde(default)]
+    words: Vec<LegacyWord>,
+    #[serde(default)]
+    translated_lyric: String,
+    #[serde(default)]
+    roman_lyric: String,
+    #[serde(default, rename = "isBG", alias = "isBg")]
+    is_bg: bool,
+    #[serde(default)]
+    is_duet: bool,
+    #[serde(default, deserialize_with = "legacy_time")]
+    start_time: u64,
+    #[serde(default, deserialize_with = "legacy_time")]
+    end_time: u64,
+}
+
+#[derive(Deserialize)]
+struct LegacyTTMLLyric {
+    lines: Vec<LegacyLine>,
+    #[serde(default)]
+    metadata: Vec<(String, Vec<String>)>,
+}
+
+/// 将旧格式迁移为当前版本的文档
+fn migrate_legacy(lines: Vec<LegacyLine>, metadata: Vec<(String, Vec<String>)>) -> LyricDocument {
+    let lines: Vec<LyricLine> = lines
+        .into_iter()
+        .map(|line| LyricLine {
+            words: line
+                .words
+                .into_iter()
+                .map(|word| crate::LyricWord {
+                    start_time: word.start_time,
+                    end_time: word.end_time,
+                    word: word.word.into(),
+                    roman_word: word.roman_word.into(),
+                    ..Default::default()
+                })
+                .collect(),
+            translated_lyric: line.translated_lyric.into(),
+            roman_lyric: line.roman_lyric.into(),
+            is_bg: line.is_bg,
+            is_duet: line.is_duet,
+            agent: Default::default(),
+            start_time: line.start_time,
+            end_time: line.end_time,
+        })
+        .collect();
+    LyricDocument::from_lines(
+        &lines,
+        metadata
+            .into_iter()
+            .map(|(key, values)| MetadataEntry { key, values })
+            .collect(),
+    )
+}
+
+/// 解析 JSON 歌词文档
+///
+/// 没有版本号的旧格式会被自动迁移为当前版本，版本号比 [`CURRENT_VERSION`] 更新的文档会返回错误。
+pub fn parse_json(src: &str) -> Result<LyricDocument, JsonError> {
+    let value: serde_json::Value = serde_json::from_str(src)?;
+    if value.is_array() {
+        return Ok(migrate_legacy(serde_json::from_value(value)?, Vec::new()));
+    }
+    match value.get("version") {
+        None => {
+            let legacy: LegacyTTMLLyric = serde_json::from_value(value)?;
+            Ok(migrate_legacy(legacy.lines, legacy.metadata))
+        }
+        Some(version) => match version.as_u64() {
+            // 版本号从 1 开始，0 不是任何版本的文档
+            Some(version) if version == 0 || version > CURRENT_VERSION as u64 => {
+                Err(JsonError::UnsupportedVersion(version))
+            }
+            _ => Ok(serde_json::from_value(value)?),
+        },
+    }
+}
+
+/// 将歌词文档序列化为 JSON 字符串
+pub fn stringify_json(document: &LyricDocument) -> String {
+    serde_json::to_string(document).expect("lyric document is always serializable")
+}
+
+/// 生成歌词文档格式的 JSON Schema
+pub fn json_schema() -> String {
+    let schema = schemars::schema_for!(LyricDocument);
+    serde_json::to_string_pretty(&schema).expect("json schema is always serializable")
+}
+
+#[test]
+fn test_json_roundtrip() {
+    let lines = vec![
+        LyricLine {
+            words: vec![crate::LyricWord {
+                start_time: 0,
+                end_time: 1000,
+                word: "Hello".into(),
+                ruby: vec![crate::LyricRuby {
+                    start_time: 0,
+                    end_time: 1000,
+                    word: "ハロー".into(),
+                }],
+                ..Default::default()
+            }],
+            translated_lyric: "你好".into(),
+            start_time: 0,
+            end_time: 1000,
+            ..Default::default()
+        },
+        LyricLine {
+            words: vec![crate::LyricWord {
+                start_time: 1000,
+                end_time: 2000,
+                word: "World".into(),
+                obscene: true,
+                empty_beat: 2,
+                attributes: vec![("x-style".into(), "whisper".into())],
+                ..Default::default()
+            }],
+            is_duet: true,
+            agent: "v3".into(),
+            start_time: 1000,
+            end_time: 2000,
+            ..Default::default()
+        },
+    ];
+    let document = LyricDocument::from_lines(
+        &lines,
+        vec![MetadataEntry {
+            key: "musicName".into(),
+            values: vec!["Song".into()],
+        }],
+    );
+    assert_eq!(document.agents.len(), 3);
+    assert_eq!(document.lines[0].agent.as_deref(), Some("v1"));
+    assert_eq!(document.lines[1].agent.as_deref(), Some("v3"));
+
+    let json = stringify_json(&document);
+    assert!(json.starts_with(r#"{"version":1,"#));
+    let parsed = parse_json(&json).unwrap();
+    assert_eq!(parsed, document);
+    let owned: Vec<LyricLineOwned> = lines.iter().map(|x| x.to_owned()).collect();
+    assert_eq!(parsed.to_lines(), owned);
+}
+
+#[test]
+fn test_json_migrate_legacy() {
+    let legacy = r#"[{
+        "words": [{ "startTime": 100.4, "endTime": null, "word": "Hi", "romanWord": "" }],
+        "translatedLyric": "",
+        "romanLyric": "",
+        "isBG": false,
+        "isDuet": true,
+        "startTime": 100.4,
+        "endTime": null
+    }]"#;
+    let document = parse_json(legacy).unwrap();
+    assert_eq!(document.version, CURRENT_VERSION);
+    assert_eq!(document.lines[0].start_time, 100);
+    assert_eq!(document.lines[0].end_time, MAX_TIME);
+    assert!(document.lines[0].is_duet);
+
+    let legacy_ttml = r#"{
+        "lines": [{ "words": [{ "startTime": 0, "endTime": 10, "word": "a", "romanWord": "" }] }],
+        "metadata": [["musicName", ["Song"]]]
+    }"#;
+    let document = parse_json(legacy_ttml).unwrap();
+    assert_eq!(document.metadata[0].key, "musicName");
+    assert_eq!(document.lines[0].words[0].end_time, 10);
+}
+
+#[test]
+fn test_json_errors() {
+    assert!(matches!(
+        parse_json(r#"{"version":2,"lines":[]}"#),
+        Err(JsonError::UnsupportedVersion(2))
+    ));
+    assert!(matches!(
+        parse_json(r#"{"version":0,"lines":[]}"#),
+        Err(JsonError::UnsupportedVersion(0))
+    ));
+    assert!(matches!(
+        parse_json(r#"{"version":1}"#),
+        Err(JsonError::Invalid(_))
+    ));
+    assert!(matches!(parse_json("[1, 2"), Err(JsonError::Invalid(_))));
+}
+
+#[test]
+fn test_json_schema_up_to_date() {
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/schema/lyric-document.schema.json"
+    );
+    let schema = json_schema() + "\n";
+    if std::env::var_os("AMLL_UPDATE_SCHEMA").is_some() {
+        std::fs::write(path, &schema).unwrap();
+    }
+    assert_eq!(
+        std::fs::read_to_string(path).unwrap_or_default(),
+        schema,
+        "the JSON schema is outdated, rerun the test with AMLL_UPDATE_SCHEMA=1 to regenerate it"
+    );
+}
@@ -0,0 +1,366 @@
+//! 第三方歌词服务的 JSON 逐字歌词导入
+//!
+//! 目前支持两种常见的结构：
+//!
+//! - Musixmatch 的 richsync：每行包含以秒为单位的 `ts`、`te`，
+//!   以及 `l` 数组，数组中每一项的 `c` 为文字，`o` 为相对于行开始时间的偏移（秒）。
+//!   既可以直接传入 `richsync_body` 数组，也可以传入包含它的完整 API 响应，
+//!   此时 `richsync_body` 可以是 JSON 字符串。
+//! - Spotify 风格的行数组：每行包含以毫秒为单位的 `startTimeMs`，
+//!   `words` 可以是整行文字，也可以是包含 `startTimeMs`、`word` 的逐字数组。
+//!   既可以直接传入行数组，也可以传入包含 `lines` 或 `lyrics.lines` 的对象。
+//!   时间戳可以是数字，也可以是数字字符串。没有结束时间的最后一行会持续到歌曲结束。
+//!
+//! 两种结构中，只包含空白字符的项都会被合并到前一个单词的末尾，
+//! 以符合本库其它格式中单词自带尾随空格的习惯。
+
+use std::{borrow::Cow, fmt::Display};
+
+use serde_json::Value;
+
+use crate::{LyricLine, LyricWord, utils::MAX_TIME};
+
+/// 解析第三方 JSON 歌词时发生的错误
+#[derive(Debug)]
+pub enum JsonSourceError {
+    /// 输入不是合法的 JSON
+    InvalidJson(serde_json::Error),
+    /// 输入是合法的 JSON，但不是支持的歌词结构
+    UnknownShape,
+    /// 指定行的时间戳缺失或无效
+    InvalidTime(usize),
+}
+
+impl Display for JsonSourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidJson(err) => write!(f, "invalid json: {err}"),
+            Self::UnknownShape => write!(f, "unsupported lyric json structure"),
+            Self::InvalidTime(line) => write!(f, "missing or invalid timestamp at line {line}"),
+        }
+    }
+}
+
+impl std::error::Error for JsonSourceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidJson(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for JsonSourceError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::InvalidJson(err)
+    }
+}
+
+/// 将数字或数字字符串转换为毫秒，`scale` 为一个单位对应的毫秒数
+fn parse_time(value: &Value, scale: f64) -> Option<u64> {
+    let value = match value {
+        Value::Number(num) => num.as_f64()?,
+        Value::String(s) => s.trim().parse::<f64>().ok()?,
+        _ => return None,
+    };
+    if !value.is_finite() || value < 0.0 {
+        return None;
+    }
+    Some(((value * scale).round() as u64).min(MAX_TIME))
+}
+
+/// 将单词追加到行中，只包含空白字符的单词会被合并到前一个单词末尾，
+/// 行首的空白单词会被丢弃
+fn push_word(words: &mut Vec<LyricWord<'static>>, text: &str, start_time: u64, end_time: u64) {
+    if text.is_empty() {
+        return;
+    }
+    if text.trim().is_empty() {
+        if let Some(last) = words.last_mut() {
+            last.word.to_mut().push_str(text);
+        }
+        return;
+    }
+    words.push(LyricWord {
+        start_time,
+        end_time: end_time.max(start_time),
+        word: Cow::Owned(text.to_owned()),
+        ..Default::default()
+    });
+}
+
+/// 在 Musixmatch 的 API 响应中查找 `richsync_body`
+fn find_richsync_body(value: &Value) -> Option<&Value> {
+    match value {
+        Value::Object(map) => map
+            .get("richsync_body")
+            .or_else(|| map.values().find_map(find_richsync_body)),
+        _ => None,
+    }
+}
+
+fn richsync_lines(value: &Value) -> Result<Vec<LyricLine<'static>>, JsonSourceError> {
+    let Value::Array(lines) = value else {
+        return Err(JsonSourceError::UnknownShape);
+    };
+    let mut result = Vec::with_capacity(lines.len());
+
+    for (i, line) in lines.iter().enumerate() {
+        let start_time = line
+            .get("ts")
+            .and_then(|x| parse_time(x, 1000.0))
+            .ok_or(JsonSourceError::InvalidTime(i))?;
+        let end_time = line
+            .get("te")
+            .and_then(|x| parse_time(x, 1000.0))
+            .ok_or(JsonSourceError::InvalidTime(i))?
+            .max(start_time);
+        let parts = line
+            .get("l")
+            .and_then(Value::as_array)
+            .ok_or(JsonSourceError::UnknownShape)?;
+
+        let parts = parts
+            .iter()
+            .map(|part| {
+                let text = part.get("c").and_then(Value::as_str).unwrap_or_default();
+                let offset = part
+                    .get("o")
+                    .and_then(|x| parse_time(x, 1000.0))
+                    .ok_or(JsonSourceError::InvalidTime(i))?;
+                Ok((text, (start_time + offset).min(end_time)))
+            })
+            .collect::<Result<Vec<_>, JsonSourceError>>()?;
+
+        let mut words = Vec::with_capacity(parts.len());
+        for (j, &(text, word_start)) in parts.iter().enumerate() {
+            // 每一项的结束时间为下一项（包括空白项）的开始时间
+            let word_end = parts.get(j + 1).map(|x| x.1).unwrap_or(end_time);
+            push_word(&mut words, text, word_start, word_end);
+        }
+
+        result.push(LyricLine {
+            words,
+            start_time,
+            end_time,
+            ..Default::default()
+        });
+    }
+
+    Ok(result)
+}
+
+/// 解析 Musixmatch 的 richsync 逐字歌词
+///
+/// 可以传入 `richsync_body` 的内容，也可以传入包含它的完整 API 响应。
+pub fn parse_richsync(src: &str) -> Result<Vec<LyricLine<'static>>, JsonSourceError> {
+    richsync_from_value(&serde_json::from_str(src)?)
+}
+
+fn richsync_from_value(value: &Value) -> Result<Vec<LyricLine<'static>>, JsonSourceError> {
+    match value {
+        Value::Array(_) => richsync_lines(value),
+        _ => match find_richsync_body(value) {
+            Some(Value::String(body)) => richsync_lines(&serde_json::from_str(body)?),
+            Some(body) => richsync_lines(body),
+            None => Err(JsonSourceError::UnknownShape),
+        },
+    }
+}
+
+/// Spotify 用于表示间奏的行文字
+const SPOTIFY_INSTRUMENTAL: &str = "♪";
+
+fn spotify_line_array(value: &Value) -> Option<&Vec<Value>> {
+    match value {
+        Value::Array(lines) => Some(lines),
+        Value::Object(map) => map
+            .get("lines")
+            .and_then(Value::as_array)
+            .or_else(|| map.get("lyrics").and_then(spotify_line_array)),
+        _ => None,
+    }
+}
+
+fn spotify_lines(lines: &[Value]) -> Result<Vec<LyricLine<'static>>, JsonSourceError> {
+    let start_times = lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            line.get("startTimeMs")
+                .and_then(|x| parse_time(x, 1.0))
+                .ok_or(JsonSourceError::InvalidTime(i))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut result = Vec::with_capacity(lines.len());
+
+    for (i, line) in lines.iter().enumerate() {
+        let start_time = start_times[i];
+        // Spotify 在没有结束时间时会给出 "0"，此时以下一行的开始时间作为结束时间，
+        // 最后一行则持续到歌曲结束
+        let end_time = line
+            .get("endTimeMs")
+            .and_then(|x| parse_time(x, 1.0))
+            .filter(|&x| x > start_time)
+            .or_else(|| start_times.get(i + 1).copied())
+            .unwrap_or(MAX_TIME)
+            .max(start_time);
+
+        let mut words = Vec::new();
+        match line.get("words") {
+            Some(Value::String(text)) => {
+                if text.trim() != SPOTIFY_INSTRUMENTAL {
+                    push_word(&mut words, text, start_time, end_time);
+                }
+            }
+            Some(Value::Array(parts)) => {
+                let parts = parts
+                    .iter()
+                    .map(|part| {
+                        let text = part
+                            .get("word")
+                            .or_else(|| part.get("words"))
+                            .and_then(Value::as_str)
+                            .unwrap_or_default();
+                        let word_start = part
+                            .get("startTimeMs")
+                            .and_then(|x| parse_time(x, 1.0))
+                            .ok_or(JsonSourceError::InvalidTime(i))?;
+                        let word_end = part
+                            .get("endTimeMs")
+                            .and_then(|x| parse_time(x, 1.0))
+                            .filter(|&x| x > word_start);
+                        Ok((text, word_start, word_end))
+                    })
+                    .collect::<Result<Vec<_>, JsonSourceError>>()?;
+                for (j, &(text, word_start, word_end)) in parts.iter().enumerate() {
+                    let word_end = word_end
+                        .or_else(|| parts.get(j + 1).map(|x| x.1))
+                        .unwrap_or(end_time);
+                    push_word(&mut words, text, word_start, word_end);
+                }
+            }
+            Some(Value::Null) | None => {}
+            Some(_) => return Err(JsonSourceError::UnknownShape),
+        }
+
+        let end_time = words
+            .last()
+            .map(|x| x.end_time.max(end_time))
+            .unwrap_or(end_time);
+        result.push(LyricLine {
+            words,
+            start_time,
+            end_time,
+            ..Default::default()
+        });
+    }
+
+    Ok(result)
+}
+
+/// 解析 Spotify 风格的逐行或逐字歌词
+///
+/// 可以传入行数组，也可以传入包含 `lines` 或 `lyrics.lines` 的对象。
+/// 文字为 `♪` 的间奏行会被转换为没有单词的空行。
+pub fn parse_spotify(src: &str) -> Result<Vec<LyricLine<'static>>, JsonSourceError> {
+    spotify_from_value(&serde_json::from_str(src)?)
+}
+
+fn spotify_from_value(value: &Value) -> Result<Vec<LyricLine<'static>>, JsonSourceError> {
+    let lines = spotify_line_array(value).ok_or(JsonSourceError::UnknownShape)?;
+    spotify_lines(lines)
+}
+
+/// 自动识别 JSON 的结构并解析为歌词行
+///
+/// 依次尝试 Musixmatch richsync 和 Spotify 风格的结构。
+pub fn parse_json_source(src: &str) -> Result<Vec<LyricLine<'static>>, JsonSourceError> {
+    let value: Value = serde_json::from_str(src)?;
+    let is_richsync = match &value {
+        Value::Array(lines) => lines.first().is_some_and(|x| x.get("ts").is_some()),
+        _ => find_richsync_body(&value).is_some(),
+    };
+    if is_richsync {
+        richsync_from_value(&value)
+    } else {
+        spotify_from_value(&value)
+    }
+}
+
+#[test]
+fn test_parse_richsync() {
+    let lines = parse_json_source(include_str!("../test/richsync.json")).unwrap();
+    assert_eq!(lines.len(), 2);
+
+    let words = &lines[0].words;
+    assert_eq!((lines[0].start_time, lines[0].end_time), (1500, 4250));
+    assert_eq!(
+        words
+            .iter()
+            .map(|x| (x.word.as_ref(), x.start_time, x.end_time))
+            .collect::<Vec<_>>(),
+        [
+            ("Hello ", 1500, 2300),
+            ("dar", 2500, 2900),
+            ("ling ", 2900, 3500),
+            ("world", 3600, 4250),
+        ]
+    );
+
+    let words = &lines[1].words;
+    assert_eq!(
+        words
+            .iter()
+            .map(|x| (x.word.as_ref(), x.start_time, x.end_time))
+            .collect::<Vec<_>>(),
+        [("你", 6000, 6500), ("好", 6500, 7250), ("吗", 7250, 8500)]
+    );
+
+    let body = r#"[{"ts":"1","te":"2","l":[{"c":" ","o":0},{"c":"a","o":0.5}]}]"#;
+    let lines = parse_richsync(body).unwrap();
+    assert_eq!(lines[0].words.len(), 1);
+    assert_eq!(lines[0].words[0].word, "a");
+    assert!(matches!(
+        parse_richsync(r#"[{"te":1,"l":[]}]"#),
+        Err(JsonSourceError::InvalidTime(0))
+    ));
+}
+
+#[test]
+fn test_parse_spotify() {
+    let lines = parse_json_source(include_str!("../test/spotify-lyrics.json")).unwrap();
+    assert_eq!(
+        lines
+            .iter()
+            .map(|x| (x.start_time, x.end_time, x.words.len()))
+            .collect::<Vec<_>>(),
+        [
+            (960, 4250, 1),
+            (4250, 12000, 0),
+            (12000, 15500, 1),
+            (15500, MAX_TIME, 0)
+        ]
+    );
+    assert_eq!(lines[0].words[0].word, "Hello darling world");
+    assert_eq!(lines[2].words[0].word, "你好吗");
+
+    let lines = parse_spotify(include_str!("../test/spotify-words.json")).unwrap();
+    assert_eq!(
+        lines
+            .iter()
+            .flat_map(|x| x.words.iter())
+            .map(|x| (x.word.as_ref(), x.start_time, x.end_time))
+            .collect::<Vec<_>>(),
+        [
+            ("Hello ", 1000, 1600),
+            ("world", 1600, 2400),
+            ("Second ", 5000, 5500),
+            ("line", 5600, MAX_TIME),
+        ]
+    );
+    assert!(matches!(
+        parse_spotify("{}"),
+        Err(JsonSourceError::UnknownShape)
+    ));
+}
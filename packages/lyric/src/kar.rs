@@ -0,0 +1,546 @@
+//! 卡拉 OK MIDI（.kar）歌词的导入与导出
+//!
+//! .kar 文件即是标准 MIDI 文件，歌词以逐音节的元事件存储：
+//! 标准 MIDI 歌词使用歌词事件（`FF 05`），.kar 文件则多使用文本事件（`FF 01`），
+//! 并以 `@` 开头的文本事件存储标题等信息。
+//!
+//! 以 `/` 开头的音节表示新的一行，以 `\` 开头的音节表示新的段落，
+//! 音节中的换行符（`\r`、`\n`）同样会被视为换行。
+//!
+//! 由于 MIDI 歌词事件只有开始时间，每个音节的结束时间会被设为下一个音节的开始时间，
+//! 最后一个音节的结束时间为音轨的结束时间。
+
+use std::{borrow::Cow, fmt::Display};
+
+use crate::{LyricLine, LyricWord, utils::MAX_TIME};
+
+/// 解析 MIDI 文件时发生的错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KarError {
+    /// 文件头不是 `MThd`
+    InvalidHeader,
+    /// 数据在指定位置意外结束
+    UnexpectedEof(usize),
+    /// 在指定位置遇到了没有状态字节的 MIDI 事件
+    MissingStatus(usize),
+}
+
+impl Display for KarError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidHeader => write!(f, "invalid MIDI header"),
+            Self::UnexpectedEof(pos) => write!(f, "unexpected end of MIDI data at {pos}"),
+            Self::MissingStatus(pos) => write!(f, "MIDI event without status byte at {pos}"),
+        }
+    }
+}
+
+impl std::error::Error for KarError {}
+
+/// 导出时使用的时间精度，和 [`EXPORT_TEMPO`] 配合使得一个 tick 恰好为一毫秒
+const EXPORT_DIVISION: u16 = 480;
+/// 导出时使用的速度，每个四分音符 480000 微秒，即 125 BPM
+const EXPORT_TEMPO: u32 = 480000;
+/// MIDI 默认速度，每个四分音符 500000 微秒，即 120 BPM
+const DEFAULT_TEMPO: u32 = 500000;
+/// 四个字节的变长整数能表示的最大值，更大的时间差会被截断
+const MAX_DELTA: u64 = 0x0FFF_FFFF;
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], KarError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or(KarError::UnexpectedEof(self.data.len()))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, KarError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, KarError> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, KarError> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    /// 读取 MIDI 的变长整数
+    fn vlq(&mut self) -> Result<u32, KarError> {
+        let mut value = 0u32;
+        for _ in 0..4 {
+            let byte = self.u8()?;
+            value = (value << 7) | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        Ok(value)
+    }
+}
+
+/// MIDI 文件的时间精度
+#[derive(Debug, Clone, Copy)]
+enum Division {
+    /// 每个四分音符的 tick 数
+    TicksPerQuarter(u16),
+    /// SMPTE 时间码，每秒的帧数和每帧的 tick 数
+    Smpte(u8, u8),
+}
+
+/// 文件中所有音轨的歌词相关事件
+#[derive(Default)]
+struct MidiEvents {
+    /// 速度变化，`(tick, 每个四分音符的微秒数)`
+    tempos: Vec<(u64, u32)>,
+    /// 歌词事件（`FF 05`），`(tick, 文字)`
+    lyrics: Vec<(u64, Vec<u8>)>,
+    /// 文本事件（`FF 01`），`(tick, 文字)`
+    texts: Vec<(u64, Vec<u8>)>,
+    /// 最后一个音轨结束的 tick
+    end_tick: u64,
+}
+
+fn read_track(data: &[u8], events: &mut MidiEvents) -> Result<(), KarError> {
+    let mut reader = Reader { data, pos: 0 };
+    let mut tick = 0u64;
+    let mut running_status = None;
+    while reader.pos < data.len() {
+        tick += reader.vlq()? as u64;
+        let mut status = reader.u8()?;
+        if status < 0x80 {
+            // 沿用上一个事件的状态字节，当前字节为第一个数据字节
+            status = running_status.ok_or(KarError::MissingStatus(reader.pos - 1))?;
+            reader.pos -= 1;
+        }
+        match status {
+            0xFF => {
+                let kind = reader.u8()?;
+                let len = reader.vlq()? as usize;
+                let body = reader.bytes(len)?;
+                match kind {
+                    0x01 => events.texts.push((tick, body.to_vec())),
+                    0x05 => events.lyrics.push((tick, body.to_vec())),
+                    0x51 if len == 3 => {
+                        let tempo = u32::from_be_bytes([0, body[0], body[1], body[2]]);
+                        events.tempos.push((tick, tempo));
+                    }
+                    0x2F => break,
+                    _ => {}
+                }
+            }
+            0xF0 | 0xF7 => {
+                let len = reader.vlq()? as usize;
+                reader.bytes(len)?;
+            }
+            _ => {
+                running_status = Some(status);
+                let len = match status & 0xF0 {
+                    0xC0 | 0xD0 => 1,
+                    _ => 2,
+                };
+                reader.bytes(len)?;
+            }
+        }
+    }
+    events.end_tick = events.end_tick.max(tick);
+    Ok(())
+}
+
+/// 根据速度变化将 tick 换算为毫秒
+struct TempoMap {
+    division: Division,
+    /// `(tick, 该 tick 对应的微秒数, 每个四分音符的微秒数)`
+    segments: Vec<(u64, u64, u32)>,
+}
+
+impl TempoMap {
+    fn new(division: Division, mut tempos: Vec<(u64, u32)>) -> Self {
+        tempos.sort_by_key(|x| x.0);
+        let mut segments = vec![(0, 0, DEFAULT_TEMPO)];
+        for (tick, tempo) in tempos {
+            let micros = Self::micros_in(division, segments.last().unwrap(), tick);
+            if segments.last().unwrap().0 == tick {
+                segments.pop();
+            }
+            segments.push((tick, micros, tempo));
+        }
+        Self { division, segments }
+    }
+
+    fn micros_in(division: Division, segment: &(u64, u64, u32), tick: u64) -> u64 {
+        let (start_tick, start_micros, tempo) = *segment;
+        let ticks = tick - start_tick;
+        start_micros
+            + match division {
+                Division::TicksPerQuarter(tpq) => ticks * tempo as u64 / tpq.max(1) as u64,
+                Division::Smpte(fps, tpf) => {
+                    ticks * 1_000_000 / (fps.max(1) as u64 * tpf.max(1) as u64)
+                }
+            }
+    }
+
+    fn to_ms(&self, tick: u64) -> u64 {
+        let index = self.segments.partition_point(|x| x.0 <= tick) - 1;
+        Self::micros_in(self.division, &self.segments[index], tick) / 1000
+    }
+}
+
+fn decode_text(data: &[u8]) -> String {
+    match std::str::from_utf8(data) {
+        Ok(text) => text.to_owned(),
+        // 大部分 .kar 文件使用单字节编码，无法以 UTF-8 解码时按 Latin-1 处理
+        Err(_) => data.iter().map(|&b| b as char).collect(),
+    }
+}
+
+/// 解析 MIDI / .kar 文件中的歌词
+///
+/// 优先使用歌词事件，文件中没有歌词事件时使用除 `@` 开头的信息以外的文本事件
+pub fn parse_kar(data: &[u8]) -> Result<Vec<LyricLine<'static>>, KarError> {
+    let mut reader = Reader { data, pos: 0 };
+    if reader.bytes(4).map_err(|_| KarError::InvalidHeader)? != b"MThd" {
+        return Err(KarError::InvalidHeader);
+    }
+    let header_len = reader.u32()? as usize;
+    let header_end = reader.pos + header_len;
+    // 无论是单音轨、多音轨还是多序列的文件，都将所有音轨的事件合并处理
+    let _format = reader.u16()?;
+    let track_count = reader.u16()?;
+    let division = reader.u16()?;
+    let division = if division & 0x8000 != 0 {
+        // 高字节为负数形式的每秒帧数
+        Division::Smpte(((division >> 8) as u8 as i8).unsigned_abs(), division as u8)
+    } else {
+        Division::TicksPerQuarter(division)
+    };
+    reader.pos = header_end;
+
+    let mut events = MidiEvents::default();
+    for _ in 0..track_count {
+        if reader.pos >= data.len() {
+            break;
+        }
+        let id = reader.bytes(4)?;
+        let len = reader.u32()? as usize;
+        let body = reader.bytes(len)?;
+        // 按照规范忽略未知类型的块
+        if id == b"MTrk" {
+            read_track(body, &mut events)?;
+        }
+    }
+
+    let tempo_map = TempoMap::new(division, std::mem::take(&mut events.tempos));
+    let syllables = if events.lyrics.is_empty() {
+        events
+            .texts
+            .into_iter()
+            .filter(|(_, text)| !text.starts_with(b"@"))
+            .collect()
+    } else {
+        events.lyrics
+    };
+    let mut syllables: Vec<(u64, String)> = syllables
+        .into_iter()
+        .map(|(tick, text)| (tempo_map.to_ms(tick), decode_text(&text)))
+        .collect();
+    syllables.sort_by_key(|x| x.0);
+    let end_time = tempo_map.to_ms(events.end_tick);
+
+    let mut lines: Vec<LyricLine<'static>> = Vec::new();
+    let mut current = LyricLine::default();
+    for (i, (start_time, text)) in syllables.iter().enumerate() {
+        let next_start = syllables
+            .get(i + 1)
+            .map(|x| x.0)
+            .unwrap_or(end_time)
+            .max(*start_time);
+        let text = match text.strip_prefix(['/', '\\']) {
+            Some(text) => {
+                push_line(&mut lines, &mut current);
+                text
+            }
+            None => text.as_str(),
+        };
+        let line_break = text.ends_with(['\r', '\n']);
+        let word = text.trim_matches(['\r', '\n']);
+        if !word.is_empty() {
+            current.words.push(LyricWord {
+                start_time: *start_time,
+                end_time: next_start,
+                word: Cow::Owned(word.to_owned()),
+                ..Default::default()
+            });
+        }
+        if line_break {
+            push_line(&mut lines, &mut current);
+        }
+    }
+    push_line(&mut lines, &mut current);
+    Ok(lines)
+}
+
+fn push_line(lines: &mut Vec<LyricLine<'static>>, line: &mut LyricLine<'static>) {
+    let mut line = std::mem::take(line);
+    if line.words.is_empty() {
+        return;
+    }
+    line.start_time = line.words.first().unwrap().start_time;
+    line.end_time = line.words.last().unwrap().end_time;
+    lines.push(line);
+}
+
+fn write_vlq(result: &mut Vec<u8>, value: u32) {
+    let mut bytes = vec![(value & 0x7F) as u8];
+    let mut value = value >> 7;
+    while value > 0 {
+        bytes.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    result.extend(bytes.iter().rev());
+}
+
+fn write_meta(track: &mut Vec<u8>, delta: u32, kind: u8, body: &[u8]) {
+    write_vlq(track, delta);
+    track.extend([0xFF, kind]);
+    write_vlq(track, body.len() as u32);
+    track.extend_from_slice(body);
+}
+
+fn write_chunk(result: &mut Vec<u8>, track: &[u8]) {
+    result.extend_from_slice(b"MTrk");
+    result.extend((track.len() as u32).to_be_bytes());
+    result.extend_from_slice(track);
+}
+
+/// 将歌词数组导出为只包含歌词的 .kar 文件
+///
+/// 生成的文件包含一个速度音轨和一个名为 `Words` 的歌词音轨，
+/// 每个单词为一个文本事件，新的一行以 `/` 开头。
+/// 由于 MIDI 歌词事件没有结束时间，单词的结束时间会丢失。
+pub fn stringify_kar(lines: &[LyricLine]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(1024);
+    result.extend_from_slice(b"MThd");
+    result.extend(6u32.to_be_bytes());
+    result.extend(1u16.to_be_bytes());
+    result.extend(2u16.to_be_bytes());
+    result.extend(EXPORT_DIVISION.to_be_bytes());
+
+    let mut tempo_track = Vec::new();
+    write_meta(&mut tempo_track, 0, 0x51, &EXPORT_TEMPO.to_be_bytes()[1..]);
+    write_meta(&mut tempo_track, 0, 0x2F, &[]);
+    write_chunk(&mut result, &tempo_track);
+
+    let mut words_track = Vec::new();
+    write_meta(&mut words_track, 0, 0x03, b"Words");
+    write_meta(&mut words_track, 0, 0x01, b"@KMIDI KARAOKE FILE");
+    let mut last_tick = 0u64;
+    for (i, line) in lines.iter().filter(|x| !x.is_empty()).enumerate() {
+        for (j, word) in line.words.iter().enumerate() {
+            let delta = word.start_time.saturating_sub(last_tick).min(MAX_DELTA);
+            let text = if i > 0 && j == 0 {
+                format!("/{}", word.word)
+            } else {
+                word.word.to_string()
+            };
+            write_meta(&mut words_track, delta as u32, 0x01, text.as_bytes());
+            last_tick += delta;
+        }
+    }
+    // LRC 等格式中最后一行没有结束时间，不能以此作为音轨的结束时间
+    let end_tick = lines
+        .iter()
+        .flat_map(|x| x.words.iter())
+        .map(|x| x.end_time)
+        .filter(|&x| x < MAX_TIME)
+        .max()
+        .unwrap_or_default();
+    let delta = end_tick.saturating_sub(last_tick).min(MAX_DELTA);
+    write_meta(&mut words_track, delta as u32, 0x2F, &[]);
+    write_chunk(&mut result, &words_track);
+
+    result
+}
+
+#[cfg(test)]
+fn test_midi(division: u16, events: &[(u32, u8, &[u8])]) -> Vec<u8> {
+    let mut track = Vec::new();
+    for (delta, kind, body) in events {
+        write_meta(&mut track, *delta, *kind, body);
+    }
+    let mut result = b"MThd\0\0\0\x06\0\0\0\x01".to_vec();
+    result.extend(division.to_be_bytes());
+    write_chunk(&mut result, &track);
+    result
+}
+
+#[test]
+fn test_parse_kar() {
+    // 120 BPM，每个四分音符 480 tick，一个 tick 约为 1.04 毫秒
+    let data = test_midi(
+        480,
+        &[
+            (0, 0x01, b"@KMIDI KARAOKE FILE"),
+            (0, 0x01, b"@TTest Song"),
+            (480, 0x01, b"\\Hel"),
+            (240, 0x01, b"lo "),
+            (240, 0x01, b"world"),
+            // 从此处开始速度变为 60 BPM
+            (480, 0x51, &[0x0F, 0x42, 0x40]),
+            (0, 0x01, b"/Sec"),
+            (480, 0x01, b"ond\r"),
+            (480, 0x2F, b""),
+        ],
+    );
+    let lines = parse_kar(&data).unwrap();
+    assert_eq!(lines.len(), 2);
+    let words: Vec<_> = lines[0]
+        .words
+        .iter()
+        .map(|x| (x.word.as_ref(), x.start_time, x.end_time))
+        .collect();
+    assert_eq!(
+        words,
+        vec![("Hel", 500, 750), ("lo ", 750, 1000), ("world", 1000, 1500)]
+    );
+    assert_eq!((lines[0].start_time, lines[0].end_time), (500, 1500));
+    let words: Vec<_> = lines[1]
+        .words
+        .iter()
+        .map(|x| (x.word.as_ref(), x.start_time, x.end_time))
+        .collect();
+    assert_eq!(words, vec![("Sec", 1500, 2500), ("ond", 2500, 3500)]);
+}
+
+#[test]
+fn test_parse_kar_lyric_events() {
+    // 同时存在歌词事件和文本事件时只使用歌词事件，Latin-1 编码的文字也能正确解码
+    let data = test_midi(
+        1000,
+        &[
+            (0, 0x03, b"Melody"),
+            (0, 0x01, b"Copyright"),
+            (0, 0x05, b"Caf\xe9 "),
+            (500, 0x05, b"au lait\r"),
+            (500, 0x05, b"Bye"),
+        ],
+    );
+    let lines = parse_kar(&data).unwrap();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].words[0].word, "Café ");
+    assert_eq!(lines[0].words[1].start_time, 250);
+    assert_eq!(lines[1].words[0].word, "Bye");
+
+    assert_eq!(parse_kar(b"RIFF"), Err(KarError::InvalidHeader));
+    assert!(matches!(
+        parse_kar(&data[..data.len() - 3]),
+        Err(KarError::UnexpectedEof(_))
+    ));
+}
+
+#[test]
+fn test_stringify_kar() {
+    let lines = vec![
+        LyricLine {
+            words: vec![
+                LyricWord {
+                    start_time: 1000,
+                    end_time: 1500,
+                    word: "Hel".into(),
+                    ..Default::default()
+                },
+                LyricWord {
+                    start_time: 1500,
+                    end_time: 2000,
+                    word: "lo".into(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        },
+        LyricLine {
+            words: vec![LyricWord {
+                start_time: 3000,
+                end_time: 4000,
+                word: "World".into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        },
+    ];
+    let data = stringify_kar(&lines);
+    let parsed = parse_kar(&data).unwrap();
+    assert_eq!(parsed.len(), 2);
+    let words: Vec<_> = parsed
+        .iter()
+        .flat_map(|x| x.words.iter())
+        .map(|x| (x.word.as_ref(), x.start_time, x.end_time))
+        .collect();
+    assert_eq!(
+        words,
+        vec![
+            ("Hel", 1000, 1500),
+            ("lo", 1500, 3000),
+            ("World", 3000, 4000)
+        ]
+    );
+}
+
+#[test]
+fn test_stringify_kar_unbounded_time() {
+    // LRC 中最后一行的结束时间为 u64::MAX，过大的时间差也不能超出变长整数的范围
+    let lines = vec![
+        LyricLine {
+            words: vec![LyricWord {
+                start_time: 1000,
+                end_time: 2000,
+                word: "Hello".into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        },
+        LyricLine {
+            words: vec![LyricWord {
+                start_time: 3000,
+                end_time: u64::MAX,
+                word: "World".into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        },
+        LyricLine {
+            words: vec![LyricWord {
+                start_time: u64::MAX / 2,
+                end_time: u64::MAX,
+                word: "Late".into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        },
+    ];
+    let data = stringify_kar(&lines);
+    let parsed = parse_kar(&data).unwrap();
+    let words: Vec<_> = parsed
+        .iter()
+        .flat_map(|x| x.words.iter())
+        .map(|x| (x.word.as_ref(), x.start_time, x.end_time))
+        .collect();
+    assert_eq!(
+        words,
+        vec![
+            ("Hello", 1000, 3000),
+            ("World", 3000, 3000 + MAX_DELTA),
+            ("Late", 3000 + MAX_DELTA, 3000 + MAX_DELTA)
+        ]
+    );
+}
@@ -4,22 +4,36 @@ pub mod ass;
 pub mod eqrc;
 #[cfg(feature = "eslrc")]
 pub mod eslrc;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "json_sources")]
+pub mod json_sources;
+#[cfg(feature = "kar")]
+pub mod kar;
 #[cfg(feature = "lrc")]
 pub mod lrc;
 #[cfg(feature = "lys")]
 pub mod lys;
 #[cfg(feature = "qrc")]
 pub mod qrc;
+#[cfg(feature = "romanize")]
+pub mod romanize;
 #[cfg(feature = "ttml")]
 pub mod ttml;
+#[cfg(feature = "ultrastar")]
+pub mod ultrastar;
 #[cfg(feature = "yrc")]
 pub mod yrc;
+#[cfg(feature = "zhconv")]
+pub mod zhconv;
+
+pub mod edit;
+pub mod interlude;
+pub mod timeline;
 
 pub mod utils;
-#[cfg(target_arch = "wasm32")]
-mod types {
-    include!(concat!(env!("OUT_DIR"), "/types.rs"));
-}
 
 use std::borrow::Cow;
 
@@ -32,14 +46,115 @@ use serde::*;
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
+/// 普通歌词行默认的演唱者标识，与导出 TTML 时声明的主演唱者一致
+pub const MAIN_AGENT: &str = "v1";
+/// 对唱歌词行默认的演唱者标识
+pub const DUET_AGENT: &str = "v2";
+
+/// 歌词行的演唱者标识，未指定时根据是否为对唱歌词行返回默认的演唱者
+fn line_agent(agent: &str, is_duet: bool) -> &str {
+    match agent {
+        "" if is_duet => DUET_AGENT,
+        "" => MAIN_AGENT,
+        agent => agent,
+    }
+}
+
+/// 一个歌词单词
 #[derive(Debug, Clone, PartialEq, Default)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+#[cfg_attr(all(target_arch = "wasm32", feature = "serde"), derive(tsify::Tsify))]
 pub struct LyricWord<'a> {
+    /// 单词的起始时间
     pub start_time: u64,
+    /// 单词的结束时间
     pub end_time: u64,
+    /// 单词
     pub word: Cow<'a, str>,
+    /// 单词的音译
     pub roman_word: Cow<'a, str>,
+    /// 该单词是否为不雅用语，渲染时可以对其进行遮挡
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "std::ops::Not::not")
+    )]
+    pub obscene: bool,
+    /// 该单词的空拍数量，常用于没有实际歌词的拟声词，渲染时会按拍数产生律动效果
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "utils::is_zero")
+    )]
+    pub empty_beat: u32,
+    /// 其他自定义的单词属性，以 `[键, 值]` 的形式存储
+    ///
+    /// 在 TTML 中对应除上述属性以外的其他 `amll:` 命名空间属性，
+    /// 导出 TTML 时无法作为 XML 属性名（NCName）的键会被跳过
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub attributes: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+    /// 单词的注音（例如日语的振假名），单词本身即为注音的基文本
+    ///
+    /// 注音可以拆分为多个带有独立时间的片段，没有独立时间的注音会使用单词的时间
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub ruby: Vec<LyricRuby<'a>>,
+    /// 演唱该单词的演唱者标识，对应 TTML 中的 `ttm:agent`
+    ///
+    /// 为空时表示与所在歌词行的演唱者相同，不为空时表示该单词由另一位演唱者演唱，
+    /// 常见于在一行歌词中途切换演唱者的对唱
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "utils::is_empty_str")
+    )]
+    pub agent: Cow<'a, str>,
+}
+
+/// 单词注音的一个片段
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+#[cfg_attr(all(target_arch = "wasm32", feature = "serde"), derive(tsify::Tsify))]
+pub struct LyricRuby<'a> {
+    /// 注音片段的起始时间
+    pub start_time: u64,
+    /// 注音片段的结束时间
+    pub end_time: u64,
+    /// 注音片段的文字
+    pub word: Cow<'a, str>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct LyricRubyOwned {
+    pub start_time: u64,
+    pub end_time: u64,
+    pub word: String,
+}
+
+impl LyricRuby<'_> {
+    pub fn to_owned(&self) -> LyricRubyOwned {
+        LyricRubyOwned {
+            start_time: self.start_time,
+            end_time: self.end_time,
+            word: self.word.clone().into_owned(),
+        }
+    }
+}
+
+impl LyricRubyOwned {
+    pub fn to_ref<'a>(&'a self) -> LyricRuby<'a> {
+        LyricRuby {
+            start_time: self.start_time,
+            end_time: self.end_time,
+            word: self.word.as_str().into(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Default)]
@@ -50,6 +165,31 @@ pub struct LyricWordOwned {
     pub end_time: u64,
     pub word: String,
     pub roman_word: String,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "std::ops::Not::not")
+    )]
+    pub obscene: bool,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "utils::is_zero")
+    )]
+    pub empty_beat: u32,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub attributes: Vec<(String, String)>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub ruby: Vec<LyricRubyOwned>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "String::is_empty")
+    )]
+    pub agent: String,
 }
 
 impl<'a> From<LyricWord<'a>> for LyricWordOwned {
@@ -59,6 +199,15 @@ impl<'a> From<LyricWord<'a>> for LyricWordOwned {
             end_time: value.end_time,
             word: value.word.into_owned(),
             roman_word: value.roman_word.into_owned(),
+            obscene: value.obscene,
+            empty_beat: value.empty_beat,
+            attributes: value
+                .attributes
+                .into_iter()
+                .map(|(k, v)| (k.into_owned(), v.into_owned()))
+                .collect(),
+            ruby: value.ruby.iter().map(LyricRuby::to_owned).collect(),
+            agent: value.agent.into_owned(),
         }
     }
 }
@@ -70,12 +219,29 @@ impl LyricWord<'_> {
             end_time: self.end_time,
             word: self.word.clone().into_owned(),
             roman_word: self.roman_word.clone().into_owned(),
+            obscene: self.obscene,
+            empty_beat: self.empty_beat,
+            attributes: self
+                .attributes
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            ruby: self.ruby.iter().map(LyricRuby::to_owned).collect(),
+            agent: self.agent.clone().into_owned(),
         }
     }
 
     pub fn is_empty(&self) -> bool {
         self.word.trim().is_empty()
     }
+
+    /// 获取指定键的自定义单词属性
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_ref())
+    }
 }
 
 impl LyricWordOwned {
@@ -85,29 +251,71 @@ impl LyricWordOwned {
             end_time: self.end_time,
             word: self.word.as_str().into(),
             roman_word: self.roman_word.as_str().into(),
+            obscene: self.obscene,
+            empty_beat: self.empty_beat,
+            attributes: self
+                .attributes
+                .iter()
+                .map(|(k, v)| (k.as_str().into(), v.as_str().into()))
+                .collect(),
+            ruby: self.ruby.iter().map(LyricRubyOwned::to_ref).collect(),
+            agent: self.agent.as_str().into(),
         }
     }
 
     pub fn is_empty(&self) -> bool {
         self.word.trim().is_empty()
     }
+
+    /// 获取指定键的自定义单词属性
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
 }
 
+/// 一行歌词，存储多个单词
+///
+/// 如果是 LyRiC 等只能表达一行歌词的格式，则会将整行当做一个单词存储起来
 #[derive(Debug, Clone, PartialEq, Default)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+#[cfg_attr(all(target_arch = "wasm32", feature = "serde"), derive(tsify::Tsify))]
 pub struct LyricLine<'a> {
+    /// 该行的所有单词
+    ///
+    /// 如果是 LyRiC 等只能表达一行歌词的格式，这里就只会有一个单词
     pub words: Vec<LyricWord<'a>>,
+    /// 该行的翻译
     #[cfg_attr(feature = "serde", serde(default))]
     pub translated_lyric: Cow<'a, str>,
+    /// 该行的音译
     #[cfg_attr(feature = "serde", serde(default))]
     pub roman_lyric: Cow<'a, str>,
+    /// 该行是否为背景歌词行
     #[cfg_attr(feature = "serde", serde(default, rename = "isBG"))]
     pub is_bg: bool,
+    /// 该行是否为对唱歌词行（即歌词行靠右对齐）
     #[cfg_attr(feature = "serde", serde(default))]
     pub is_duet: bool,
+    /// 演唱该行的演唱者标识，对应 TTML 中的 `ttm:agent`
+    ///
+    /// 为空时表示默认的演唱者，即普通歌词行为 [`MAIN_AGENT`]，对唱歌词行为 [`DUET_AGENT`]
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "utils::is_empty_str")
+    )]
+    pub agent: Cow<'a, str>,
+    /// 该行的开始时间
+    ///
+    /// **并不总是等于第一个单词的开始时间**
     #[cfg_attr(feature = "serde", serde(default))]
     pub start_time: u64,
+    /// 该行的结束时间
+    ///
+    /// **并不总是等于最后一个单词的结束时间**
     #[cfg_attr(feature = "serde", serde(default))]
     pub end_time: u64,
 }
@@ -121,6 +329,11 @@ pub struct LyricLineOwned {
     pub roman_lyric: String,
     pub is_bg: bool,
     pub is_duet: bool,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "String::is_empty")
+    )]
+    pub agent: String,
     pub start_time: u64,
     pub end_time: u64,
 }
@@ -133,13 +346,14 @@ impl<'a> From<LyricLine<'a>> for LyricLineOwned {
             roman_lyric: value.roman_lyric.into_owned(),
             is_bg: value.is_bg,
             is_duet: value.is_duet,
+            agent: value.agent.into_owned(),
             start_time: value.start_time,
             end_time: value.end_time,
         }
     }
 }
 
-impl LyricLine<'_> {
+impl<'a> LyricLine<'a> {
     pub fn to_owned(&self) -> LyricLineOwned {
         LyricLineOwned {
             words: self.words.iter().map(|w| w.to_owned()).collect(),
@@ -147,6 +361,7 @@ impl LyricLine<'_> {
             roman_lyric: self.roman_lyric.clone().into_owned(),
             is_bg: self.is_bg,
             is_duet: self.is_duet,
+            agent: self.agent.clone().into_owned(),
             start_time: self.start_time,
             end_time: self.end_time,
         }
@@ -163,6 +378,83 @@ impl LyricLine<'_> {
     pub fn is_empty(&self) -> bool {
         self.words.is_empty() || self.words.iter().all(|x| x.is_empty())
     }
+
+    /// 演唱该行的演唱者标识，未指定时为 [`MAIN_AGENT`] 或 [`DUET_AGENT`]
+    pub fn agent(&self) -> &str {
+        line_agent(&self.agent, self.is_duet)
+    }
+
+    /// 按单词的演唱者将歌词行拆分为多个片段，用于只能表达整行演唱者的格式
+    ///
+    /// 连续的同一演唱者的单词会组成一个片段，空白单词会归入前一个片段。未指定演唱者或演唱者与歌词行
+    /// 相同的片段保留原歌词行的对唱属性和演唱者，其他片段使用单词的演唱者，除 [`MAIN_AGENT`]
+    /// 以外的演唱者均视为对唱（普通歌词行的其他演唱者同样视为对唱）。翻译和音译只会保留在第一个片段中。
+    /// 如果没有单词指定了演唱者，则返回只包含原歌词行的数组。
+    pub fn split_by_agent(&self) -> Vec<LyricLine<'a>> {
+        if self.words.iter().all(|x| x.agent.is_empty()) {
+            return vec![self.clone()];
+        }
+        let mut segments: Vec<(Cow<'a, str>, Vec<LyricWord<'a>>)> = Vec::new();
+        for word in &self.words {
+            // 与歌词行相同的演唱者和未指定演唱者等价
+            let agent = if word.agent == self.agent() {
+                Cow::Borrowed("")
+            } else {
+                word.agent.clone()
+            };
+            match segments.last_mut() {
+                Some((last, words)) if word.is_empty() || *last == agent => {
+                    words.push(word.clone());
+                }
+                _ => segments.push((agent, vec![word.clone()])),
+            }
+        }
+        segments
+            .into_iter()
+            .enumerate()
+            .map(|(i, (agent, mut words))| {
+                for word in words.iter_mut() {
+                    word.agent = Cow::Borrowed("");
+                }
+                let timed = words.iter().filter(|x| x.end_time > x.start_time);
+                let start_time = timed.clone().map(|x| x.start_time).min();
+                let end_time = timed.map(|x| x.end_time).max();
+                let (is_duet, agent) = if agent.is_empty() {
+                    (self.is_duet, self.agent.clone())
+                } else {
+                    // 普通歌词行的其他演唱者一定不是主演唱者
+                    let is_duet = !self.is_duet || agent != MAIN_AGENT;
+                    let default_agent = line_agent("", is_duet);
+                    (
+                        is_duet,
+                        if agent == default_agent {
+                            Cow::Borrowed("")
+                        } else {
+                            agent
+                        },
+                    )
+                };
+                LyricLine {
+                    words,
+                    translated_lyric: if i == 0 {
+                        self.translated_lyric.clone()
+                    } else {
+                        Cow::Borrowed("")
+                    },
+                    roman_lyric: if i == 0 {
+                        self.roman_lyric.clone()
+                    } else {
+                        Cow::Borrowed("")
+                    },
+                    is_bg: self.is_bg,
+                    is_duet,
+                    agent,
+                    start_time: start_time.unwrap_or(self.start_time),
+                    end_time: end_time.unwrap_or(self.end_time),
+                }
+            })
+            .collect()
+    }
 }
 
 impl LyricLineOwned {
@@ -173,6 +465,7 @@ impl LyricLineOwned {
             roman_lyric: self.roman_lyric.as_str().into(),
             is_bg: self.is_bg,
             is_duet: self.is_duet,
+            agent: self.agent.as_str().into(),
             start_time: self.start_time,
             end_time: self.end_time,
         }
@@ -189,4 +482,9 @@ impl LyricLineOwned {
     pub fn is_empty(&self) -> bool {
         self.words.is_empty() || self.words.iter().all(|x| x.is_empty())
     }
+
+    /// 演唱该行的演唱者标识，未指定时为 [`MAIN_AGENT`] 或 [`DUET_AGENT`]
+    pub fn agent(&self) -> &str {
+        line_agent(&self.agent, self.is_duet)
+    }
 }
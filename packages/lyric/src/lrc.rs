@@ -84,7 +84,7 @@ pub fn parse_line(src: &str) -> IResult<&str, Vec<LyricLine<'_>>> {
                             start_time: t,
                             end_time: 0,
                             word: Cow::Borrowed(line),
-                            roman_word: std::borrow::Cow::Borrowed(""),
+                            ..Default::default()
                         }],
                         start_time: t,
                         end_time: 0,
@@ -105,7 +105,7 @@ pub fn parse_line(src: &str) -> IResult<&str, Vec<LyricLine<'_>>> {
                         start_time: t,
                         end_time: 0,
                         word: Cow::Borrowed(input),
-                        roman_word: std::borrow::Cow::Borrowed(""),
+                        ..Default::default()
                     }],
                     start_time: t,
                     ..Default::default()
@@ -127,7 +127,7 @@ fn lyric_line_test() {
                     start_time: 1120,
                     end_time: 0,
                     word: Cow::Borrowed(" test LyRiC"),
-                    roman_word: std::borrow::Cow::Borrowed(""),
+                    ..Default::default()
                 }],
                 start_time: 1120,
                 ..Default::default()
@@ -145,7 +145,7 @@ fn lyric_line_test() {
                         start_time: 10254,
                         end_time: 0,
                         word: Cow::Borrowed(" sssxxx"),
-                        roman_word: std::borrow::Cow::Borrowed(""),
+                        ..Default::default()
                     }],
                     start_time: 10254,
                     ..Default::default()
@@ -155,7 +155,7 @@ fn lyric_line_test() {
                         start_time: 10254,
                         end_time: 0,
                         word: Cow::Borrowed(" sssxxx"),
-                        roman_word: std::borrow::Cow::Borrowed(""),
+                        ..Default::default()
                     }],
                     start_time: 10254,
                     ..Default::default()
@@ -172,7 +172,7 @@ fn lyric_line_test() {
                     start_time: 70100,
                     end_time: 0,
                     word: Cow::Borrowed(""),
-                    roman_word: std::borrow::Cow::Borrowed(""),
+                    ..Default::default()
                 }],
                 start_time: 70100,
                 ..Default::default()
@@ -188,7 +188,7 @@ fn lyric_line_test() {
                     start_time: 26650,
                     end_time: 0,
                     word: Cow::Borrowed(""),
-                    roman_word: std::borrow::Cow::Borrowed(""),
+                    ..Default::default()
                 }],
                 start_time: 26650,
                 ..Default::default()
@@ -259,17 +259,28 @@ fn stringify_lrc_test() {
     );
 }
 
+/// 解析 LyRiC 格式的歌词字符串
 #[cfg(all(target_arch = "wasm32", feature = "serde"))]
-#[wasm_bindgen(js_name = "parseLrc", skip_typescript)]
-pub fn parse_lrc_js(src: &str) -> JsValue {
-    serde_wasm_bindgen::to_value(&parse_lrc(src)).unwrap()
+#[wasm_bindgen(
+    js_name = "parseLrc",
+    unchecked_return_type = "LyricLine[]",
+    return_description = "成功解析出来的歌词"
+)]
+pub fn parse_lrc_js(
+    #[wasm_bindgen(param_description = "歌词字符串")] src: &str,
+) -> Result<JsValue, JsError> {
+    crate::utils::to_js(&parse_lrc(src))
 }
 
+/// 将歌词数组转换为 LyRiC 格式的字符串
 #[cfg(all(target_arch = "wasm32", feature = "serde"))]
-#[wasm_bindgen(js_name = "stringifyLrc", skip_typescript)]
-pub fn stringify_lrc_js(lrc: JsValue) -> String {
-    let lines: Vec<LyricLine> = serde_wasm_bindgen::from_value(lrc).unwrap();
-    stringify_lrc(&lines)
+#[wasm_bindgen(js_name = "stringifyLrc", return_description = "LyRiC 格式的字符串")]
+pub fn stringify_lrc_js(
+    #[wasm_bindgen(unchecked_param_type = "LyricLine[]", param_description = "歌词数组")]
+    lines: JsValue,
+) -> Result<String, JsError> {
+    let lines: Vec<LyricLine> = crate::utils::from_js(lines)?;
+    Ok(stringify_lrc(&lines))
 }
 
 #[test]
@@ -81,7 +81,7 @@ pub fn parse_word(src: &str) -> IResult<&str, LyricWord<'_>> {
                     start_time,
                     end_time: start_time + duration,
                     word: Cow::Borrowed(&src[..i]),
-                    roman_word: std::borrow::Cow::Borrowed(""),
+                    ..Default::default()
                 },
             ));
         }
@@ -160,7 +160,8 @@ pub fn stringify_lys(lines: &[LyricLine]) -> String {
         .sum();
     let mut result = String::with_capacity(capacity);
 
-    for line in lines {
+    // LYS 只能表达整行的演唱者，中途切换演唱者的歌词行需要拆分
+    for line in lines.iter().flat_map(LyricLine::split_by_agent) {
         if !line.words.is_empty() {
             let prop = match (line.is_bg, line.is_duet) {
                 (false, false) => "[0]",
@@ -182,17 +183,31 @@ pub fn stringify_lys(lines: &[LyricLine]) -> String {
     result
 }
 
+/// 解析 Lyricify Syllable 格式的歌词字符串
 #[cfg(all(target_arch = "wasm32", feature = "serde"))]
-#[wasm_bindgen(js_name = "parseLys", skip_typescript)]
-pub fn parse_lys_js(src: &str) -> JsValue {
-    serde_wasm_bindgen::to_value(&parse_lys(src)).unwrap()
+#[wasm_bindgen(
+    js_name = "parseLys",
+    unchecked_return_type = "LyricLine[]",
+    return_description = "成功解析出来的歌词"
+)]
+pub fn parse_lys_js(
+    #[wasm_bindgen(param_description = "歌词字符串")] src: &str,
+) -> Result<JsValue, JsError> {
+    crate::utils::to_js(&parse_lys(src))
 }
 
+/// 将歌词数组转换为 Lyricify Syllable 格式的字符串
 #[cfg(all(target_arch = "wasm32", feature = "serde"))]
-#[wasm_bindgen(js_name = "stringifyLys", skip_typescript)]
-pub fn stringify_lys_js(lrc: JsValue) -> String {
-    let lines: Vec<LyricLine> = serde_wasm_bindgen::from_value(lrc).unwrap();
-    stringify_lys(&lines)
+#[wasm_bindgen(
+    js_name = "stringifyLys",
+    return_description = "Lyricify Syllable 格式的字符串"
+)]
+pub fn stringify_lys_js(
+    #[wasm_bindgen(unchecked_param_type = "LyricLine[]", param_description = "歌词数组")]
+    lines: JsValue,
+) -> Result<String, JsError> {
+    let lines: Vec<LyricLine> = crate::utils::from_js(lines)?;
+    Ok(stringify_lys(&lines))
 }
 
 #[test]
@@ -214,3 +229,41 @@ fn test_props() {
         stringify_lys(&parse_lys("[8]Test(1234,567)"))
     );
 }
+
+#[test]
+fn test_split_by_agent() {
+    let mut line = parse_line("[0]I (1000,500)you (1500,500)we(2000,500)")
+        .unwrap()
+        .1;
+    line.words[1].agent = "v2".into();
+    assert_eq!(
+        stringify_lys(&[line]),
+        "[0]I (1000,500)\n[2]you (1500,500)\n[0]we(2000,500)\n"
+    );
+
+    let mut line = parse_line("[2]I (1000,500)you (1500,500)we(2000,500)")
+        .unwrap()
+        .1;
+    line.words[0].agent = "v2".into();
+    line.words[1].agent = "v1".into();
+    line.words[2].agent = "v3".into();
+    let segments = line.split_by_agent();
+    assert_eq!(
+        segments
+            .iter()
+            .map(|x| (x.is_duet, x.agent()))
+            .collect::<Vec<_>>(),
+        [(true, "v2"), (false, "v1"), (true, "v3")]
+    );
+    assert_eq!(
+        stringify_lys(&segments),
+        "[2]I (1000,500)\n[0]you (1500,500)\n[2]we(2000,500)\n"
+    );
+
+    let mut line = parse_line("[0]I (1000,500)you (1500,500)").unwrap().1;
+    line.words[1].agent = "v1".into();
+    assert_eq!(
+        stringify_lys(&line.split_by_agent()),
+        stringify_lys(&[line])
+    );
+}
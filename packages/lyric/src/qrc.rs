@@ -65,7 +65,7 @@ pub fn parse_word(src: &str) -> IResult<&str, LyricWord<'_>> {
                     start_time,
                     end_time: start_time + duration,
                     word: Cow::Borrowed(&src[..i]),
-                    roman_word: std::borrow::Cow::Borrowed(""),
+                    ..Default::default()
                 },
             ));
         }
@@ -158,15 +158,26 @@ pub fn stringify_qrc(lines: &[LyricLine]) -> String {
     result
 }
 
+/// 解析 QRC 格式的歌词字符串
 #[cfg(all(target_arch = "wasm32", feature = "serde"))]
-#[wasm_bindgen(js_name = "parseQrc", skip_typescript)]
-pub fn parse_qrc_js(src: &str) -> JsValue {
-    serde_wasm_bindgen::to_value(&parse_qrc(src)).unwrap()
+#[wasm_bindgen(
+    js_name = "parseQrc",
+    unchecked_return_type = "LyricLine[]",
+    return_description = "成功解析出来的歌词"
+)]
+pub fn parse_qrc_js(
+    #[wasm_bindgen(param_description = "歌词字符串")] src: &str,
+) -> Result<JsValue, JsError> {
+    crate::utils::to_js(&parse_qrc(src))
 }
 
+/// 将歌词数组转换为 QRC 格式的字符串
 #[cfg(all(target_arch = "wasm32", feature = "serde"))]
-#[wasm_bindgen(js_name = "stringifyQrc", skip_typescript)]
-pub fn stringify_qrc_js(lrc: JsValue) -> String {
-    let lines: Vec<LyricLine> = serde_wasm_bindgen::from_value(lrc).unwrap();
-    stringify_qrc(&lines)
+#[wasm_bindgen(js_name = "stringifyQrc", return_description = "QRC 格式的字符串")]
+pub fn stringify_qrc_js(
+    #[wasm_bindgen(unchecked_param_type = "LyricLine[]", param_description = "歌词数组")]
+    lines: JsValue,
+) -> Result<String, JsError> {
+    let lines: Vec<LyricLine> = crate::utils::from_js(lines)?;
+    Ok(stringify_qrc(&lines))
 }
@@ -0,0 +1,92 @@
+//! 韩文到国语罗马字（Revised Romanization of Korean）的转换
+//!
+//! 只实现了连音和 ㄹ 相关的基本音变规则，其余音变（例如鼻音化）不做处理
+
+use std::borrow::Cow;
+
+const INITIALS: [&str; 19] = [
+    "g", "kk", "n", "d", "tt", "r", "m", "b", "pp", "s", "ss", "", "j", "jj", "ch", "k", "t", "p",
+    "h",
+];
+
+const VOWELS: [&str; 21] = [
+    "a", "ae", "ya", "yae", "eo", "e", "yeo", "ye", "o", "wa", "wae", "oe", "yo", "u", "wo", "we",
+    "wi", "yu", "eu", "ui", "i",
+];
+
+/// 收音在词尾或辅音前的读法
+const FINALS: [&str; 28] = [
+    "", "k", "k", "k", "n", "n", "n", "t", "l", "k", "m", "l", "l", "l", "p", "l", "m", "p", "p",
+    "t", "t", "ng", "t", "t", "k", "t", "p", "t",
+];
+
+/// 收音在元音前连读到下一音节时的读法
+const LIAISON_FINALS: [&str; 28] = [
+    "", "g", "kk", "ks", "n", "nj", "n", "d", "r", "lg", "lm", "lb", "ls", "lt", "lp", "r", "m",
+    "b", "ps", "s", "ss", "ng", "j", "ch", "k", "t", "p", "",
+];
+
+const INITIAL_SILENT: usize = 11;
+const INITIAL_RIEUL: usize = 5;
+const FINAL_NIEUN: usize = 4;
+const FINAL_RIEUL: usize = 8;
+
+pub(super) fn is_hangul(c: char) -> bool {
+    matches!(c, '가'..='힣')
+}
+
+/// 将韩文音节拆分为初声、中声和终声的下标
+fn decompose(c: char) -> Option<(usize, usize, usize)> {
+    if !is_hangul(c) {
+        return None;
+    }
+    let index = (c as u32 - '가' as u32) as usize;
+    Some((index / 588, (index % 588) / 28, index % 28))
+}
+
+/// 逐字转换韩文音节，返回值与输入一一对应
+pub(super) fn romanize(chars: &[char]) -> Vec<Option<Cow<'static, str>>> {
+    let syllables: Vec<_> = chars.iter().map(|&c| decompose(c)).collect();
+    syllables
+        .iter()
+        .enumerate()
+        .map(|(i, syllable)| {
+            let (initial, vowel, final_) = (*syllable)?;
+            let prev = i.checked_sub(1).and_then(|i| syllables[i]);
+            let next = syllables.get(i + 1).copied().flatten();
+
+            let mut result = String::with_capacity(8);
+            result.push_str(match (prev, initial) {
+                // 上一音节的收音已经连读到这里
+                (Some((_, _, f)), INITIAL_SILENT) if f != 0 => "",
+                (Some((_, _, FINAL_RIEUL | FINAL_NIEUN)), INITIAL_RIEUL) => "l",
+                _ => INITIALS[initial],
+            });
+            result.push_str(VOWELS[vowel]);
+            result.push_str(match next {
+                Some((INITIAL_SILENT, _, _)) => LIAISON_FINALS[final_],
+                Some((INITIAL_RIEUL, _, _)) if final_ == FINAL_NIEUN => "l",
+                _ => FINALS[final_],
+            });
+            Some(Cow::Owned(result))
+        })
+        .collect()
+}
+
+#[test]
+fn test_hangul_romanize() {
+    let romanize_str = |s: &str| {
+        let chars: Vec<char> = s.chars().collect();
+        romanize(&chars)
+            .into_iter()
+            .zip(chars)
+            .map(|(x, c)| x.unwrap_or_else(|| c.to_string().into()))
+            .collect::<String>()
+    };
+    assert_eq!(romanize_str("사랑해"), "saranghae");
+    assert_eq!(romanize_str("한국어"), "hangugeo");
+    assert_eq!(romanize_str("서울"), "seoul");
+    assert_eq!(romanize_str("신라"), "silla");
+    assert_eq!(romanize_str("별빛"), "byeolbit");
+    assert_eq!(romanize_str("좋아 너무"), "joa neomu");
+}
@@ -0,0 +1,218 @@
+//! 日语假名到平文式罗马字（Hepburn）的转换
+
+use std::borrow::Cow;
+
+/// 将片假名转换成对应的平假名，其余字符原样返回
+fn to_hiragana(c: char) -> char {
+    match c {
+        'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    }
+}
+
+pub(super) fn is_kana(c: char) -> bool {
+    matches!(c, 'ぁ'..='ゖ' | 'ァ'..='ヺ' | 'ー')
+}
+
+fn kana_romaji(c: char) -> Option<&'static str> {
+    Some(match to_hiragana(c) {
+        'あ' => "a",
+        'い' => "i",
+        'う' => "u",
+        'え' => "e",
+        'お' => "o",
+        'か' => "ka",
+        'き' => "ki",
+        'く' => "ku",
+        'け' => "ke",
+        'こ' => "ko",
+        'が' => "ga",
+        'ぎ' => "gi",
+        'ぐ' => "gu",
+        'げ' => "ge",
+        'ご' => "go",
+        'さ' => "sa",
+        'し' => "shi",
+        'す' => "su",
+        'せ' => "se",
+        'そ' => "so",
+        'ざ' => "za",
+        'じ' => "ji",
+        'ず' => "zu",
+        'ぜ' => "ze",
+        'ぞ' => "zo",
+        'た' => "ta",
+        'ち' => "chi",
+        'つ' => "tsu",
+        'て' => "te",
+        'と' => "to",
+        'だ' => "da",
+        'ぢ' => "ji",
+        'づ' => "zu",
+        'で' => "de",
+        'ど' => "do",
+        'な' => "na",
+        'に' => "ni",
+        'ぬ' => "nu",
+        'ね' => "ne",
+        'の' => "no",
+        'は' => "ha",
+        'ひ' => "hi",
+        'ふ' => "fu",
+        'へ' => "he",
+        'ほ' => "ho",
+        'ば' => "ba",
+        'び' => "bi",
+        'ぶ' => "bu",
+        'べ' => "be",
+        'ぼ' => "bo",
+        'ぱ' => "pa",
+        'ぴ' => "pi",
+        'ぷ' => "pu",
+        'ぺ' => "pe",
+        'ぽ' => "po",
+        'ま' => "ma",
+        'み' => "mi",
+        'む' => "mu",
+        'め' => "me",
+        'も' => "mo",
+        'や' => "ya",
+        'ゆ' => "yu",
+        'よ' => "yo",
+        'ら' => "ra",
+        'り' => "ri",
+        'る' => "ru",
+        'れ' => "re",
+        'ろ' => "ro",
+        'わ' => "wa",
+        'ゐ' => "i",
+        'ゑ' => "e",
+        'を' => "o",
+        'ん' => "n",
+        'ぁ' => "a",
+        'ぃ' => "i",
+        'ぅ' => "u",
+        'ぇ' => "e",
+        'ぉ' => "o",
+        'ゃ' => "ya",
+        'ゅ' => "yu",
+        'ょ' => "yo",
+        'ゎ' => "wa",
+        'ゔ' => "vu",
+        'ゕ' => "ka",
+        'ゖ' => "ke",
+        _ => return None,
+    })
+}
+
+fn is_small_y(c: char) -> bool {
+    matches!(to_hiragana(c), 'ゃ' | 'ゅ' | 'ょ')
+}
+
+fn is_small_vowel(c: char) -> bool {
+    matches!(to_hiragana(c), 'ぁ' | 'ぃ' | 'ぅ' | 'ぇ' | 'ぉ')
+}
+
+/// 尝试将两个假名组合成一个拗音或外来语音节
+fn digraph(first: char, second: char) -> Option<String> {
+    let base = kana_romaji(first)?;
+    let small = kana_romaji(second)?;
+    if is_small_y(second) {
+        // きゃ → kya，しゃ → sha，じゃ → ja
+        let stem = base.strip_suffix('i')?;
+        if stem.is_empty() {
+            return None;
+        }
+        if matches!(stem, "sh" | "ch" | "j") {
+            Some(format!("{stem}{}", &small[1..]))
+        } else {
+            Some(format!("{stem}{small}"))
+        }
+    } else if is_small_vowel(second) {
+        // ふぁ → fa，てぃ → ti，うぃ → wi，しぇ → she
+        let stem = base.trim_end_matches(['a', 'i', 'u', 'e', 'o']);
+        let stem = match stem {
+            "" => "w",
+            "sh" | "ch" | "j" | "f" | "v" | "ts" | "t" | "d" => stem,
+            _ => return None,
+        };
+        Some(format!("{stem}{small}"))
+    } else {
+        None
+    }
+}
+
+/// 逐字转换假名，返回值与输入一一对应
+///
+/// 拗音等由多个假名组成的音节会放在第一个假名上，其余假名对应空字符串
+pub(super) fn romanize(chars: &[char]) -> Vec<Option<Cow<'static, str>>> {
+    let mut result: Vec<Option<Cow<'static, str>>> = vec![None; chars.len()];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if let Some(&next) = chars.get(i + 1)
+            && let Some(syllable) = digraph(c, next)
+        {
+            result[i] = Some(syllable.into());
+            result[i + 1] = Some("".into());
+            i += 2;
+            continue;
+        }
+        result[i] = match to_hiragana(c) {
+            'ー' => result[..i]
+                .iter()
+                .rev()
+                .flatten()
+                .find_map(|x| x.chars().last())
+                .filter(|x| matches!(x, 'a' | 'i' | 'u' | 'e' | 'o'))
+                .map(|x| x.to_string().into()),
+            _ => kana_romaji(c).map(Cow::Borrowed),
+        };
+        i += 1;
+    }
+
+    // 促音和拨音的读法取决于后一个音节，需要在所有音节确定后再处理
+    for i in 0..chars.len() {
+        let next = result[i + 1..]
+            .iter()
+            .take_while(|x| x.is_some())
+            .flatten()
+            .find(|x| !x.is_empty());
+        match to_hiragana(chars[i]) {
+            'っ' => {
+                let doubled = match next.and_then(|x| x.chars().next()) {
+                    Some('c') => "t".to_string(),
+                    Some(c) if !matches!(c, 'a' | 'i' | 'u' | 'e' | 'o' | 'n') => c.to_string(),
+                    _ => String::new(),
+                };
+                result[i] = Some(doubled.into());
+            }
+            'ん' if next.is_some_and(|x| x.starts_with(['a', 'i', 'u', 'e', 'o', 'y'])) => {
+                result[i] = Some("n'".into());
+            }
+            _ => {}
+        }
+    }
+    result
+}
+
+#[test]
+fn test_kana_romanize() {
+    let romanize_str = |s: &str| {
+        let chars: Vec<char> = s.chars().collect();
+        romanize(&chars)
+            .into_iter()
+            .map(|x| x.unwrap_or_default())
+            .collect::<String>()
+    };
+    assert_eq!(romanize_str("こんにちは"), "konnichiha");
+    assert_eq!(romanize_str("きゃりーぱみゅぱみゅ"), "kyariipamyupamyu");
+    assert_eq!(romanize_str("しゃしん"), "shashin");
+    assert_eq!(romanize_str("きって"), "kitte");
+    assert_eq!(romanize_str("まっちゃ"), "matcha");
+    assert_eq!(romanize_str("ラーメン"), "raamen");
+    assert_eq!(romanize_str("ファンタジー"), "fantajii");
+    assert_eq!(romanize_str("パーティー"), "paatii");
+    assert_eq!(romanize_str("きんようび"), "kin'youbi");
+    assert_eq!(romanize_str("じゅう"), "juu");
+}
@@ -0,0 +1,290 @@
+//! 离线音译生成模块
+//!
+//! 使用内置的读音表为歌词生成音译，不依赖任何在线服务，支持：
+//!
+//! - 普通话拼音（带声调符号、声调数字或不带声调）
+//! - 粤语粤拼，内置读音表只收录常用字，未收录的字会原样保留
+//! - 日语假名到平文式罗马字，汉字（日文中的汉字）会原样保留
+//! - 韩文到国语罗马字
+//!
+//! 生成的逐词音译会和原有的单词拆分一一对应，写入 [`LyricWord::roman_word`]，
+//! 整行音译写入 [`LyricLine::roman_lyric`]。
+use std::{borrow::Cow, collections::HashMap, sync::LazyLock};
+
+use pinyin::ToPinyin;
+
+use crate::LyricLine;
+#[cfg(test)]
+use crate::LyricWord;
+
+mod hangul;
+mod kana;
+
+/// 拼音的声调表示方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PinyinTone {
+    /// 使用声调符号，例如 `zhōng`
+    #[default]
+    Marks,
+    /// 在音节末尾使用声调数字，例如 `zhong1`
+    Numbers,
+    /// 不标注声调，例如 `zhong`
+    None,
+}
+
+/// 音译方案
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RomanizeScheme {
+    /// 普通话汉语拼音
+    Pinyin(PinyinTone),
+    /// 粤语粤拼（香港语言学学会粤语拼音方案）
+    Jyutping,
+    /// 日语平文式罗马字
+    Romaji,
+    /// 韩文国语罗马字
+    RevisedRomanization,
+}
+
+impl RomanizeScheme {
+    /// 根据文本中出现的文字猜测合适的音译方案
+    ///
+    /// 出现假名时认为是日语，出现韩文时认为是韩语，只有汉字时认为是普通话。
+    /// 粤语无法和普通话区分，需要手动指定。
+    pub fn detect(text: &str) -> Option<Self> {
+        let mut has_han = false;
+        for c in text.chars() {
+            if kana::is_kana(c) {
+                return Some(Self::Romaji);
+            } else if hangul::is_hangul(c) {
+                return Some(Self::RevisedRomanization);
+            }
+            has_han |= c.to_pinyin().is_some();
+        }
+        has_han.then_some(Self::Pinyin(PinyinTone::Marks))
+    }
+
+    /// 音节之间是否需要以空格分隔
+    fn separate_syllables(self) -> bool {
+        matches!(self, Self::Pinyin(_) | Self::Jyutping)
+    }
+
+    /// 逐字生成音译，返回值与输入一一对应
+    ///
+    /// `None` 代表该字无法音译，应保留原文；空字符串代表该字的读音已经合并到前一个字中
+    fn romanize_chars(self, chars: &[char]) -> Vec<Option<Cow<'static, str>>> {
+        match self {
+            Self::Pinyin(tone) => chars
+                .iter()
+                .map(|c| {
+                    let pinyin = c.to_pinyin()?;
+                    Some(Cow::Borrowed(match tone {
+                        PinyinTone::Marks => pinyin.with_tone(),
+                        PinyinTone::Numbers => pinyin.with_tone_num_end(),
+                        PinyinTone::None => pinyin.plain(),
+                    }))
+                })
+                .collect(),
+            Self::Jyutping => chars
+                .iter()
+                .map(|c| JYUTPING_TABLE.get(c).map(|x| Cow::Borrowed(*x)))
+                .collect(),
+            Self::Romaji => kana::romanize(chars),
+            Self::RevisedRomanization => hangul::romanize(chars),
+        }
+    }
+}
+
+static JYUTPING_TABLE: LazyLock<HashMap<char, &'static str>> = LazyLock::new(|| {
+    let mut table = HashMap::with_capacity(4096);
+    for line in include_str!("jyutping.txt").lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+        if let Some((jyutping, chars)) = line.split_once('\t') {
+            for c in chars.chars() {
+                table.entry(c).or_insert(jyutping);
+            }
+        }
+    }
+    table
+});
+
+/// 将逐字音译结果拼接成文本，返回拼接结果和是否有任何字被音译
+fn join_syllables(
+    chars: &[char],
+    syllables: &[Option<Cow<'static, str>>],
+    separate: bool,
+) -> (String, bool) {
+    let mut result = String::with_capacity(chars.len() * 4);
+    let mut romanized = false;
+    let mut last_is_syllable = false;
+    for (c, syllable) in chars.iter().zip(syllables) {
+        match syllable {
+            Some(syllable) if syllable.is_empty() => {}
+            Some(syllable) => {
+                if separate && !result.is_empty() && !result.ends_with(char::is_whitespace) {
+                    result.push(' ');
+                }
+                result.push_str(syllable);
+                romanized = true;
+                last_is_syllable = true;
+            }
+            None => {
+                if separate && last_is_syllable && !c.is_whitespace() {
+                    result.push(' ');
+                }
+                result.push(*c);
+                last_is_syllable = false;
+            }
+        }
+    }
+    (result, romanized)
+}
+
+/// 为一段文本生成音译
+///
+/// 无法音译的字符会原样保留
+pub fn romanize_text(text: &str, scheme: RomanizeScheme) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let syllables = scheme.romanize_chars(&chars);
+    join_syllables(&chars, &syllables, scheme.separate_syllables()).0
+}
+
+/// 为一行歌词生成逐词音译和整行音译
+///
+/// 只会填充原本为空的 `roman_word` 和 `roman_lyric`，已有的音译不会被覆盖。
+/// 不包含任何可音译文字的单词不会生成逐词音译。
+pub fn romanize_line(line: &mut LyricLine<'_>, scheme: RomanizeScheme) {
+    // 以整行为单位转换，这样跨越单词边界的音变（例如促音和连音）也能正确处理
+    let chars: Vec<char> = line.words.iter().flat_map(|w| w.word.chars()).collect();
+    let syllables = scheme.romanize_chars(&chars);
+    let separate = scheme.separate_syllables();
+
+    let mut line_roman = String::with_capacity(chars.len() * 4);
+    let mut line_romanized = false;
+    let mut offset = 0;
+    for word in line.words.iter_mut() {
+        let len = word.word.chars().count();
+        let range = offset..offset + len;
+        offset += len;
+
+        let (word_roman, romanized) =
+            join_syllables(&chars[range.clone()], &syllables[range], separate);
+        if romanized {
+            line_romanized = true;
+            if word.roman_word.is_empty() {
+                word.roman_word = Cow::Owned(word_roman.trim().to_owned());
+            }
+        }
+
+        if separate
+            && !line_roman.is_empty()
+            && !line_roman.ends_with(char::is_whitespace)
+            && !word_roman.starts_with(char::is_whitespace)
+        {
+            line_roman.push(' ');
+        }
+        line_roman.push_str(&word_roman);
+    }
+
+    if line_romanized && line.roman_lyric.is_empty() {
+        line.roman_lyric = line_roman
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .into();
+    }
+}
+
+/// 为所有歌词行生成音译，规则同 [`romanize_line`]
+pub fn romanize_lines(lines: &mut [LyricLine<'_>], scheme: RomanizeScheme) {
+    for line in lines.iter_mut() {
+        romanize_line(line, scheme);
+    }
+}
+
+#[cfg(test)]
+fn test_line(words: &[&'static str]) -> LyricLine<'static> {
+    LyricLine {
+        words: words
+            .iter()
+            .map(|&word| LyricWord {
+                word: Cow::Borrowed(word),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_romanize_pinyin() {
+    let mut line = test_line(&["我", "爱", "你 ", "baby"]);
+    romanize_line(&mut line, RomanizeScheme::Pinyin(PinyinTone::Marks));
+    let roman_words: Vec<_> = line.words.iter().map(|w| w.roman_word.as_ref()).collect();
+    assert_eq!(roman_words, vec!["wǒ", "ài", "nǐ", ""]);
+    assert_eq!(line.roman_lyric, "wǒ ài nǐ baby");
+
+    assert_eq!(
+        romanize_text("中文歌词", RomanizeScheme::Pinyin(PinyinTone::Numbers)),
+        "zhong1 wen2 ge1 ci2"
+    );
+    assert_eq!(
+        romanize_text("中文歌词", RomanizeScheme::Pinyin(PinyinTone::None)),
+        "zhong wen ge ci"
+    );
+}
+
+#[test]
+fn test_romanize_jyutping() {
+    let mut line = test_line(&["我", "哋", "一", "齊", "唱"]);
+    romanize_line(&mut line, RomanizeScheme::Jyutping);
+    assert_eq!(line.roman_lyric, "ngo5 dei6 jat1 cai4 coeng3");
+    assert_eq!(line.words[3].roman_word, "cai4");
+}
+
+#[test]
+fn test_romanize_romaji() {
+    // 促音跨越了单词边界
+    let mut line = test_line(&["ず", "っ", "と", " ", "君", "を"]);
+    romanize_line(&mut line, RomanizeScheme::Romaji);
+    let roman_words: Vec<_> = line.words.iter().map(|w| w.roman_word.as_ref()).collect();
+    assert_eq!(roman_words, vec!["zu", "t", "to", "", "", "o"]);
+    assert_eq!(line.roman_lyric, "zutto 君o");
+}
+
+#[test]
+fn test_romanize_hangul() {
+    let mut line = test_line(&["사랑", "해 ", "너를"]);
+    romanize_line(&mut line, RomanizeScheme::RevisedRomanization);
+    assert_eq!(line.roman_lyric, "saranghae neoreul");
+    assert_eq!(line.words[2].roman_word, "neoreul");
+}
+
+#[test]
+fn test_romanize_keep_existing() {
+    let mut line = test_line(&["你", "好"]);
+    line.words[0].roman_word = Cow::Borrowed("lei");
+    line.roman_lyric = Cow::Borrowed("lei hou");
+    romanize_line(&mut line, RomanizeScheme::Pinyin(PinyinTone::Marks));
+    assert_eq!(line.words[0].roman_word, "lei");
+    assert_eq!(line.words[1].roman_word, "hǎo");
+    assert_eq!(line.roman_lyric, "lei hou");
+}
+
+#[test]
+fn test_detect_scheme() {
+    assert_eq!(
+        RomanizeScheme::detect("君の名は"),
+        Some(RomanizeScheme::Romaji)
+    );
+    assert_eq!(
+        RomanizeScheme::detect("사랑해"),
+        Some(RomanizeScheme::RevisedRomanization)
+    );
+    assert_eq!(
+        RomanizeScheme::detect("晴天"),
+        Some(RomanizeScheme::Pinyin(PinyinTone::Marks))
+    );
+    assert_eq!(RomanizeScheme::detect("hello"), None);
+}
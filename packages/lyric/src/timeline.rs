@@ -0,0 +1,396 @@
+//! 歌词时间轴索引
+//!
+//! 渲染器和播放器每一帧都需要知道“当前时间下有哪些歌词行处于激活状态”，
+//! 以及行内正在播放的单词，此模块预先把歌词行按时间建立索引，
+//! 使这些查询大多能通过二分查找完成。
+//!
+//! 背景歌词行的归组规则与渲染器一致：紧跟在一个普通歌词行后面的背景歌词行
+//! 会和该普通行组成一组，组的时间范围即主歌词行的时间范围；其他背景歌词行不属于任何组，
+//! 渲染器也不会显示它们。
+//!
+//! 没有结束时间的歌词行（例如 LRC 的最后一行，结束时间为 `u64::MAX`）
+//! 会在下一组开始时结束，避免一个组一直处于激活状态。
+
+use crate::{LyricLine, LyricLineOwned, utils::MAX_TIME};
+
+/// 一组同时显示的歌词行，由一个主歌词行和可选的背景歌词行组成
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LyricLineGroup {
+    /// 主歌词行在原歌词数组中的下标
+    pub main: usize,
+    /// 附属于主歌词行的背景歌词行在原歌词数组中的下标
+    pub bg: Option<usize>,
+    /// 该组的开始时间，即主歌词行的开始时间
+    pub start_time: u64,
+    /// 该组的结束时间，即主歌词行的结束时间，没有结束时间时为下一组的开始时间
+    pub end_time: u64,
+}
+
+impl LyricLineGroup {
+    /// 该组在指定时间是否处于激活状态，时间范围为左闭右开区间
+    pub fn is_active(&self, time: u64) -> bool {
+        self.start_time <= time && time < self.end_time
+    }
+
+    /// 按顺序迭代该组内的所有歌词行下标
+    pub fn lines(&self) -> impl Iterator<Item = usize> + '_ {
+        std::iter::once(self.main).chain(self.bg)
+    }
+}
+
+/// 不可变的歌词时间轴索引
+///
+/// 索引内只保存时间信息和下标，所有查询结果都是原歌词数组中的下标，
+/// 因此构建后原歌词数组不应再被修改，否则需要重新构建。
+#[derive(Debug, Clone, Default)]
+pub struct LyricTimeline {
+    /// 按开始时间排序的歌词行组
+    groups: Vec<LyricLineGroup>,
+    /// `groups[..=i]` 中最晚的结束时间，用于在重叠的组中提前结束查找
+    max_end_times: Vec<u64>,
+    /// 每个歌词行所属的组在 `groups` 中的下标，不属于任何组的背景歌词行为 `None`
+    line_groups: Vec<Option<usize>>,
+    /// 所有歌词行的单词时间，按歌词行顺序平铺存放
+    word_times: Vec<(u64, u64)>,
+    /// 每个歌词行的单词在 `word_times` 中的起始位置，末尾额外存放总长度
+    word_offsets: Vec<usize>,
+}
+
+impl LyricTimeline {
+    /// 从歌词行数组构建时间轴索引
+    pub fn new(lines: &[LyricLine<'_>]) -> Self {
+        Self::build(lines.iter().map(|line| {
+            (
+                line.is_bg,
+                line.start_time,
+                line.end_time,
+                line.words.iter().map(|w| (w.start_time, w.end_time)),
+            )
+        }))
+    }
+
+    /// 从所有权歌词行数组构建时间轴索引
+    pub fn from_owned(lines: &[LyricLineOwned]) -> Self {
+        Self::build(lines.iter().map(|line| {
+            (
+                line.is_bg,
+                line.start_time,
+                line.end_time,
+                line.words.iter().map(|w| (w.start_time, w.end_time)),
+            )
+        }))
+    }
+
+    fn build<W: Iterator<Item = (u64, u64)>>(
+        lines: impl Iterator<Item = (bool, u64, u64, W)>,
+    ) -> Self {
+        let mut result = Self::default();
+        result.word_offsets.push(0);
+        let mut last_is_bg = true;
+
+        for (i, (is_bg, start_time, end_time, words)) in lines.enumerate() {
+            result.word_times.extend(words);
+            result.word_offsets.push(result.word_times.len());
+
+            // 背景歌词行只会附属于紧邻的上一行，且上一行必须是普通歌词行，
+            // 否则和渲染器一样跳过该行
+            let attach_to_last = is_bg && !last_is_bg;
+            last_is_bg = is_bg;
+            if is_bg {
+                let last = result.groups.last_mut().filter(|_| attach_to_last);
+                if let Some(last) = last {
+                    last.bg = Some(i);
+                }
+                result
+                    .line_groups
+                    .push(attach_to_last.then(|| result.groups.len() - 1));
+                continue;
+            }
+
+            result.groups.push(LyricLineGroup {
+                main: i,
+                bg: None,
+                start_time,
+                end_time,
+            });
+            result.line_groups.push(Some(result.groups.len() - 1));
+        }
+
+        // 按开始时间稳定排序，并修正每行所属组的下标
+        let mut order: Vec<usize> = (0..result.groups.len()).collect();
+        order.sort_by_key(|&i| result.groups[i].start_time);
+        let mut new_index = vec![0; order.len()];
+        for (new_i, &old_i) in order.iter().enumerate() {
+            new_index[old_i] = new_i;
+        }
+        result.groups = order.iter().map(|&i| result.groups[i]).collect();
+        for group_i in result.line_groups.iter_mut().flatten() {
+            *group_i = new_index[*group_i];
+        }
+
+        // 没有结束时间的组截断到之后第一个开始时间更晚的组的开始时间，
+        // 否则查找激活的组时需要扫描它之后的所有组
+        for i in 0..result.groups.len() {
+            let group = result.groups[i];
+            let next = result
+                .groups
+                .partition_point(|g| g.start_time <= group.start_time);
+            if group.end_time >= MAX_TIME
+                && let Some(next) = result.groups.get(next)
+            {
+                result.groups[i].end_time = next.start_time;
+            }
+        }
+
+        let mut max_end = 0;
+        result.max_end_times = result
+            .groups
+            .iter()
+            .map(|group| {
+                max_end = max_end.max(group.end_time);
+                max_end
+            })
+            .collect();
+
+        result
+    }
+
+    /// 所有歌词行组，按开始时间排序
+    pub fn groups(&self) -> &[LyricLineGroup] {
+        &self.groups
+    }
+
+    /// 索引中的歌词行数量
+    pub fn line_count(&self) -> usize {
+        self.line_groups.len()
+    }
+
+    /// 获取指定歌词行所属的组，不属于任何组的背景歌词行返回 `None`
+    pub fn group_of_line(&self, line: usize) -> Option<&LyricLineGroup> {
+        self.line_groups
+            .get(line)
+            .copied()
+            .flatten()
+            .map(|i| &self.groups[i])
+    }
+
+    /// 获取指定时间下所有处于激活状态的歌词行组，按开始时间排序
+    ///
+    /// 先二分查找已经开始的组，再从后往前扫描到所有组都已结束为止。
+    /// 没有结束时间的组在构建时已被截断，因此只需扫描与该时间重叠的组，
+    /// 复杂度为 O(log n + k)，k 为重叠的组数量
+    pub fn active_groups(&self, time: u64) -> Vec<&LyricLineGroup> {
+        let end = self.groups.partition_point(|g| g.start_time <= time);
+        let mut result = Vec::new();
+        for i in (0..end).rev() {
+            if self.max_end_times[i] <= time {
+                break;
+            }
+            if self.groups[i].is_active(time) {
+                result.push(&self.groups[i]);
+            }
+        }
+        result.reverse();
+        result
+    }
+
+    /// 获取指定时间下所有处于激活状态的歌词行下标，背景歌词行紧跟在其主歌词行之后
+    pub fn active_lines(&self, time: u64) -> Vec<usize> {
+        self.active_groups(time)
+            .into_iter()
+            .flat_map(|g| g.lines())
+            .collect()
+    }
+
+    /// 获取指定时间之后第一个开始的歌词行组
+    pub fn next_group(&self, time: u64) -> Option<&LyricLineGroup> {
+        let i = self.groups.partition_point(|g| g.start_time <= time);
+        self.groups.get(i)
+    }
+
+    /// 获取指定时间之后第一个开始的主歌词行下标
+    pub fn next_line(&self, time: u64) -> Option<usize> {
+        self.next_group(time).map(|g| g.main)
+    }
+
+    fn words_of_line(&self, line: usize) -> &[(u64, u64)] {
+        match (self.word_offsets.get(line), self.word_offsets.get(line + 1)) {
+            (Some(&start), Some(&end)) => &self.word_times[start..end],
+            _ => &[],
+        }
+    }
+
+    /// 获取指定歌词行在指定时间下正在播放的单词下标
+    ///
+    /// 单词需要按开始时间排序，如果该时间处于两个单词之间的空隙则返回 `None`
+    pub fn active_word(&self, line: usize, time: u64) -> Option<usize> {
+        let words = self.words_of_line(line);
+        let i = words.partition_point(|w| w.0 <= time).checked_sub(1)?;
+        if time < words[i].1 { Some(i) } else { None }
+    }
+
+    /// 获取指定单词在指定时间下的播放进度，范围为 `0.0..=1.0`
+    ///
+    /// 在单词开始前为 `0.0`，结束后为 `1.0`，若单词不存在则返回 `None`
+    pub fn word_progress(&self, line: usize, word: usize, time: u64) -> Option<f64> {
+        let &(start_time, end_time) = self.words_of_line(line).get(word)?;
+        if time >= end_time {
+            Some(1.0)
+        } else if time <= start_time {
+            Some(0.0)
+        } else {
+            Some((time - start_time) as f64 / (end_time - start_time) as f64)
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_lines() -> Vec<LyricLine<'static>> {
+    use crate::LyricWord;
+    let word = |start_time, end_time, word| LyricWord {
+        start_time,
+        end_time,
+        word: std::borrow::Cow::Borrowed(word),
+        ..Default::default()
+    };
+    vec![
+        LyricLine {
+            words: vec![word(1000, 1500, "Hello"), word(1600, 2000, "World")],
+            start_time: 1000,
+            end_time: 2000,
+            ..Default::default()
+        },
+        LyricLine {
+            words: vec![word(1800, 2500, "(ooh)")],
+            is_bg: true,
+            start_time: 1800,
+            end_time: 2500,
+            ..Default::default()
+        },
+        LyricLine {
+            words: vec![word(2200, 3000, "Next")],
+            start_time: 2200,
+            end_time: 3000,
+            ..Default::default()
+        },
+        LyricLine {
+            words: vec![word(5000, 6000, "Last")],
+            start_time: 5000,
+            end_time: 6000,
+            ..Default::default()
+        },
+    ]
+}
+
+#[test]
+fn test_timeline_groups() {
+    let lines = test_lines();
+    let timeline = LyricTimeline::new(&lines);
+    assert_eq!(timeline.groups().len(), 3);
+    assert_eq!(
+        timeline.groups()[0],
+        LyricLineGroup {
+            main: 0,
+            bg: Some(1),
+            start_time: 1000,
+            end_time: 2000,
+        }
+    );
+    assert_eq!(timeline.group_of_line(1).unwrap().main, 0);
+    assert_eq!(timeline.line_count(), 4);
+}
+
+#[test]
+fn test_timeline_active_lines() {
+    let lines = test_lines();
+    let timeline = LyricTimeline::new(&lines);
+    assert!(timeline.active_lines(500).is_empty());
+    assert_eq!(timeline.active_lines(1000), vec![0, 1]);
+    assert_eq!(timeline.active_lines(1900), vec![0, 1]);
+    // 背景歌词行不会延长整组的激活时间
+    assert_eq!(timeline.active_lines(2300), vec![2]);
+    assert_eq!(timeline.active_lines(2500), vec![2]);
+    assert!(timeline.active_lines(4000).is_empty());
+    assert_eq!(timeline.active_lines(5999), vec![3]);
+    assert!(timeline.active_lines(6000).is_empty());
+}
+
+#[test]
+fn test_timeline_next_line() {
+    let lines = test_lines();
+    let timeline = LyricTimeline::new(&lines);
+    assert_eq!(timeline.next_line(0), Some(0));
+    assert_eq!(timeline.next_line(1000), Some(2));
+    assert_eq!(timeline.next_line(3000), Some(3));
+    assert_eq!(timeline.next_line(5000), None);
+}
+
+#[test]
+fn test_timeline_words() {
+    let lines = test_lines();
+    let timeline = LyricTimeline::new(&lines);
+    assert_eq!(timeline.active_word(0, 999), None);
+    assert_eq!(timeline.active_word(0, 1200), Some(0));
+    assert_eq!(timeline.active_word(0, 1550), None);
+    assert_eq!(timeline.active_word(0, 1600), Some(1));
+    assert_eq!(timeline.active_word(0, 2000), None);
+    assert_eq!(timeline.active_word(9, 2000), None);
+    assert_eq!(timeline.word_progress(0, 0, 900), Some(0.0));
+    assert_eq!(timeline.word_progress(0, 0, 1250), Some(0.5));
+    assert_eq!(timeline.word_progress(0, 0, 1700), Some(1.0));
+    assert_eq!(timeline.word_progress(0, 5, 1700), None);
+}
+
+#[test]
+fn test_timeline_unsorted_and_orphan_bg() {
+    let mut lines = test_lines();
+    lines.swap(2, 3);
+    lines.insert(
+        0,
+        LyricLine {
+            is_bg: true,
+            start_time: 100,
+            end_time: 200,
+            ..Default::default()
+        },
+    );
+    let timeline = LyricTimeline::new(&lines);
+    // 开头的背景歌词行和不紧跟在普通歌词行后面的背景歌词行都不属于任何组
+    let mains: Vec<_> = timeline.groups().iter().map(|g| g.main).collect();
+    assert_eq!(mains, vec![1, 4, 3]);
+    assert_eq!(timeline.group_of_line(0), None);
+    assert!(timeline.active_lines(150).is_empty());
+    assert_eq!(timeline.active_lines(1900), vec![1, 2]);
+    assert_eq!(timeline.active_lines(2300), vec![4]);
+    assert_eq!(timeline.line_count(), 5);
+
+    lines.insert(
+        3,
+        LyricLine {
+            is_bg: true,
+            start_time: 1900,
+            end_time: 2600,
+            ..Default::default()
+        },
+    );
+    let timeline = LyricTimeline::new(&lines);
+    assert_eq!(timeline.group_of_line(2).unwrap().main, 1);
+    assert_eq!(timeline.group_of_line(3), None);
+    assert_eq!(timeline.active_lines(2550), vec![5]);
+}
+
+#[test]
+fn test_timeline_unbounded_end() {
+    let line = |start_time| LyricLine {
+        start_time,
+        end_time: u64::MAX,
+        ..Default::default()
+    };
+    let lines = vec![line(1000), line(2000), line(2000), line(3000)];
+    let timeline = LyricTimeline::new(&lines);
+    let ends: Vec<_> = timeline.groups().iter().map(|g| g.end_time).collect();
+    assert_eq!(ends, vec![2000, 3000, 3000, u64::MAX]);
+    assert_eq!(timeline.active_lines(1500), vec![0]);
+    assert_eq!(timeline.active_lines(2500), vec![1, 2]);
+    assert_eq!(timeline.active_lines(u64::MAX - 1), vec![3]);
+}
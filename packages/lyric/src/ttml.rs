@@ -11,11 +11,15 @@ pub use write::*;
 #[cfg(feature = "serde")]
 use serde::*;
 
+/// 一个 TTML 歌词行对象，存储了歌词行信息和 AMLL 元数据信息
 #[derive(Debug, Default, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+#[cfg_attr(all(target_arch = "wasm32", feature = "serde"), derive(tsify::Tsify))]
 pub struct TTMLLyric<'a> {
+    /// TTML 中存储的歌词行信息
     pub lines: Vec<LyricLine<'a>>,
+    /// 一个元数据表，以 `[键, 值数组]` 的形式存储
     pub metadata: Vec<(Cow<'a, str>, Vec<Cow<'a, str>>)>,
 }
 
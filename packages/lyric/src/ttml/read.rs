@@ -8,7 +8,7 @@ use quick_xml::{
 use std::{borrow::Cow, collections::HashMap, io::BufRead};
 use thiserror::Error;
 
-use crate::{LyricLine, LyricWord};
+use crate::{LyricLine, LyricRuby, LyricWord};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum CurrentStatus {
@@ -38,6 +38,19 @@ enum CurrentStatus {
     InTtml,
 }
 
+/// 单词 span 内部的注音（`tts:ruby`）元素
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RubyRole {
+    /// 单词 span 本身就是注音容器
+    WordContainer,
+    Container,
+    Base,
+    TextContainer,
+    Text,
+    /// 没有 `tts:ruby` 属性的其他 span
+    Other,
+}
+
 #[derive(Error, Debug)]
 pub enum TTMLError {
     #[error("unexpected tt element at {0}")]
@@ -122,11 +135,20 @@ fn configure_lyric_line(
 fn configure_lyric_word(
     e: &BytesStart<'_>,
     read_len: usize,
+    line_agent: &[u8],
     word: &mut LyricWord<'_>,
 ) -> std::result::Result<(), TTMLError> {
     for attr in e.attributes() {
         match attr {
             Ok(a) => match a.key.as_ref() {
+                b"ttm:agent" => {
+                    // 只记录和所在歌词行不同的演唱者
+                    if a.value.as_ref() != line_agent
+                        && let Ok(agent) = std::str::from_utf8(&a.value)
+                    {
+                        word.agent = Cow::Owned(agent.to_owned());
+                    }
+                }
                 b"begin" => {
                     if let Ok((_, time)) = parse_timestamp(a.value.as_bytes()) {
                         word.start_time = time as _;
@@ -141,6 +163,54 @@ fn configure_lyric_word(
                         return Err(TTMLError::XmlTimeStampError(read_len));
                     }
                 }
+                b"amll:obscene" => {
+                    word.obscene = a.value.as_ref() == b"true";
+                }
+                b"amll:empty-beat" => {
+                    word.empty_beat = std::str::from_utf8(&a.value)
+                        .ok()
+                        .and_then(|x| x.trim().parse().ok())
+                        .unwrap_or_default();
+                }
+                key => {
+                    // 其他 AMLL 命名空间下的属性作为自定义单词属性保留
+                    if let Some(key) = key.strip_prefix(b"amll:")
+                        && let Ok(key) = std::str::from_utf8(key)
+                        && let Ok(value) = a.unescape_value()
+                    {
+                        word.attributes
+                            .push((Cow::Owned(key.to_owned()), Cow::Owned(value.into_owned())));
+                    }
+                }
+            },
+            Err(err) => return Err(TTMLError::XmlAttrError(read_len, err)),
+        }
+    }
+    Ok(())
+}
+
+fn configure_lyric_ruby(
+    e: &BytesStart<'_>,
+    read_len: usize,
+    ruby: &mut LyricRuby<'_>,
+) -> std::result::Result<(), TTMLError> {
+    for attr in e.attributes() {
+        match attr {
+            Ok(a) => match a.key.as_ref() {
+                b"begin" => {
+                    if let Ok((_, time)) = parse_timestamp(a.value.as_bytes()) {
+                        ruby.start_time = time as _;
+                    } else {
+                        return Err(TTMLError::XmlTimeStampError(read_len));
+                    }
+                }
+                b"end" => {
+                    if let Ok((_, time)) = parse_timestamp(a.value.as_bytes()) {
+                        ruby.end_time = time as _;
+                    } else {
+                        return Err(TTMLError::XmlTimeStampError(read_len));
+                    }
+                }
                 _ => {}
             },
             Err(err) => return Err(TTMLError::XmlAttrError(read_len, err)),
@@ -149,6 +219,32 @@ fn configure_lyric_word(
     Ok(())
 }
 
+fn ruby_role(e: &BytesStart<'_>) -> Option<RubyRole> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.as_ref() == b"tts:ruby")
+        .and_then(|a| match a.value.as_ref() {
+            b"container" => Some(RubyRole::Container),
+            b"base" => Some(RubyRole::Base),
+            b"textContainer" => Some(RubyRole::TextContainer),
+            b"text" => Some(RubyRole::Text),
+            _ => None,
+        })
+}
+
+/// 获取当前正在解析的单词
+fn current_word<'a, 'b>(
+    lines: &'b mut [LyricLine<'a>],
+    status: CurrentStatus,
+) -> Option<&'b mut LyricWord<'a>> {
+    let line = if status == CurrentStatus::InSpanInBackgroundSpan {
+        lines.iter_mut().rev().find(|x| x.is_bg)
+    } else {
+        lines.last_mut()
+    };
+    line.and_then(|x| x.words.last_mut())
+}
+
 pub fn parse_ttml<'a>(data: impl BufRead) -> std::result::Result<TTMLLyric<'a>, TTMLError> {
     let mut reader = Reader::from_reader(data);
     let mut buf: Vec<u8> = Vec::with_capacity(256);
@@ -172,6 +268,12 @@ pub fn parse_ttml<'a>(data: impl BufRead) -> std::result::Result<TTMLLyric<'a>,
     let mut current_itunes_trans_pieces: Vec<String> = Vec::new();
     // 记录每一行对应的 itunes:key，以便结束后把 pieces 分配到 word
     let mut line_key_map: Vec<(usize, Vec<u8>)> = Vec::new();
+    // 当前歌词行的演唱者
+    let mut line_agent: Vec<u8> = Vec::new();
+    // 当前单词 span 内部尚未闭合的注音元素
+    let mut ruby_stack: Vec<RubyRole> = Vec::new();
+    // 用于拼接注音文字
+    let mut ruby_buf = String::with_capacity(32);
 
     loop {
         match reader.read_event_into(&mut buf) {
@@ -279,37 +381,35 @@ pub fn parse_ttml<'a>(data: impl BufRead) -> std::result::Result<TTMLLyric<'a>,
                             return Err(TTMLError::UnexpectedMetadataElement(read_len));
                         }
                     }
-                    b"ttm:agent" => {
-                        if main_agent.is_empty() {
-                            if let CurrentStatus::InMetadata = status {
-                                let mut agent_type = Cow::Borrowed(&[] as &[u8]);
-                                let mut agent_id = Cow::Borrowed(&[] as &[u8]);
-                                for attr in e.attributes() {
-                                    match attr {
-                                        Ok(a) => match a.key.as_ref() {
-                                            b"type" => {
-                                                agent_type = a.value.clone();
-                                            }
-                                            b"xml:id" => {
-                                                agent_id = a.value.clone();
-                                            }
-                                            _ => {}
-                                        },
-                                        Err(err) => {
-                                            return Err(TTMLError::XmlAttrError(read_len, err));
+                    b"ttm:agent" if main_agent.is_empty() => {
+                        if let CurrentStatus::InMetadata = status {
+                            let mut agent_type = Cow::Borrowed(&[] as &[u8]);
+                            let mut agent_id = Cow::Borrowed(&[] as &[u8]);
+                            for attr in e.attributes() {
+                                match attr {
+                                    Ok(a) => match a.key.as_ref() {
+                                        b"type" => {
+                                            agent_type = a.value.clone();
+                                        }
+                                        b"xml:id" => {
+                                            agent_id = a.value.clone();
                                         }
+                                        _ => {}
+                                    },
+                                    Err(err) => {
+                                        return Err(TTMLError::XmlAttrError(read_len, err));
                                     }
                                 }
-                                if agent_type == &b"person"[..] {
-                                    main_agent = agent_id.into_owned();
-                                    // println!(
-                                    //     "main agent: {}",
-                                    //     std::str::from_utf8(&main_agent).unwrap()
-                                    // );
-                                }
-                            } else {
-                                return Err(TTMLError::UnexpectedTtmlAgentElement(read_len));
                             }
+                            if agent_type == &b"person"[..] {
+                                main_agent = agent_id.into_owned();
+                                // println!(
+                                //     "main agent: {}",
+                                //     std::str::from_utf8(&main_agent).unwrap()
+                                // );
+                            }
+                        } else {
+                            return Err(TTMLError::UnexpectedTtmlAgentElement(read_len));
                         }
                     }
                     b"amll:meta" => {
@@ -371,14 +471,26 @@ pub fn parse_ttml<'a>(data: impl BufRead) -> std::result::Result<TTMLLyric<'a>,
 
                             // 在配置行信息时，检查是否有 itunes:key 并查找翻译
                             let mut itunes_key: Option<Vec<u8>> = None;
+                            line_agent.clear();
                             for a in e.attributes().flatten() {
-                                if a.key.as_ref() == b"itunes:key" {
-                                    itunes_key = Some(a.value.into_owned());
-                                    break; // 找到 key 就退出
+                                match a.key.as_ref() {
+                                    b"itunes:key" => itunes_key = Some(a.value.into_owned()),
+                                    b"ttm:agent" => line_agent.extend_from_slice(&a.value),
+                                    _ => {}
                                 }
                             }
+                            if line_agent.is_empty() {
+                                line_agent.extend_from_slice(&main_agent);
+                            }
 
                             configure_lyric_line(&e, read_len, &main_agent, &mut new_line)?;
+                            // 只记录非默认的演唱者，参见 LyricLine::agent
+                            if line_agent != main_agent
+                                && line_agent != crate::DUET_AGENT.as_bytes()
+                                && let Ok(agent) = std::str::from_utf8(&line_agent)
+                            {
+                                new_line.agent = Cow::Owned(agent.to_owned());
+                            }
 
                             if let Some(key) = &itunes_key {
                                 if let Some(translation_text) = itunes_translations.get(key)
@@ -405,6 +517,27 @@ pub fn parse_ttml<'a>(data: impl BufRead) -> std::result::Result<TTMLLyric<'a>,
                             return Err(TTMLError::UnexpectedPElement(read_len));
                         }
                     }
+                    b"span"
+                        if matches!(
+                            status,
+                            CurrentStatus::InSpan | CurrentStatus::InSpanInBackgroundSpan
+                        ) =>
+                    {
+                        let role = ruby_role(&e).unwrap_or(RubyRole::Other);
+                        if role == RubyRole::Text
+                            && let Some(word) = current_word(&mut result.lines, status)
+                        {
+                            let mut ruby = LyricRuby {
+                                start_time: word.start_time,
+                                end_time: word.end_time,
+                                ..Default::default()
+                            };
+                            configure_lyric_ruby(&e, read_len, &mut ruby)?;
+                            word.ruby.push(ruby);
+                            ruby_buf.clear();
+                        }
+                        ruby_stack.push(role);
+                    }
                     b"span" => match status {
                         CurrentStatus::InP => {
                             status = CurrentStatus::InSpan;
@@ -450,8 +583,11 @@ pub fn parse_ttml<'a>(data: impl BufRead) -> std::result::Result<TTMLLyric<'a>,
                             }
                             if let CurrentStatus::InSpan = status {
                                 let mut new_word = LyricWord::default();
-                                configure_lyric_word(&e, read_len, &mut new_word)?;
+                                configure_lyric_word(&e, read_len, &line_agent, &mut new_word)?;
                                 result.lines.last_mut().unwrap().words.push(new_word);
+                                if ruby_role(&e) == Some(RubyRole::Container) {
+                                    ruby_stack.push(RubyRole::WordContainer);
+                                }
                             }
                         }
                         CurrentStatus::InBackgroundSpan => {
@@ -479,8 +615,11 @@ pub fn parse_ttml<'a>(data: impl BufRead) -> std::result::Result<TTMLLyric<'a>,
                             }
                             if let CurrentStatus::InSpanInBackgroundSpan = status {
                                 let mut new_word = LyricWord::default();
-                                configure_lyric_word(&e, read_len, &mut new_word)?;
+                                configure_lyric_word(&e, read_len, &line_agent, &mut new_word)?;
                                 result.lines.last_mut().unwrap().words.push(new_word);
+                                if ruby_role(&e) == Some(RubyRole::Container) {
+                                    ruby_stack.push(RubyRole::WordContainer);
+                                }
                             }
                         }
                         CurrentStatus::InITunesTranslationText => {}
@@ -595,9 +734,26 @@ pub fn parse_ttml<'a>(data: impl BufRead) -> std::result::Result<TTMLLyric<'a>,
                             return Err(TTMLError::UnexpectedPElement(read_len));
                         }
                     }
+                    b"span"
+                        if matches!(
+                            status,
+                            CurrentStatus::InSpan | CurrentStatus::InSpanInBackgroundSpan
+                        ) && ruby_stack
+                            .last()
+                            .is_some_and(|x| *x != RubyRole::WordContainer) =>
+                    {
+                        if ruby_stack.pop() == Some(RubyRole::Text)
+                            && let Some(ruby) = current_word(&mut result.lines, status)
+                                .and_then(|x| x.ruby.last_mut())
+                        {
+                            ruby.word = ruby_buf.clone().into();
+                            ruby_buf.clear();
+                        }
+                    }
                     b"span" => match status {
                         CurrentStatus::InSpan => {
                             status = CurrentStatus::InP;
+                            ruby_stack.clear();
                             result
                                 .lines
                                 .last_mut()
@@ -614,6 +770,7 @@ pub fn parse_ttml<'a>(data: impl BufRead) -> std::result::Result<TTMLLyric<'a>,
                         }
                         CurrentStatus::InSpanInBackgroundSpan => {
                             status = CurrentStatus::InBackgroundSpan;
+                            ruby_stack.clear();
                             // TODO: 尽可能借用而不克隆
                             result
                                 .lines
@@ -702,6 +859,20 @@ pub fn parse_ttml<'a>(data: impl BufRead) -> std::result::Result<TTMLLyric<'a>,
                     if decoded_char != '\0' {
                         // 处于各类 span 内部时，才将解码后的字符追加到 str_buf
                         match status {
+                            CurrentStatus::InSpan | CurrentStatus::InSpanInBackgroundSpan
+                                if ruby_stack.last() == Some(&RubyRole::Text) =>
+                            {
+                                ruby_buf.push(decoded_char);
+                            }
+                            CurrentStatus::InSpan | CurrentStatus::InSpanInBackgroundSpan
+                                if matches!(
+                                    ruby_stack.last(),
+                                    Some(
+                                        RubyRole::WordContainer
+                                            | RubyRole::Container
+                                            | RubyRole::TextContainer
+                                    )
+                                ) => {}
                             CurrentStatus::InSpan
                             | CurrentStatus::InTranslationSpan
                             | CurrentStatus::InRomanSpan
@@ -754,6 +925,21 @@ pub fn parse_ttml<'a>(data: impl BufRead) -> std::result::Result<TTMLLyric<'a>,
                                     ..Default::default()
                                 });
                         }
+                        CurrentStatus::InSpan | CurrentStatus::InSpanInBackgroundSpan
+                            if ruby_stack.last() == Some(&RubyRole::Text) =>
+                        {
+                            ruby_buf.push_str(&txt);
+                        }
+                        // 注音容器中的空白不属于单词
+                        CurrentStatus::InSpan | CurrentStatus::InSpanInBackgroundSpan
+                            if matches!(
+                                ruby_stack.last(),
+                                Some(
+                                    RubyRole::WordContainer
+                                        | RubyRole::Container
+                                        | RubyRole::TextContainer
+                                )
+                            ) => {}
                         CurrentStatus::InSpan
                         | CurrentStatus::InTranslationSpan
                         | CurrentStatus::InRomanSpan
@@ -872,10 +1058,18 @@ pub fn parse_ttml<'a>(data: impl BufRead) -> std::result::Result<TTMLLyric<'a>,
     Ok(result)
 }
 
+/// 解析 TTML 格式（包含 AMLL 特有属性信息）的歌词字符串
 #[cfg(all(target_arch = "wasm32", feature = "serde"))]
-#[wasm_bindgen(js_name = "parseTTML", skip_typescript)]
-pub fn parse_ttml_js(src: &str) -> JsValue {
-    serde_wasm_bindgen::to_value(&parse_ttml(src.as_bytes()).unwrap()).unwrap()
+#[wasm_bindgen(
+    js_name = "parseTTML",
+    unchecked_return_type = "TTMLLyric",
+    return_description = "成功解析出来的 TTML 歌词对象"
+)]
+pub fn parse_ttml_js(
+    #[wasm_bindgen(param_description = "歌词字符串")] src: &str,
+) -> std::result::Result<JsValue, JsError> {
+    let lyric = parse_ttml(src.as_bytes()).map_err(|err| JsError::new(&err.to_string()))?;
+    crate::utils::to_js(&lyric)
 }
 
 #[test]
@@ -1139,3 +1333,142 @@ fn test_parse_apple_music_word_by_word_lyrics() {
     assert!(line3.translated_lyric.is_empty(), "第三行不应有翻译");
     assert!(line3.roman_lyric.is_empty(), "第三行不应有音译");
 }
+
+#[test]
+fn test_parse_word_attributes() {
+    const TTML: &str = r#"<tt xmlns="http://www.w3.org/ns/ttml" xmlns:ttm="http://www.w3.org/ns/ttml#metadata" xmlns:amll="http://www.example.com/ns/amll"><head><metadata><ttm:agent type="person" xml:id="v1"/></metadata></head><body><div><p begin="00:01.000" end="00:04.000" ttm:agent="v1"><span begin="00:01.000" end="00:02.000" amll:obscene="true">damn</span> <span begin="00:02.000" end="00:04.000" amll:empty-beat="3" amll:x-style="whisper">oh</span></p></div></body></tt>"#;
+
+    let ttml_lyric = parse_ttml(TTML.as_bytes()).unwrap();
+    let words = &ttml_lyric.lines[0].words;
+    assert!(words[0].obscene);
+    assert_eq!(words[0].empty_beat, 0);
+    assert!(words[0].attributes.is_empty());
+    assert!(!words[2].obscene);
+    assert_eq!(words[2].empty_beat, 3);
+    assert_eq!(words[2].attribute("x-style"), Some("whisper"));
+
+    let ttml_str = super::stringify_ttml(&ttml_lyric).unwrap();
+    assert!(ttml_str.contains(r#"amll:obscene="true""#));
+    assert!(ttml_str.contains(r#"amll:empty-beat="3" amll:x-style="whisper""#));
+    let reparsed = parse_ttml(ttml_str.as_bytes()).unwrap();
+    assert_eq!(reparsed.lines[0].words, ttml_lyric.lines[0].words);
+
+    // 无法作为 XML 属性名的键会被跳过，而不是生成无效的 TTML
+    let mut ttml_lyric = ttml_lyric;
+    let invalid_keys = ["a b", "x=\"1\"", "<x", "a:b", "1st", "", "obscene"];
+    for key in invalid_keys {
+        ttml_lyric.lines[0].words[2]
+            .attributes
+            .push((key.into(), "bad".into()));
+    }
+    ttml_lyric.lines[0].words[2]
+        .attributes
+        .push(("歌手.名_1".into(), "ok".into()));
+    let ttml_str = super::stringify_ttml(&ttml_lyric).unwrap();
+    assert!(!ttml_str.contains("bad"));
+    let reparsed = parse_ttml(ttml_str.as_bytes()).unwrap();
+    let words = &reparsed.lines[0].words;
+    assert_eq!(words[2].attributes.len(), 2);
+    assert_eq!(words[2].attribute("歌手.名_1"), Some("ok"));
+}
+
+#[test]
+fn test_stringify_duplicate_word_attributes() {
+    const TTML: &str = r#"<tt xmlns="http://www.w3.org/ns/ttml" xmlns:amll="http://www.example.com/ns/amll"><body><div><p begin="00:01.000" end="00:02.000"><span begin="00:01.000" end="00:02.000" amll:obscene="true" amll:empty-beat="2">oh</span></p></div></body></tt>"#;
+
+    // 和内置属性同名的键以及重复的键不能生成重复的 XML 属性
+    let mut ttml_lyric = parse_ttml(TTML.as_bytes()).unwrap();
+    let word = &mut ttml_lyric.lines[0].words[0];
+    for (key, value) in [
+        ("obscene", "false"),
+        ("empty-beat", "5"),
+        ("x-style", "whisper"),
+        ("x-style", "shout"),
+    ] {
+        word.attributes.push((key.into(), value.into()));
+    }
+    let ttml_str = super::stringify_ttml(&ttml_lyric).unwrap();
+    assert_eq!(ttml_str.matches("amll:obscene=").count(), 1);
+    assert_eq!(ttml_str.matches("amll:empty-beat=").count(), 1);
+    assert_eq!(ttml_str.matches("amll:x-style=").count(), 1);
+
+    let reparsed = parse_ttml(ttml_str.as_bytes()).unwrap();
+    let word = &reparsed.lines[0].words[0];
+    assert!(word.obscene);
+    assert_eq!(word.empty_beat, 2);
+    assert_eq!(word.attributes.len(), 1);
+    assert_eq!(word.attribute("x-style"), Some("shout"));
+}
+
+#[test]
+fn test_parse_ruby() {
+    const TTML: &str = r#"<tt xmlns="http://www.w3.org/ns/ttml" xmlns:ttm="http://www.w3.org/ns/ttml#metadata" xmlns:tts="http://www.w3.org/ns/ttml#styling"><head><metadata><ttm:agent type="person" xml:id="v1"/></metadata></head><body><div><p begin="00:01.000" end="00:03.000" ttm:agent="v1"><span begin="00:01.000" end="00:02.000"><span tts:ruby="container"><span tts:ruby="base">東京</span><span tts:ruby="textContainer"><span tts:ruby="text" begin="00:01.000" end="00:01.400">とう</span><span tts:ruby="text" begin="00:01.400" end="00:02.000">きょう</span></span></span></span><span begin="00:02.000" end="00:03.000" tts:ruby="container"><span tts:ruby="base">駅</span><span tts:ruby="text">えき</span></span></p></div></body></tt>"#;
+
+    let ttml_lyric = parse_ttml(TTML.as_bytes()).unwrap();
+    let words = &ttml_lyric.lines[0].words;
+    assert_eq!(words.len(), 2);
+    assert_eq!(words[0].word, "東京");
+    assert_eq!(
+        words[0].ruby,
+        vec![
+            LyricRuby {
+                start_time: 1000,
+                end_time: 1400,
+                word: "とう".into(),
+            },
+            LyricRuby {
+                start_time: 1400,
+                end_time: 2000,
+                word: "きょう".into(),
+            },
+        ]
+    );
+    assert_eq!(words[1].word, "駅");
+    assert_eq!(
+        words[1].ruby,
+        vec![LyricRuby {
+            start_time: 2000,
+            end_time: 3000,
+            word: "えき".into(),
+        }]
+    );
+
+    let ttml_str = super::stringify_ttml(&ttml_lyric).unwrap();
+    assert!(ttml_str.contains(r#"<span tts:ruby="text">えき</span>"#));
+    let reparsed = parse_ttml(ttml_str.as_bytes()).unwrap();
+    assert_eq!(reparsed.lines[0].words, ttml_lyric.lines[0].words);
+}
+
+#[test]
+fn test_parse_word_agent() {
+    const TTML: &str = r#"<tt xmlns="http://www.w3.org/ns/ttml" xmlns:ttm="http://www.w3.org/ns/ttml#metadata"><head><metadata><ttm:agent type="person" xml:id="v1"/><ttm:agent type="person" xml:id="v2"/></metadata></head><body><div><p begin="00:01.000" end="00:04.000" ttm:agent="v1"><span begin="00:01.000" end="00:02.000" ttm:agent="v1">I</span> <span begin="00:02.000" end="00:03.000" ttm:agent="v2">you</span> <span begin="00:03.000" end="00:04.000">we</span></p></div></body></tt>"#;
+
+    let ttml_lyric = parse_ttml(TTML.as_bytes()).unwrap();
+    let words = &ttml_lyric.lines[0].words;
+    assert!(!ttml_lyric.lines[0].is_duet);
+    assert_eq!(words[0].agent, "");
+    assert_eq!(words[2].agent, "v2");
+    assert_eq!(words[4].agent, "");
+
+    let ttml_str = super::stringify_ttml(&ttml_lyric).unwrap();
+    assert!(ttml_str.contains(r#"<ttm:agent type="other" xml:id="v2"/>"#));
+    let reparsed = parse_ttml(ttml_str.as_bytes()).unwrap();
+    assert_eq!(reparsed.lines[0].words, ttml_lyric.lines[0].words);
+}
+
+#[test]
+fn test_parse_line_agent() {
+    const TTML: &str = r#"<tt xmlns="http://www.w3.org/ns/ttml" xmlns:ttm="http://www.w3.org/ns/ttml#metadata"><head><metadata><ttm:agent type="person" xml:id="v1"/><ttm:agent type="other" xml:id="v2"/><ttm:agent type="group" xml:id="v1000"/></metadata></head><body><div><p begin="00:01.000" end="00:02.000" ttm:agent="v1"><span begin="00:01.000" end="00:02.000">a</span></p><p begin="00:02.000" end="00:03.000" ttm:agent="v2"><span begin="00:02.000" end="00:03.000">b</span></p><p begin="00:03.000" end="00:04.000" ttm:agent="v1000"><span begin="00:03.000" end="00:04.000">c</span></p></div></body></tt>"#;
+
+    let ttml_lyric = parse_ttml(TTML.as_bytes()).unwrap();
+    let agents: Vec<_> = ttml_lyric.lines.iter().map(|x| x.agent()).collect();
+    assert_eq!(agents, ["v1", "v2", "v1000"]);
+    assert_eq!(ttml_lyric.lines[1].agent, "");
+    assert!(ttml_lyric.lines[2].is_duet);
+
+    let ttml_str = super::stringify_ttml(&ttml_lyric).unwrap();
+    assert!(ttml_str.contains(r#"<ttm:agent type="other" xml:id="v1000"/>"#));
+    assert!(ttml_str.contains(r#"ttm:agent="v1000""#));
+    let reparsed = parse_ttml(ttml_str.as_bytes()).unwrap();
+    assert_eq!(reparsed.lines, ttml_lyric.lines);
+}
@@ -6,18 +6,27 @@ use std::io::Cursor;
 use quick_xml::{Writer, events::*};
 
 use super::TTMLLyric;
+use crate::LyricWord;
 
 pub fn stringify_ttml(lyric: &TTMLLyric) -> Result<String, quick_xml::Error> {
     // let mut writer =
     //     Writer::new_with_indent(Cursor::new(Vec::<u8>::with_capacity(64 * 1024)), b' ', 4);
     let mut writer = Writer::new(Cursor::new(Vec::<u8>::with_capacity(64 * 1024)));
 
-    writer.write_event(Event::Start(BytesStart::new("tt").with_attributes([
+    let mut tt = BytesStart::new("tt").with_attributes([
         ("xmlns", "http://www.w3.org/ns/ttml"),
         ("xmlns:ttm", "http://www.w3.org/ns/ttml#metadata"),
         ("xmlns:amll", "http://www.example.com/ns/amll"),
         ("xmlns:itunes", "http://music.apple.com/lyric-ttml-internal"),
-    ])))?;
+    ]);
+    if lyric
+        .lines
+        .iter()
+        .any(|line| line.words.iter().any(|word| !word.ruby.is_empty()))
+    {
+        tt.push_attribute(("xmlns:tts", "http://www.w3.org/ns/ttml#styling"));
+    }
+    writer.write_event(Event::Start(tt))?;
 
     {
         writer.write_event(Event::Start(BytesStart::new("head")))?;
@@ -35,6 +44,25 @@ pub fn stringify_ttml(lyric: &TTMLLyric) -> Result<String, quick_xml::Error> {
                             .with_attributes([("type", "other"), ("xml:id", "v2")]),
                     ))?;
                 }
+                // 歌词行和单词上引用的其他演唱者也需要声明
+                let line_agents = lyric.lines.iter().map(|line| line.agent.as_ref());
+                let word_agents = lyric
+                    .lines
+                    .iter()
+                    .flat_map(|line| line.words.iter().map(|word| word.agent.as_ref()));
+                let mut other_agents: Vec<&str> = Vec::new();
+                for agent in line_agents.chain(word_agents) {
+                    let declared = matches!(agent, "" | "v1") || (has_duet && agent == "v2");
+                    if !declared && !other_agents.contains(&agent) {
+                        other_agents.push(agent);
+                    }
+                }
+                for agent in other_agents {
+                    writer.write_event(Event::Empty(
+                        BytesStart::new("ttm:agent")
+                            .with_attributes([("type", "other"), ("xml:id", agent)]),
+                    ))?;
+                }
                 for (meta_key, meta_values) in lyric.metadata.iter() {
                     for meta_value in meta_values {
                         writer.write_event(Event::Empty(
@@ -83,25 +111,12 @@ pub fn stringify_ttml(lyric: &TTMLLyric) -> Result<String, quick_xml::Error> {
                     writer.write_event(Event::Start(BytesStart::new("p").with_attributes([
                         ("begin", begin_ts.as_str()),
                         ("end", end_ts.as_str()),
-                        ("ttm:agent", if line.is_duet { "v2" } else { "v1" }),
+                        ("ttm:agent", line.agent()),
                         ("itunes:key", &format!("L{line_i}")),
                     ])))?;
 
                     for word in &line.words {
-                        let begin_ts = ms_to_timestamp(word.start_time);
-                        let end_ts = ms_to_timestamp(word.end_time);
-                        if word.word.trim().is_empty() {
-                            writer.write_event(Event::Text(BytesText::new(word.word.as_ref())))?;
-                        } else {
-                            writer.write_event(Event::Start(
-                                BytesStart::new("span").with_attributes([
-                                    ("begin", begin_ts.as_str()),
-                                    ("end", end_ts.as_str()),
-                                ]),
-                            ))?;
-                            writer.write_event(Event::Text(BytesText::new(word.word.as_ref())))?;
-                            writer.write_event(Event::End(BytesEnd::new("span")))?;
-                        }
+                        write_word(&mut writer, word)?;
                     }
 
                     if let Some(next_line) = line_it.peek() {
@@ -117,24 +132,7 @@ pub fn stringify_ttml(lyric: &TTMLLyric) -> Result<String, quick_xml::Error> {
                             ))?;
 
                             for word in &next_line.words {
-                                let begin_ts = ms_to_timestamp(word.start_time);
-                                let end_ts = ms_to_timestamp(word.end_time);
-                                if word.word.trim().is_empty() {
-                                    writer.write_event(Event::Text(BytesText::new(
-                                        word.word.as_ref(),
-                                    )))?;
-                                } else {
-                                    writer.write_event(Event::Start(
-                                        BytesStart::new("span").with_attributes([
-                                            ("begin", begin_ts.as_str()),
-                                            ("end", end_ts.as_str()),
-                                        ]),
-                                    ))?;
-                                    writer.write_event(Event::Text(BytesText::new(
-                                        word.word.as_ref(),
-                                    )))?;
-                                    writer.write_event(Event::End(BytesEnd::new("span")))?;
-                                }
+                                write_word(&mut writer, word)?;
                             }
 
                             if !next_line.translated_lyric.is_empty() {
@@ -200,11 +198,108 @@ pub fn stringify_ttml(lyric: &TTMLLyric) -> Result<String, quick_xml::Error> {
     Ok(String::from_utf8(writer.into_inner().into_inner()).unwrap())
 }
 
+/// 自定义单词属性的键能否作为 `amll:` 命名空间下的属性名写出
+///
+/// 键必须是 XML 的 NCName，即不含冒号、空白、引号和尖括号等字符，
+/// 并且不能与已有的 `amll:obscene` 和 `amll:empty-beat` 重复
+fn is_custom_attribute_name(key: &str) -> bool {
+    // XML 1.0 第五版中 NameStartChar 的定义，去掉了 NCName 不允许的冒号
+    let is_name_start_char = |c: char| {
+        matches!(c,
+            'A'..='Z' | '_' | 'a'..='z' | '\u{C0}'..='\u{D6}' | '\u{D8}'..='\u{F6}'
+            | '\u{F8}'..='\u{2FF}' | '\u{370}'..='\u{37D}' | '\u{37F}'..='\u{1FFF}'
+            | '\u{200C}'..='\u{200D}' | '\u{2070}'..='\u{218F}' | '\u{2C00}'..='\u{2FEF}'
+            | '\u{3001}'..='\u{D7FF}' | '\u{F900}'..='\u{FDCF}' | '\u{FDF0}'..='\u{FFFD}'
+            | '\u{10000}'..='\u{EFFFF}')
+    };
+    let is_name_char = |c: char| {
+        is_name_start_char(c)
+            || matches!(c,
+                '-' | '.' | '0'..='9' | '\u{B7}' | '\u{300}'..='\u{36F}' | '\u{203F}'..='\u{2040}')
+    };
+    let mut chars = key.chars();
+    chars.next().is_some_and(is_name_start_char)
+        && chars.all(is_name_char)
+        && !matches!(key, "obscene" | "empty-beat")
+}
+
+fn write_word<W: std::io::Write>(
+    writer: &mut Writer<W>,
+    word: &LyricWord<'_>,
+) -> Result<(), quick_xml::Error> {
+    if word.word.trim().is_empty() {
+        writer.write_event(Event::Text(BytesText::new(word.word.as_ref())))?;
+        return Ok(());
+    }
+    let begin_ts = ms_to_timestamp(word.start_time);
+    let end_ts = ms_to_timestamp(word.end_time);
+    let mut span = BytesStart::new("span")
+        .with_attributes([("begin", begin_ts.as_str()), ("end", end_ts.as_str())]);
+    if word.obscene {
+        span.push_attribute(("amll:obscene", "true"));
+    }
+    if word.empty_beat > 0 {
+        span.push_attribute(("amll:empty-beat", word.empty_beat.to_string().as_str()));
+    }
+    if !word.agent.is_empty() {
+        span.push_attribute(("ttm:agent", word.agent.as_ref()));
+    }
+    // 重复的键只写出最后一个值，否则生成的 XML 会包含重复的属性
+    for (i, (key, value)) in word.attributes.iter().enumerate() {
+        if is_custom_attribute_name(key) && !word.attributes[i + 1..].iter().any(|(k, _)| k == key)
+        {
+            span.push_attribute((format!("amll:{key}").as_str(), value.as_ref()));
+        }
+    }
+    if word.ruby.is_empty() {
+        writer.write_event(Event::Start(span))?;
+        writer.write_event(Event::Text(BytesText::new(word.word.as_ref())))?;
+        writer.write_event(Event::End(BytesEnd::new("span")))?;
+        return Ok(());
+    }
+
+    // 带有注音的单词，单词 span 本身作为注音容器
+    span.push_attribute(("tts:ruby", "container"));
+    writer.write_event(Event::Start(span))?;
+    writer.write_event(Event::Start(
+        BytesStart::new("span").with_attributes([("tts:ruby", "base")]),
+    ))?;
+    writer.write_event(Event::Text(BytesText::new(word.word.as_ref())))?;
+    writer.write_event(Event::End(BytesEnd::new("span")))?;
+    writer.write_event(Event::Start(
+        BytesStart::new("span").with_attributes([("tts:ruby", "textContainer")]),
+    ))?;
+    for ruby in &word.ruby {
+        let mut ruby_span = BytesStart::new("span").with_attributes([("tts:ruby", "text")]);
+        // 和单词时间相同的注音不需要单独标注时间
+        if (ruby.start_time, ruby.end_time) != (word.start_time, word.end_time) {
+            ruby_span.push_attribute(("begin", ms_to_timestamp(ruby.start_time).as_str()));
+            ruby_span.push_attribute(("end", ms_to_timestamp(ruby.end_time).as_str()));
+        }
+        writer.write_event(Event::Start(ruby_span))?;
+        writer.write_event(Event::Text(BytesText::new(ruby.word.as_ref())))?;
+        writer.write_event(Event::End(BytesEnd::new("span")))?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("span")))?;
+    writer.write_event(Event::End(BytesEnd::new("span")))?;
+    Ok(())
+}
+
+/// 将歌词数组转换为 TTML 格式（包含 AMLL 特有属性信息）的歌词字符串
 #[cfg(all(target_arch = "wasm32", feature = "serde"))]
-#[wasm_bindgen(js_name = "stringifyTTML", skip_typescript)]
-pub fn stringify_ttml_js(lrc: JsValue) -> String {
-    let lyric: TTMLLyric = serde_wasm_bindgen::from_value(lrc).unwrap();
-    stringify_ttml(&lyric).unwrap()
+#[wasm_bindgen(
+    js_name = "stringifyTTML",
+    return_description = "TTML 格式的歌词字符串"
+)]
+pub fn stringify_ttml_js(
+    #[wasm_bindgen(
+        unchecked_param_type = "TTMLLyric",
+        param_description = "TTML 歌词对象"
+    )]
+    lyric: JsValue,
+) -> Result<String, JsError> {
+    let lyric: TTMLLyric = crate::utils::from_js(lyric)?;
+    stringify_ttml(&lyric).map_err(|err| JsError::new(&err.to_string()))
 }
 
 #[test]
@@ -0,0 +1,342 @@
+//! UltraStar 卡拉 OK 歌曲文本格式（.txt）的解析和导出
+//!
+//! UltraStar 以拍为单位记录每个音节的时间和音高，时间由 `#BPM` 和 `#GAP` 头信息换算：
+//! 一拍为四分之一个 BPM 节拍，第 0 拍位于 `#GAP` 毫秒处。
+//!
+//! 对唱歌曲以 `P1`、`P2` 标记两位演唱者，`P2` 的歌词行会被视为对唱歌词行。
+//! 音符的音高会以 [`PITCH_ATTRIBUTE`] 属性保存在单词中，
+//! 金色、自由和说唱音符的类型会以 [`NOTE_TYPE_ATTRIBUTE`] 属性保存。
+
+use std::{borrow::Cow, fmt::Write};
+
+use nom::{
+    IResult, Parser,
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{char, i32 as parse_i32, i64 as parse_i64, one_of, space1},
+    combinator::eof,
+};
+
+use crate::{LyricLine, LyricWord};
+
+/// 存储音符音高的单词属性，值为以半音为单位的整数，0 为中央 C
+pub const PITCH_ATTRIBUTE: &str = "pitch";
+/// 存储音符类型的单词属性，值为 `golden`、`freestyle`、`rap` 或 `rap-golden`，普通音符没有此属性
+pub const NOTE_TYPE_ATTRIBUTE: &str = "note-type";
+
+/// 一首 UltraStar 歌曲
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UltraStarSong<'a> {
+    /// 歌曲的头信息，例如 `TITLE`、`ARTIST`，键不包含 `#` 前缀
+    pub headers: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+    /// 歌词行
+    pub lines: Vec<LyricLine<'a>>,
+}
+
+impl<'a> UltraStarSong<'a> {
+    /// 获取指定的头信息，键不区分大小写
+    pub fn header(&self, key: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_ref())
+    }
+}
+
+fn note_type_name(note_type: char) -> Option<&'static str> {
+    match note_type {
+        '*' => Some("golden"),
+        'F' => Some("freestyle"),
+        'R' => Some("rap"),
+        'G' => Some("rap-golden"),
+        _ => None,
+    }
+}
+
+fn note_type_char(name: Option<&str>) -> char {
+    match name {
+        Some("golden") => '*',
+        Some("freestyle") => 'F',
+        Some("rap") => 'R',
+        Some("rap-golden") => 'G',
+        _ => ':',
+    }
+}
+
+/// 解析一个音符行，返回音符类型、开始拍、持续拍数、音高和文字
+pub fn parse_note(src: &str) -> IResult<&str, (char, i64, i64, &str, &str)> {
+    let (src, note_type) = one_of(":*FRG")(src)?;
+    let (src, _) = space1(src)?;
+    let (src, start) = parse_i64(src)?;
+    let (src, _) = space1(src)?;
+    let (src, length) = parse_i64(src)?;
+    let (src, _) = space1(src)?;
+    let pitch_src = src;
+    let (src, _) = parse_i32(src)?;
+    let pitch = &pitch_src[..pitch_src.len() - src.len()];
+    // 音高后只有一个空格作为分隔符，其余的空白属于音节文字
+    let text = src.strip_prefix([' ', '\t']).unwrap_or(src);
+    Ok(("", (note_type, start, length, pitch, text)))
+}
+
+/// 解析一个换行标记行，返回换行所在的拍和相对模式下下一行的起始拍
+pub fn parse_line_break(src: &str) -> IResult<&str, (i64, Option<i64>)> {
+    let (src, _) = char('-')(src)?;
+    let (src, _) = nom::character::complete::space0(src)?;
+    let (src, beat) = parse_i64(src)?;
+    let (src, next) = nom::combinator::opt((space1, parse_i64).map(|x| x.1)).parse(src)?;
+    Ok((src, (beat, next)))
+}
+
+/// 解析一个演唱者标记行，返回演唱者编号，只接受 `P1` 和 `P2`
+pub fn parse_player(src: &str) -> IResult<&str, u32> {
+    let (src, _) = char('P')(src)?;
+    let (src, _) = nom::character::complete::space0(src)?;
+    let (src, player) = alt((tag("1"), tag("2"))).parse(src)?;
+    let (src, _) = eof(src)?;
+    Ok((src, player.parse().unwrap()))
+}
+
+fn parse_number(value: &str) -> Option<f64> {
+    value.trim().replace(',', ".").parse().ok()
+}
+
+fn push_line<'a>(lines: &mut Vec<LyricLine<'a>>, line: &mut LyricLine<'a>) {
+    let mut line = std::mem::replace(
+        line,
+        LyricLine {
+            is_duet: line.is_duet,
+            ..Default::default()
+        },
+    );
+    if line.words.is_empty() {
+        return;
+    }
+    line.start_time = line.words.first().unwrap().start_time;
+    line.end_time = line.words.last().unwrap().end_time;
+    lines.push(line);
+}
+
+/// 解析 UltraStar 格式的歌曲
+///
+/// 无法识别的行会被忽略，缺少 `#BPM` 时无法换算时间，返回的歌曲将没有歌词行
+pub fn parse_ultrastar(src: &str) -> UltraStarSong<'_> {
+    let mut song = UltraStarSong::default();
+    let mut bpm = 0.0;
+    let mut gap = 0.0;
+    let mut relative = false;
+    // 相对模式下当前行的起始拍
+    let mut offset = 0;
+    let mut current = LyricLine::default();
+
+    for line in src.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim_end_matches('\r');
+        if let Some(header) = line.strip_prefix('#') {
+            if let Some((key, value)) = header.split_once(':') {
+                match key.trim().to_ascii_uppercase().as_str() {
+                    "BPM" => bpm = parse_number(value).unwrap_or_default(),
+                    "GAP" => gap = parse_number(value).unwrap_or_default(),
+                    "RELATIVE" => relative = value.trim().eq_ignore_ascii_case("yes"),
+                    _ => {}
+                }
+                song.headers
+                    .push((Cow::Borrowed(key.trim()), Cow::Borrowed(value.trim())));
+            }
+            continue;
+        }
+        if bpm <= 0.0 {
+            continue;
+        }
+        let to_ms = |beat: i64| (gap + beat as f64 * 15000.0 / bpm).round().max(0.0) as u64;
+
+        if let Ok((_, (note_type, start, length, pitch, text))) = parse_note(line) {
+            let start = start + offset;
+            let mut attributes = vec![(Cow::Borrowed(PITCH_ATTRIBUTE), Cow::Borrowed(pitch))];
+            if let Some(name) = note_type_name(note_type) {
+                attributes.push((Cow::Borrowed(NOTE_TYPE_ATTRIBUTE), Cow::Borrowed(name)));
+            }
+            current.words.push(LyricWord {
+                start_time: to_ms(start),
+                end_time: to_ms(start + length.max(0)),
+                word: Cow::Borrowed(text),
+                attributes,
+                ..Default::default()
+            });
+        } else if let Ok((_, (beat, next))) = parse_line_break(line) {
+            push_line(&mut song.lines, &mut current);
+            if relative {
+                offset += next.unwrap_or(beat);
+            }
+        } else if let Ok((_, player)) = parse_player(line.trim()) {
+            push_line(&mut song.lines, &mut current);
+            current.is_duet = player == 2;
+            offset = 0;
+        } else if line.trim() == "E" {
+            break;
+        }
+    }
+    push_line(&mut song.lines, &mut current);
+    song
+}
+
+/// 将歌曲导出为 UltraStar 格式，所有音符的时间会按照指定的 BPM 换算为拍
+///
+/// `#BPM` 和 `#GAP` 会根据参数和第一个单词的开始时间重新生成，其余头信息原样导出。
+/// 单词中保存的音高和音符类型会被保留，没有音高的单词音高为 0。
+/// 包含对唱歌词行时会分别以 `P1` 和 `P2` 导出普通歌词行和对唱歌词行。
+///
+/// `bpm` 不是大于 0 的有限数时无法换算时间，返回 `None`。
+pub fn stringify_ultrastar(song: &UltraStarSong, bpm: f64) -> Option<String> {
+    if !bpm.is_finite() || bpm <= 0.0 {
+        return None;
+    }
+    let mut result = String::with_capacity(song.lines.len() * 64);
+    for (key, value) in &song.headers {
+        if !["BPM", "GAP", "RELATIVE"]
+            .iter()
+            .any(|x| key.eq_ignore_ascii_case(x))
+        {
+            writeln!(result, "#{key}:{value}").unwrap();
+        }
+    }
+
+    let gap = song
+        .lines
+        .iter()
+        .flat_map(|x| x.words.iter())
+        .filter(|x| !x.is_empty())
+        .map(|x| x.start_time)
+        .min()
+        .unwrap_or_default();
+    writeln!(result, "#BPM:{bpm}").unwrap();
+    writeln!(result, "#GAP:{gap}").unwrap();
+
+    let to_beat = |time: u64| (time.saturating_sub(gap) as f64 * bpm / 15000.0).round() as i64;
+    let has_duet = song.lines.iter().any(|x| x.is_duet);
+    for is_duet in [false, true] {
+        if is_duet && !has_duet {
+            break;
+        }
+        if has_duet {
+            result.push_str(if is_duet { "P2\n" } else { "P1\n" });
+        }
+        let mut lines: Vec<_> = song
+            .lines
+            .iter()
+            .filter(|x| x.is_duet == is_duet && !x.is_empty())
+            .collect();
+        lines.sort_by_key(|x| x.start_time);
+
+        let mut last_end: Option<i64> = None;
+        for line in lines {
+            // 空白单词合并到前一个单词中
+            let mut notes: Vec<(&LyricWord, String)> = Vec::with_capacity(line.words.len());
+            for word in &line.words {
+                match notes.last_mut() {
+                    Some((_, text)) if word.is_empty() => text.push_str(&word.word),
+                    _ if word.is_empty() => {}
+                    _ => notes.push((word, word.word.to_string())),
+                }
+            }
+            let first_start = to_beat(notes[0].0.start_time);
+            if let Some(last_end) = last_end {
+                writeln!(result, "- {}", last_end.min(first_start)).unwrap();
+            }
+            for (word, text) in notes {
+                let start = to_beat(word.start_time);
+                let length = (to_beat(word.end_time) - start).max(1);
+                let pitch = word.attribute(PITCH_ATTRIBUTE).unwrap_or("0");
+                let note_type = note_type_char(word.attribute(NOTE_TYPE_ATTRIBUTE));
+                writeln!(result, "{note_type} {start} {length} {pitch} {text}").unwrap();
+                last_end = Some(start + length);
+            }
+        }
+    }
+    result.push_str("E\n");
+    Some(result)
+}
+
+#[test]
+fn test_parse_ultrastar() {
+    const SONG: &str = "#TITLE:Test Song\n#ARTIST:Tester\n#BPM:150\n#GAP:1000\n\
+        : 0 4 5 Hel\n: 4 4 7 lo \n* 8 8 9 world\n- 20\nF 24 4 0 ah\nR 28 4 0  yeah\nG 32 2 -3 !\nE\n";
+    let song = parse_ultrastar(SONG);
+    assert_eq!(song.header("title"), Some("Test Song"));
+    assert_eq!(song.lines.len(), 2);
+    // 150 BPM 时一拍为 100 毫秒
+    let words: Vec<_> = song.lines[0]
+        .words
+        .iter()
+        .map(|x| (x.word.as_ref(), x.start_time, x.end_time))
+        .collect();
+    assert_eq!(
+        words,
+        vec![
+            ("Hel", 1000, 1400),
+            ("lo ", 1400, 1800),
+            ("world", 1800, 2600)
+        ]
+    );
+    assert_eq!(song.lines[0].words[0].attribute(PITCH_ATTRIBUTE), Some("5"));
+    assert_eq!(song.lines[0].words[0].attribute(NOTE_TYPE_ATTRIBUTE), None);
+    assert_eq!(
+        song.lines[0].words[2].attribute(NOTE_TYPE_ATTRIBUTE),
+        Some("golden")
+    );
+    let line = &song.lines[1];
+    assert_eq!((line.start_time, line.end_time), (3400, 4400));
+    assert_eq!(line.words[1].word, " yeah");
+    assert_eq!(line.words[1].attribute(NOTE_TYPE_ATTRIBUTE), Some("rap"));
+    assert_eq!(line.words[2].attribute(PITCH_ATTRIBUTE), Some("-3"));
+    assert_eq!(
+        line.words[2].attribute(NOTE_TYPE_ATTRIBUTE),
+        Some("rap-golden")
+    );
+}
+
+#[test]
+fn test_parse_ultrastar_duet_relative() {
+    const SONG: &str = "#TITLE:Duet\r\n#BPM:300,0\r\n#GAP:0\r\n#RELATIVE:YES\r\n\
+        P1\r\n: 0 2 0 One\r\n- 4 10\r\n: 0 2 0 Two\r\nP2\r\n: 20 2 0 Three\r\nE\r\n";
+    let song = parse_ultrastar(SONG);
+    assert_eq!(song.lines.len(), 3);
+    // 300 BPM 时一拍为 50 毫秒
+    assert_eq!(song.lines[1].start_time, 500);
+    assert!(!song.lines[1].is_duet);
+    assert_eq!(song.lines[2].words[0].word, "Three");
+    assert_eq!(song.lines[2].start_time, 1000);
+    assert!(song.lines[2].is_duet);
+}
+
+#[test]
+fn test_stringify_ultrastar() {
+    const SONG: &str = "#TITLE:Test Song\n#BPM:150\n#GAP:1000\n\
+        : 0 4 5 Hel\n: 4 4 7 lo \n* 8 8 9 world\n- 20\nP2\nF 24 4 0 ah\nE\n";
+    let song = parse_ultrastar(SONG);
+    let result = stringify_ultrastar(&song, 150.0).unwrap();
+    assert_eq!(
+        result,
+        "#TITLE:Test Song\n#BPM:150\n#GAP:1000\nP1\n: 0 4 5 Hel\n: 4 4 7 lo \n* 8 8 9 world\nP2\nF 24 4 0 ah\nE\n"
+    );
+    assert_eq!(parse_ultrastar(&result).lines, song.lines);
+
+    // 以其他 BPM 导出时时间会被重新换算
+    let result = stringify_ultrastar(&song, 75.0).unwrap();
+    assert!(result.contains("* 4 4 9 world\n"));
+}
+
+#[test]
+fn test_stringify_ultrastar_invalid_bpm() {
+    let song = parse_ultrastar(": 0 4 5 Hel\nE\n");
+    for bpm in [f64::NAN, f64::INFINITY, 0.0, -120.0] {
+        assert_eq!(stringify_ultrastar(&song, bpm), None);
+    }
+}
+
+#[test]
+fn test_parse_player() {
+    assert_eq!(parse_player("P1"), Ok(("", 1)));
+    assert_eq!(parse_player("P 2"), Ok(("", 2)));
+    assert!(parse_player("P12").is_err());
+    assert!(parse_player("P3").is_err());
+}
@@ -3,6 +3,19 @@ use wasm_bindgen::prelude::*;
 
 use crate::LyricLine;
 
+/// 将 JavaScript 传入的值转换为 Rust 类型，结构不正确时返回带有具体原因的错误
+#[cfg(all(target_arch = "wasm32", feature = "serde"))]
+pub(crate) fn from_js<T: serde::de::DeserializeOwned>(value: JsValue) -> Result<T, JsError> {
+    serde_wasm_bindgen::from_value(value)
+        .map_err(|err| JsError::new(&format!("invalid lyric data: {err}")))
+}
+
+/// 将 Rust 类型转换为 JavaScript 值
+#[cfg(all(target_arch = "wasm32", feature = "serde"))]
+pub(crate) fn to_js<T: serde::Serialize>(value: &T) -> Result<JsValue, JsError> {
+    serde_wasm_bindgen::to_value(value).map_err(|err| JsError::new(&err.to_string()))
+}
+
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
 /// When the `console_error_panic_hook` feature is enabled, we can call the
 /// `set_panic_hook` function at least once during initialization, and then
@@ -15,6 +28,19 @@ pub fn wasm_start() {
     console_error_panic_hook::set_once();
 }
 
+/// 歌词时间戳能表示的最大时间
+pub(crate) const MAX_TIME: u64 = 60039999; // 999:99.999
+
+#[cfg(feature = "serde")]
+pub(crate) fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+#[cfg(feature = "serde")]
+pub(crate) fn is_empty_str(value: &str) -> bool {
+    value.is_empty()
+}
+
 pub fn process_lyrics(lines: &mut [LyricLine]) {
     lines.sort_by(|a, b| {
         a.words
@@ -22,7 +48,6 @@ pub fn process_lyrics(lines: &mut [LyricLine]) {
             .map(|x| x.start_time)
             .cmp(&b.words.first().map(|x| x.start_time))
     });
-    const MAX_TIME: u64 = 60039999; // 999:99.999
     for line in lines.iter_mut() {
         line.start_time = line
             .words
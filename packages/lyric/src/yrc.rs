@@ -66,7 +66,7 @@ pub fn parse_words(src: &str) -> IResult<&str, Vec<LyricWord<'_>>> {
             start_time: x.0.0,
             end_time: x.0.0 + x.0.1,
             word: Cow::Borrowed(x.1),
-            roman_word: std::borrow::Cow::Borrowed(""),
+            ..Default::default()
         })
         .collect();
     Ok((src, words))
@@ -152,15 +152,26 @@ pub fn stringify_yrc(lines: &[LyricLine]) -> String {
     result
 }
 
+/// 解析 YRC 格式的歌词字符串
 #[cfg(all(target_arch = "wasm32", feature = "serde"))]
-#[wasm_bindgen(js_name = "parseYrc", skip_typescript)]
-pub fn parse_yrc_js(src: &str) -> JsValue {
-    serde_wasm_bindgen::to_value(&parse_yrc(src)).unwrap()
+#[wasm_bindgen(
+    js_name = "parseYrc",
+    unchecked_return_type = "LyricLine[]",
+    return_description = "成功解析出来的歌词"
+)]
+pub fn parse_yrc_js(
+    #[wasm_bindgen(param_description = "歌词字符串")] src: &str,
+) -> Result<JsValue, JsError> {
+    crate::utils::to_js(&parse_yrc(src))
 }
 
+/// 将歌词数组转换为 YRC 格式的字符串
 #[cfg(all(target_arch = "wasm32", feature = "serde"))]
-#[wasm_bindgen(js_name = "stringifyYrc", skip_typescript)]
-pub fn stringify_yrc_js(lrc: JsValue) -> String {
-    let lines: Vec<LyricLine> = serde_wasm_bindgen::from_value(lrc).unwrap();
-    stringify_yrc(&lines)
+#[wasm_bindgen(js_name = "stringifyYrc", return_description = "YRC 格式的字符串")]
+pub fn stringify_yrc_js(
+    #[wasm_bindgen(unchecked_param_type = "LyricLine[]", param_description = "歌词数组")]
+    lines: JsValue,
+) -> Result<String, JsError> {
+    let lines: Vec<LyricLine> = crate::utils::from_js(lines)?;
+    Ok(stringify_yrc(&lines))
 }
@@ -0,0 +1,264 @@
+//! 离线简繁转换模块
+//!
+//! 支持简体中文、繁体中文、台湾正体和香港繁体之间的转换，转换规则与 OpenCC
+//! 的 `s2t`、`s2tw`、`s2hk`、`t2s`、`tw2s`、`hk2s` 一致：
+//! 先按词组进行正向最大匹配，再逐字转换。
+//!
+//! 转换歌词行时会保留原有的单词拆分和时间信息：跨越单词边界的词组只有在
+//! 转换前后字数相同时才会被采用，这样转换结果可以逐字分配回各个单词。
+//!
+//! 词典数据来自 [OpenCC](https://github.com/BYVoid/OpenCC) 项目，
+//! 以 Apache License 2.0 协议授权。
+use std::{collections::HashMap, sync::LazyLock};
+
+#[cfg(feature = "serde")]
+use serde::*;
+
+use crate::LyricLine;
+
+/// 中文转换模式，与播放器中的 `TextConversionMode` 取值一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum ConversionMode {
+    /// 繁体中文转简体中文
+    TraditionalToSimplified,
+    /// 简体中文转繁体中文
+    SimplifiedToTraditional,
+    /// 简体中文转台湾正体
+    SimplifiedToTaiwan,
+    /// 台湾正体转简体中文
+    TaiwanToSimplified,
+    /// 简体中文转香港繁体
+    SimplifiedToHongKong,
+    /// 香港繁体转简体中文
+    HongKongToSimplified,
+}
+
+/// 一个用于正向最大匹配的词典
+struct Dictionary {
+    entries: HashMap<&'static str, &'static str>,
+    /// 词典中最长词条的字数
+    max_len: usize,
+}
+
+impl Dictionary {
+    /// 从多个 OpenCC 文本词典构建，靠前的词典中的词条优先
+    fn new(sources: &[&'static str]) -> Self {
+        let mut entries = HashMap::new();
+        let mut max_len = 0;
+        for source in sources {
+            for line in source.lines() {
+                if line.starts_with('#') {
+                    continue;
+                }
+                let Some((key, values)) = line.split_once('\t') else {
+                    continue;
+                };
+                let Some(value) = values.split(' ').next() else {
+                    continue;
+                };
+                if !entries.contains_key(key) {
+                    entries.insert(key, value);
+                    max_len = max_len.max(key.chars().count());
+                }
+            }
+        }
+        Self { entries, max_len }
+    }
+}
+
+macro_rules! dictionary {
+    ($name:ident, $($file:literal),+) => {
+        static $name: LazyLock<Dictionary> = LazyLock::new(|| {
+            Dictionary::new(&[$(include_str!(concat!("dict/", $file))),+])
+        });
+    };
+}
+
+dictionary!(ST, "STPhrases.txt", "STCharacters.txt");
+dictionary!(TS, "TSPhrases.txt", "TSCharacters.txt");
+dictionary!(TW_VARIANTS, "TWVariants.txt");
+dictionary!(
+    TW_VARIANTS_REV,
+    "TWVariantsRevPhrases.txt",
+    "TWVariantsRev.txt"
+);
+dictionary!(HK_VARIANTS, "HKVariants.txt");
+dictionary!(
+    HK_VARIANTS_REV,
+    "HKVariantsRevPhrases.txt",
+    "HKVariantsRev.txt"
+);
+
+impl ConversionMode {
+    /// 该模式依次需要经过的词典
+    fn chain(self) -> Vec<&'static Dictionary> {
+        match self {
+            Self::TraditionalToSimplified => vec![&TS],
+            Self::SimplifiedToTraditional => vec![&ST],
+            Self::SimplifiedToTaiwan => vec![&ST, &TW_VARIANTS],
+            Self::TaiwanToSimplified => vec![&TW_VARIANTS_REV, &TS],
+            Self::SimplifiedToHongKong => vec![&ST, &HK_VARIANTS],
+            Self::HongKongToSimplified => vec![&HK_VARIANTS_REV, &TS],
+        }
+    }
+}
+
+/// 使用一个词典转换多个相连的文本片段，返回的片段数量与输入一致
+fn convert_segments(segments: &[String], dict: &Dictionary) -> Vec<String> {
+    let text: String = segments.concat();
+    // 每个字在原文中的字节位置，末尾额外存放原文长度
+    let offsets: Vec<usize> = text
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(text.len()))
+        .collect();
+    // 每个字所属的片段下标
+    let owners: Vec<usize> = segments
+        .iter()
+        .enumerate()
+        .flat_map(|(i, s)| std::iter::repeat_n(i, s.chars().count()))
+        .collect();
+    let char_count = owners.len();
+
+    let mut result = vec![String::new(); segments.len()];
+    let mut i = 0;
+    while i < char_count {
+        let mut matched = None;
+        for len in (1..=dict.max_len.min(char_count - i)).rev() {
+            let key = &text[offsets[i]..offsets[i + len]];
+            if let Some(&value) = dict.entries.get(key) {
+                let crosses_boundary = owners[i] != owners[i + len - 1];
+                if !crosses_boundary || value.chars().count() == len {
+                    matched = Some((len, value));
+                    break;
+                }
+            }
+        }
+
+        match matched {
+            Some((len, value)) if owners[i] != owners[i + len - 1] => {
+                for (j, c) in value.chars().enumerate() {
+                    result[owners[i + j]].push(c);
+                }
+                i += len;
+            }
+            Some((len, value)) => {
+                result[owners[i]].push_str(value);
+                i += len;
+            }
+            None => {
+                result[owners[i]].push_str(&text[offsets[i]..offsets[i + 1]]);
+                i += 1;
+            }
+        }
+    }
+    result
+}
+
+/// 转换多个相连的文本片段，返回的片段数量与输入一致
+///
+/// 词组可以跨越片段匹配，但只有转换前后字数不变时才会跨片段采用
+pub fn convert_segments_with(segments: &[&str], mode: ConversionMode) -> Vec<String> {
+    let mut segments: Vec<String> = segments.iter().map(|x| x.to_string()).collect();
+    for dict in mode.chain() {
+        segments = convert_segments(&segments, dict);
+    }
+    segments
+}
+
+/// 转换一段文本
+pub fn convert_text(text: &str, mode: ConversionMode) -> String {
+    convert_segments_with(&[text], mode)
+        .pop()
+        .unwrap_or_default()
+}
+
+/// 转换一行歌词的单词文本和翻译，音译不做处理
+///
+/// 单词的拆分和时间信息保持不变
+pub fn convert_line(line: &mut LyricLine<'_>, mode: ConversionMode) {
+    let words: Vec<&str> = line.words.iter().map(|w| w.word.as_ref()).collect();
+    let converted = convert_segments_with(&words, mode);
+    for (word, converted) in line.words.iter_mut().zip(converted) {
+        if word.word != converted {
+            word.word = converted.into();
+        }
+    }
+    if !line.translated_lyric.is_empty() {
+        line.translated_lyric = convert_text(&line.translated_lyric, mode).into();
+    }
+}
+
+/// 转换所有歌词行，规则同 [`convert_line`]
+pub fn convert_lines(lines: &mut [LyricLine<'_>], mode: ConversionMode) {
+    for line in lines.iter_mut() {
+        convert_line(line, mode);
+    }
+}
+
+/// 转换 TTML 歌词中的所有歌词行和元数据值
+#[cfg(feature = "ttml")]
+pub fn convert_ttml(lyric: &mut crate::ttml::TTMLLyric<'_>, mode: ConversionMode) {
+    convert_lines(&mut lyric.lines, mode);
+    for (_, values) in lyric.metadata.iter_mut() {
+        for value in values.iter_mut() {
+            *value = convert_text(value, mode).into();
+        }
+    }
+}
+
+#[test]
+fn test_convert_text() {
+    assert_eq!(
+        convert_text("头发和鼠标", ConversionMode::SimplifiedToTraditional),
+        "頭髮和鼠標"
+    );
+    assert_eq!(
+        convert_text("頭髮和鼠標", ConversionMode::TraditionalToSimplified),
+        "头发和鼠标"
+    );
+    assert_eq!(
+        convert_text("为什么里面", ConversionMode::SimplifiedToTaiwan),
+        "為什麼裡面"
+    );
+    assert_eq!(
+        convert_text("为什么里面", ConversionMode::SimplifiedToHongKong),
+        "為什麼裏面"
+    );
+    assert_eq!(
+        convert_text("為什麼裡面", ConversionMode::TaiwanToSimplified),
+        "为什么里面"
+    );
+    assert_eq!(
+        convert_text("Hello 世界", ConversionMode::SimplifiedToTraditional),
+        "Hello 世界"
+    );
+}
+
+#[test]
+fn test_convert_line_keeps_words() {
+    use crate::LyricWord;
+    use std::borrow::Cow;
+
+    let mut line = LyricLine {
+        words: ["剪", "头", "发 ", "干", "净"]
+            .iter()
+            .enumerate()
+            .map(|(i, &word)| LyricWord {
+                start_time: i as u64 * 100,
+                end_time: i as u64 * 100 + 100,
+                word: Cow::Borrowed(word),
+                ..Default::default()
+            })
+            .collect(),
+        translated_lyric: Cow::Borrowed("面条"),
+        ..Default::default()
+    };
+    convert_line(&mut line, ConversionMode::SimplifiedToTraditional);
+    let words: Vec<_> = line.words.iter().map(|w| w.word.as_ref()).collect();
+    assert_eq!(words, vec!["剪", "頭", "髮 ", "乾", "淨"]);
+    assert_eq!(line.words[4].start_time, 400);
+    assert_eq!(line.translated_lyric, "麪條");
+}
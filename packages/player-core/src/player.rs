@@ -65,6 +65,31 @@ pub struct AudioInfo {
     pub comment: String,
     pub duration: f64,
     pub position: f64,
+    #[serde(default)]
+    pub album_artist: String,
+    #[serde(default)]
+    pub track_number: Option<u32>,
+    #[serde(default)]
+    pub disc_number: Option<u32>,
+    /// 发行年份
+    #[serde(default)]
+    pub year: Option<u32>,
+    #[serde(default)]
+    pub genres: Vec<String>,
+    #[serde(default)]
+    pub isrc: String,
+    /// 是否含有不雅内容
+    #[serde(default)]
+    pub explicit: bool,
+}
+
+impl AudioInfo {
+    /// 封面图片数据的 MD5 值，可用于判断封面是否与之前的相同
+    pub fn cover_hash(&self) -> Option<String> {
+        self.cover
+            .as_ref()
+            .map(|cover| format!("{:x}", md5::compute(cover)))
+    }
 }
 
 impl Debug for AudioInfo {
@@ -79,6 +104,13 @@ impl Debug for AudioInfo {
             .field("comment", &self.comment)
             .field("duration", &self.duration)
             .field("position", &self.position)
+            .field("album_artist", &self.album_artist)
+            .field("track_number", &self.track_number)
+            .field("disc_number", &self.disc_number)
+            .field("year", &self.year)
+            .field("genres", &self.genres)
+            .field("isrc", &self.isrc)
+            .field("explicit", &self.explicit)
             .finish()
     }
 }
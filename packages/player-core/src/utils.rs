@@ -20,6 +20,35 @@ pub fn read_audio_info(input_ctx: &mut ffmpeg::format::context::Input) -> AudioI
     if let Some(comment) = metadata.get("comment") {
         new_audio_info.comment = comment.to_string();
     }
+    if let Some(album_artist) = metadata.get("album_artist") {
+        new_audio_info.album_artist = album_artist.to_string();
+    }
+    // 音轨号和碟号可能带有总数，例如 `3/12`
+    new_audio_info.track_number = metadata.get("track").and_then(parse_leading_number);
+    new_audio_info.disc_number = metadata.get("disc").and_then(parse_leading_number);
+    // 日期可能是完整的日期，例如 `2024-05-01`，只取开头的年份
+    new_audio_info.year = metadata
+        .get("date")
+        .or_else(|| metadata.get("year"))
+        .and_then(parse_leading_number);
+    if let Some(genre) = metadata.get("genre") {
+        new_audio_info.genres = genre
+            .split([';', '\0'])
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect();
+    }
+    if let Some(isrc) = metadata.get("isrc").or_else(|| metadata.get("TSRC")) {
+        new_audio_info.isrc = isrc.trim().to_string();
+    }
+    // iTunes 的内容分级标签，1 和 4 表示含有不雅内容
+    if let Some(advisory) = metadata
+        .get("ITUNESADVISORY")
+        .or_else(|| metadata.get("rtng"))
+    {
+        new_audio_info.explicit = matches!(advisory.trim(), "1" | "4");
+    }
 
     'outer: for (stream, packet) in input_ctx.packets() {
         if stream
@@ -41,3 +70,11 @@ pub fn read_audio_info(input_ctx: &mut ffmpeg::format::context::Input) -> AudioI
 
     new_audio_info
 }
+
+fn parse_leading_number(value: &str) -> Option<u32> {
+    let value = value.trim();
+    let end = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    value[..end].parse().ok()
+}
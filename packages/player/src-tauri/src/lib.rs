@@ -52,7 +52,7 @@ async fn ws_broadcast_payload(
     ws: AMLLWebSocketServerState<'_>,
     payload: ws_protocol::v2::Payload,
 ) -> Result<(), String> {
-    ws.write().await.broadcast_payload(payload).await;
+    ws.read().await.broadcast_payload(payload).await;
     Ok(())
 }
 
@@ -2,19 +2,29 @@ use std::sync::LazyLock;
 
 use amll_player_core::AudioThreadEventMessage;
 use amll_player_core::AudioThreadMessage;
+use amll_player_core::{AudioInfo, AudioThreadEvent};
 use amll_player_core::{AudioPlayer, AudioPlayerConfig, AudioPlayerHandle};
 use rodio::OutputStream;
 use rodio::OutputStreamBuilder;
-use tauri::{AppHandle, Emitter, Runtime};
-use tokio::sync::RwLock;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+use tokio::sync::{RwLock, mpsc};
 use tracing::error;
 use tracing::warn;
+use ws_protocol::v2;
+
+use crate::AMLLWebSocketServerWrapper;
 
 pub static PLAYER_HANDLER: LazyLock<RwLock<Option<AudioPlayerHandle>>> =
     LazyLock::new(|| RwLock::new(None));
 
+/// 播放核心计算频谱时使用的频率范围，初始值与播放核心的默认值一致
+static FFT_RANGE: std::sync::Mutex<(f32, f32)> = std::sync::Mutex::new((80.0, 2000.0));
+
 #[tauri::command]
 pub async fn local_player_send_msg(msg: AudioThreadEventMessage<AudioThreadMessage>) {
+    if let Some(AudioThreadMessage::SetFFTRange { from_freq, to_freq }) = msg.data() {
+        *FFT_RANGE.lock().unwrap() = (*from_freq, *to_freq);
+    }
     if let Some(handler) = &*PLAYER_HANDLER.read().await
         && let Err(err) = handler.send(msg).await
     {
@@ -51,12 +61,102 @@ async fn local_player_main<R: Runtime>(app: AppHandle<R>, stream: OutputStream)
     let player = AudioPlayer::new(AudioPlayerConfig {}, stream);
     let handler = player.handler();
     PLAYER_HANDLER.write().await.replace(handler);
+    let (broadcast_sender, broadcast_receiver) = mpsc::unbounded_channel();
+    tauri::async_runtime::spawn(broadcast_loop(app.clone(), broadcast_receiver));
     let app_clone = app.clone();
     player
         .run(move |evt| {
+            let broadcast = match evt.data() {
+                Some(AudioThreadEvent::LoadAudio {
+                    music_id,
+                    music_info,
+                    ..
+                }) => Some(Broadcast::Payload(v2::Payload::State(
+                    v2::StateUpdate::SetMusic(to_ws_music_info(music_id, music_info)),
+                ))),
+                Some(AudioThreadEvent::FFTData { data }) => Some(Broadcast::Spectrum(data.clone())),
+                _ => None,
+            };
+            if let Some(broadcast) = broadcast {
+                let _ = broadcast_sender.send(broadcast);
+            }
             if let Err(err) = app_clone.emit("plugin:player-core-event", &evt) {
                 error!("发送事件时出错: {err:?}");
             }
         })
         .await;
 }
+
+/// 将播放核心读取到的歌曲信息转换为 HybridV2 协议的歌曲信息
+///
+/// 歌词偏移保存在前端的歌曲数据库中，此处无法得知，因此不会发送 `lyric_offset`。
+fn to_ws_music_info(music_id: &str, info: &AudioInfo) -> v2::MusicInfo {
+    let non_empty = |s: &str| (!s.is_empty()).then(|| s.to_string());
+    v2::MusicInfo {
+        music_id: music_id.to_string(),
+        music_name: info.name.clone(),
+        album_id: String::new(),
+        album_name: info.album.clone(),
+        artists: non_empty(&info.artist)
+            .map(|name| ws_protocol::Artist {
+                id: Default::default(),
+                name: name.into(),
+            })
+            .into_iter()
+            .collect(),
+        duration: (info.duration * 1000.0) as u64,
+        album_artist: non_empty(&info.album_artist),
+        track_number: info.track_number,
+        disc_number: info.disc_number,
+        year: info.year,
+        genres: info.genres.clone(),
+        isrc: non_empty(&info.isrc),
+        explicit: info.explicit,
+        cover_hash: info.cover_hash(),
+        ..Default::default()
+    }
+}
+
+/// 需要向 WebSocket 客户端广播的播放核心事件
+enum Broadcast {
+    /// 歌曲信息等已经构造好的消息
+    Payload(v2::Payload),
+    /// 播放核心计算的频谱，只有协商了频谱能力的客户端会收到
+    Spectrum(Vec<f32>),
+}
+
+/// 按照事件发生的顺序向所有 WebSocket 客户端广播
+///
+/// 所有广播都由这一个任务发送，因此歌曲信息和频谱不会乱序；
+/// 没有客户端协商频谱能力时不会编码频谱。
+async fn broadcast_loop<R: Runtime>(
+    app: AppHandle<R>,
+    mut receiver: mpsc::UnboundedReceiver<Broadcast>,
+) {
+    while let Some(broadcast) = receiver.recv().await {
+        let Some(ws) = app.try_state::<AMLLWebSocketServerWrapper>() else {
+            continue;
+        };
+        let ws = ws.read().await;
+        let payload = match broadcast {
+            Broadcast::Payload(payload) => payload,
+            Broadcast::Spectrum(data) => {
+                if !ws.has_capability(v2::Capability::Spectrum).await {
+                    continue;
+                }
+                let (from_freq, to_freq) = *FFT_RANGE.lock().unwrap();
+                let Some(spectrum) = v2::Spectrum::encode(
+                    &data,
+                    v2::SpectrumFormat::U8,
+                    from_freq,
+                    to_freq,
+                    v2::FrequencyScale::Linear,
+                ) else {
+                    continue;
+                };
+                v2::Payload::State(v2::StateUpdate::Spectrum(spectrum))
+            }
+        };
+        ws.broadcast_payload(payload).await;
+    }
+}
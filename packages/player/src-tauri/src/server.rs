@@ -6,25 +6,32 @@ use futures::stream::SplitSink;
 use tauri::ipc::Channel;
 use tauri::{AppHandle, Emitter};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::RwLock as TokioRwLock;
+use tokio::sync::{RwLock as TokioRwLock, mpsc};
 use tokio::task::JoinHandle;
 use tokio_tungstenite::tungstenite::Message;
 use tokio_tungstenite::{WebSocketStream, accept_async};
 use tracing::*;
-use ws_protocol::{v1, v2};
+use ws_protocol::{clock, v1, v2};
 
 type Connections = Arc<TokioRwLock<HashMap<SocketAddr, ConnectionInfo>>>;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum ProtocolType {
-    Unknown,
     BinaryV1,
     HybridV2,
 }
 
+/// 本服务端支持的 HybridV2 可选能力
+const SUPPORTED_CAPABILITIES: &[v2::Capability] = v2::Capability::ALL;
+/// 每个连接的发送队列长度，队列已满时新的消息会被丢弃
+const QUEUE_SIZE: usize = 64;
+
 struct ConnectionInfo {
-    sink: SplitSink<WebSocketStream<TcpStream>, Message>,
+    /// 发送队列，由连接自己的任务写入 WebSocket，避免慢速客户端阻塞其它连接
+    sender: mpsc::Sender<Message>,
     protocol: ProtocolType,
+    /// 握手后协商的能力，BinaryV1 协议的连接为空
+    capabilities: Vec<v2::Capability>,
 }
 
 pub struct AMLLWebSocketServer {
@@ -47,8 +54,8 @@ impl AMLLWebSocketServer {
             task.abort();
         }
         let mut conns = self.connections.write().await;
-        for (addr, conn_sink) in conns.iter_mut() {
-            if let Err(e) = conn_sink.sink.close().await {
+        for (addr, conn_info) in conns.iter() {
+            if let Err(e) = conn_info.sender.try_send(Message::Close(None)) {
                 warn!("断开和 {} 的 WebSocket 连接失败:{:?}", addr, e);
             }
         }
@@ -96,8 +103,17 @@ impl AMLLWebSocketServer {
         self.connections.read().await.keys().copied().collect()
     }
 
-    pub async fn broadcast_payload(&mut self, payload: v2::Payload) {
-        let mut conns = self.connections.write().await;
+    /// 是否有连接协商了指定的能力，BinaryV1 协议的连接没有任何可选能力
+    pub async fn has_capability(&self, capability: v2::Capability) -> bool {
+        self.connections
+            .read()
+            .await
+            .values()
+            .any(|x| x.capabilities.contains(&capability))
+    }
+
+    pub async fn broadcast_payload(&self, mut payload: v2::Payload) {
+        payload.stamp(clock::now_millis());
 
         let v2_msg = serde_json::to_string(&payload)
             .ok()
@@ -113,29 +129,57 @@ impl AMLLWebSocketServer {
 
         let mut disconnected_addrs = Vec::new();
 
-        for (addr, conn_info) in conns.iter_mut() {
+        let conns = self.connections.read().await;
+        for (addr, conn_info) in conns.iter() {
             let msg_to_send = match conn_info.protocol {
                 ProtocolType::BinaryV1 => v1_msg.as_ref(),
-                ProtocolType::HybridV2 => v2_msg.as_ref(),
-                _ => None,
+                ProtocolType::HybridV2 => match payload.required_capability() {
+                    Some(cap) if !conn_info.capabilities.contains(&cap) => None,
+                    _ => v2_msg.as_ref(),
+                },
             };
 
             if let Some(msg) = msg_to_send {
                 if msg.is_empty() {
                     continue;
                 }
-                if let Err(err) = conn_info.sink.send(msg.clone()).await {
-                    warn!("WebSocket 客户端 {addr} 发送失败: {err:?}");
+                if !Self::enqueue(*addr, &conn_info.sender, msg.clone()) {
                     disconnected_addrs.push(*addr);
                 }
             }
         }
+        drop(conns);
+
+        if !disconnected_addrs.is_empty() {
+            let mut conns = self.connections.write().await;
+            for addr in disconnected_addrs {
+                conns.remove(&addr);
+            }
+        }
+    }
 
-        for addr in disconnected_addrs {
-            conns.remove(&addr);
+    /// 将消息放入连接的发送队列，队列已满时丢弃消息，连接已断开时返回 `false`
+    fn enqueue(addr: SocketAddr, sender: &mpsc::Sender<Message>, message: Message) -> bool {
+        match sender.try_send(message) {
+            Ok(()) => true,
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                warn!("WebSocket 客户端 {addr} 的发送队列已满，丢弃消息");
+                true
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => false,
         }
     }
 
+    fn enqueue_payload(
+        addr: SocketAddr,
+        sender: &mpsc::Sender<Message>,
+        payload: &v2::Payload,
+    ) -> anyhow::Result<()> {
+        let text = serde_json::to_string(payload)?;
+        Self::enqueue(addr, sender, Message::Text(text.into()));
+        Ok(())
+    }
+
     async fn accept_conn(
         stream: TcpStream,
         app: AppHandle,
@@ -150,63 +194,111 @@ impl AMLLWebSocketServer {
         info!("已连接 WebSocket 客户端: {addr}");
         app.emit("on-ws-protocol-client-connected", &addr_str)?;
 
-        let (write_sink, mut read_stream) = wss.split();
-
-        let mut temp_sink = Some(write_sink);
+        let (mut write_sink, mut read_stream) = wss.split();
+        let (sender, mut receiver) = mpsc::channel::<Message>(QUEUE_SIZE);
+        let mut protocol = None;
 
-        if let Some(Ok(first_message)) = read_stream.next().await {
+        // 根据第一条有效的消息识别协议，无法识别时告知客户端并继续等待
+        while let Some(Ok(first_message)) = read_stream.next().await {
+            let mut capabilities = Vec::new();
             let protocol_type = match first_message {
-                Message::Text(ref text) => {
-                    if let Ok(v2_message) = serde_json::from_str::<v2::MessageV2>(text) {
-                        if v2_message.payload == v2::Payload::Initialize {
-                            info!("已识别为 HybridV2 协议");
-                            ProtocolType::HybridV2
+                Message::Text(ref text) => match serde_json::from_str::<v2::MessageV2>(text) {
+                    Ok(v2::MessageV2 {
+                        payload: v2::Payload::Initialize(init),
+                    }) => {
+                        let ack = init.accept(
+                            "AMLL Player",
+                            env!("CARGO_PKG_VERSION"),
+                            SUPPORTED_CAPABILITIES,
+                        );
+                        if init.is_legacy() {
+                            info!("已识别为 HybridV2 协议（旧版客户端）");
                         } else {
-                            warn!("收到了一个非 Initialize 的 V2 消息，断开。");
-                            return Ok(());
+                            info!(
+                                "已识别为 HybridV2 协议，客户端 {} {}，协议版本 {}，协商能力 {:?}",
+                                init.client_name,
+                                init.client_version,
+                                ack.protocol_version,
+                                ack.capabilities
+                            );
+                            let reply = v2::Payload::InitializeAck(ack.clone());
+                            Self::send_payload(&mut write_sink, &reply).await?;
                         }
-                    } else {
-                        warn!("发送了无法识别的文本消息，断开。");
-                        return Ok(());
+                        capabilities = ack.capabilities;
+                        ProtocolType::HybridV2
                     }
-                }
+                    Ok(_) => {
+                        warn!("收到了一个非 Initialize 的 V2 消息，等待握手");
+                        let error = v2::ErrorInfo::new(
+                            v2::ErrorCode::MalformedMessage,
+                            "第一条消息必须是 initialize",
+                        );
+                        Self::send_payload(&mut write_sink, &v2::Payload::Error(error)).await?;
+                        continue;
+                    }
+                    Err(err) => {
+                        warn!("发送了无法识别的文本消息，等待握手: {err:?}");
+                        let error =
+                            v2::ErrorInfo::new(v2::ErrorCode::MalformedMessage, err.to_string());
+                        Self::send_payload(&mut write_sink, &v2::Payload::Error(error)).await?;
+                        continue;
+                    }
+                },
                 Message::Binary(_) => {
                     info!("已识别为 BinaryV1 协议");
-                    if let Err(e) = Self::process_v1_message(first_message, &channel).await {
-                        error!("处理 V1 协议的消息时失败: {e:?}");
-                        return Ok(());
-                    }
+                    Self::process_v1_message(first_message, &channel)?;
                     ProtocolType::BinaryV1
                 }
-                _ => ProtocolType::Unknown,
+                _ => continue,
             };
 
-            if protocol_type != ProtocolType::Unknown
-                && let Some(sink) = temp_sink.take()
-            {
-                conns.write().await.insert(
-                    addr,
-                    ConnectionInfo {
-                        sink,
-                        protocol: protocol_type,
-                    },
-                );
-            }
+            conns.write().await.insert(
+                addr,
+                ConnectionInfo {
+                    sender: sender.clone(),
+                    protocol: protocol_type,
+                    capabilities,
+                },
+            );
+            protocol = Some(protocol_type);
+            break;
         }
 
-        while let Some(Ok(message)) = read_stream.next().await {
-            let conns_read = conns.read().await;
-            if let Some(conn_info) = conns_read.get(&addr) {
-                let process_result = match conn_info.protocol {
-                    ProtocolType::BinaryV1 => Self::process_v1_message(message, &channel).await,
-                    ProtocolType::HybridV2 => Self::process_v2_message(message, &channel).await,
-                    _ => Ok(()),
+        // 发送队列中的消息由此任务写入 WebSocket，发送 Close 消息后结束
+        let writer = async move {
+            while let Some(message) = receiver.recv().await {
+                let is_close = matches!(message, Message::Close(_));
+                if let Err(err) = write_sink.send(message).await {
+                    warn!("WebSocket 客户端 {addr} 发送失败: {err:?}");
+                    break;
+                }
+                if is_close {
+                    break;
+                }
+            }
+        };
+        let reader = async {
+            let Some(protocol) = protocol else {
+                return;
+            };
+            while let Some(Ok(message)) = read_stream.next().await {
+                let receive_time = clock::now_millis();
+                let process_result = match protocol {
+                    ProtocolType::BinaryV1 => Self::process_v1_message(message, &channel),
+                    ProtocolType::HybridV2 => {
+                        Self::process_v2_message(message, receive_time, addr, &sender, &channel)
+                    }
                 };
+                // 无法解析的消息已在处理时跳过，这里的错误意味着连接或前端已不可用
                 if let Err(e) = process_result {
-                    error!("处理消息失败: {e:?}");
+                    error!("处理消息失败，断开: {e:?}");
                     break;
                 }
             }
+        };
+        tokio::select! {
+            _ = writer => {}
+            _ = reader => {}
         }
 
         info!("已断开 WebSocket 客户端: {addr}");
@@ -215,26 +307,86 @@ impl AMLLWebSocketServer {
         Ok(())
     }
 
-    async fn process_v1_message(
-        message: Message,
-        channel: &Channel<v2::Payload>,
+    async fn send_payload(
+        sink: &mut SplitSink<WebSocketStream<TcpStream>, Message>,
+        payload: &v2::Payload,
     ) -> anyhow::Result<()> {
+        let text = serde_json::to_string(payload)?;
+        sink.send(Message::Text(text.into())).await?;
+        Ok(())
+    }
+
+    fn process_v1_message(message: Message, channel: &Channel<v2::Payload>) -> anyhow::Result<()> {
         if let Message::Binary(data) = message {
-            let v1_body = v1::parse_body(&data)?;
-            channel.send(v1_body.into())?;
+            // v1 协议无法报告错误，只能跳过无法解析的消息
+            match v1::parse_body(&data) {
+                Ok(v1_body) => channel.send(v1_body.into())?,
+                Err(err) => warn!("解析 V1 协议的消息失败，已跳过: {err:?}"),
+            }
         }
         Ok(())
     }
 
-    async fn process_v2_message(
+    fn process_v2_message(
         message: Message,
+        receive_time: u64,
+        addr: SocketAddr,
+        sender: &mpsc::Sender<Message>,
         channel: &Channel<v2::Payload>,
     ) -> anyhow::Result<()> {
-        let payload = match message {
-            Message::Text(text) => serde_json::from_str::<v2::MessageV2>(&text)?.payload,
-            Message::Binary(data) => v2::parse_binary_v2(&data)?.into(),
+        let parsed = match message {
+            Message::Text(text) => serde_json::from_str::<v2::MessageV2>(&text)
+                .map(|x| x.payload)
+                .map_err(anyhow::Error::from),
+            Message::Binary(data) => v2::parse_binary_v2(&data).map(Into::into),
             _ => return Ok(()),
         };
+        let payload = match parsed {
+            Ok(payload) => payload,
+            Err(err) => {
+                warn!("解析 V2 协议的消息失败，已跳过: {err:?}");
+                let error = v2::ErrorInfo::new(v2::ErrorCode::MalformedMessage, err.to_string());
+                Self::enqueue_payload(addr, sender, &v2::Payload::Error(error))?;
+                return Ok(());
+            }
+        };
+        match payload {
+            v2::Payload::Command(
+                ref request @ v2::CommandRequest {
+                    command: v2::Command::Unknown,
+                    ..
+                },
+            ) => {
+                debug!("收到了一条无法识别的 V2 指令");
+                let error = v2::ErrorInfo::new(v2::ErrorCode::UnsupportedCommand, "无法识别的指令");
+                let reply = match request.respond(Err(error.clone())) {
+                    Some(result) => v2::Payload::CommandResult(result),
+                    None => v2::Payload::Error(error),
+                };
+                Self::enqueue_payload(addr, sender, &reply)?;
+                return Ok(());
+            }
+            v2::Payload::Unknown | v2::Payload::State(v2::StateUpdate::Unknown) => {
+                debug!("收到了一条无法识别的 V2 消息");
+                let error = v2::ErrorInfo::new(v2::ErrorCode::UnsupportedMessage, "无法识别的消息");
+                Self::enqueue_payload(addr, sender, &v2::Payload::Error(error))?;
+                return Ok(());
+            }
+            v2::Payload::Initialize(_) | v2::Payload::InitializeAck(_) => {
+                warn!("忽略了一条重复的握手消息");
+                return Ok(());
+            }
+            v2::Payload::TimeSyncRequest(request) => {
+                let reply = v2::Payload::TimeSyncResponse(request.respond(receive_time));
+                Self::enqueue_payload(addr, sender, &reply)?;
+                return Ok(());
+            }
+            v2::Payload::TimeSyncResponse(_) => {
+                debug!("忽略了一条未请求的时间同步回应");
+                return Ok(());
+            }
+            _ => {}
+        }
         channel.send(payload)?;
         Ok(())
     }
@@ -8,6 +8,7 @@ import {
 	musicDurationAtom,
 	musicIdAtom,
 	musicLyricLinesAtom,
+	musicLyricOffsetAtom,
 	musicNameAtom,
 	musicPlayingAtom,
 	musicPlayingPositionAtom,
@@ -30,13 +31,105 @@ import { useAtom, useAtomValue, useSetAtom, useStore } from "jotai";
 import { type FC, useEffect, useLayoutEffect, useRef, useState } from "react";
 import { useTranslation } from "react-i18next";
 import { toast } from "react-toastify";
+import { commit } from "virtual:git-metadata-plugin";
 import { useLyricParser } from "../../hooks/useLyricParser";
 import {
 	WebSocketConnectionStatus,
 	wsConnectionStatusAtom,
 	wsServerUrlAtom,
 } from "../../states/appAtoms";
-import type { MessageV2, Payload, RepeatMode as WSRepeatMode } from "./types";
+import type {
+	Capability,
+	MessageV2,
+	Payload,
+	RepeatMode as WSRepeatMode,
+	Spectrum,
+	TimeSyncResponse,
+} from "./types";
+
+/** 与 ws-protocol 中 `PROTOCOL_VERSION` 保持一致 */
+const PROTOCOL_VERSION = 1;
+/** 本客户端支持的可选能力，音频数据暂未处理 */
+const CLIENT_CAPABILITIES: Capability[] = [
+	"coverData",
+	"playbackMode",
+	"clockSync",
+	"spectrum",
+];
+/** 与 ws-protocol 中 `clock::ClockSync` 保持一致，保留的最近采样数量 */
+const MAX_CLOCK_SAMPLES = 8;
+/** 时间同步的间隔，单位为毫秒 */
+const CLOCK_SYNC_INTERVAL = 10000;
+
+/** 一次时间同步的结果，`offset` 为服务端时钟减去本地时钟 */
+interface ClockSample {
+	offset: number;
+	rtt: number;
+}
+
+/** 与 ws-protocol 中 `TimeSyncResponse::sample` 的计算方式一致 */
+const sampleClock = (
+	response: TimeSyncResponse,
+	receiveTime: number,
+): ClockSample => {
+	const t0 = response.clientSendTime;
+	const t1 = response.serverReceiveTime;
+	const t2 = response.serverSendTime;
+	const t3 = receiveTime;
+	return {
+		offset: Math.trunc((t1 - t0 + (t2 - t3)) / 2),
+		rtt: Math.max(t3 - t0 - (t2 - t1), 0),
+	};
+};
+
+/**
+ * 与 ws-protocol 中 `ClockSync::position_at` 的计算方式一致，
+ * 将收到的进度换算为本地时间 `now` 时的进度，使用往返时间最短的采样估算时钟偏移
+ */
+const positionAt = (
+	samples: ClockSample[],
+	progress: number,
+	timestamp: number | undefined,
+	paused: boolean,
+	now: number,
+): number => {
+	if (timestamp === undefined || paused) return progress;
+	const best = samples.reduce<ClockSample | undefined>(
+		(acc, x) => (acc && acc.rtt <= x.rtt ? acc : x),
+		undefined,
+	);
+	const localTime = Math.max(timestamp - (best?.offset ?? 0), 0);
+	return progress + Math.max(now - localTime, 0);
+};
+
+/** 解码频谱中各个频段的幅值，格式无法识别或数据长度不符时返回 `null` */
+const decodeSpectrum = (spectrum: Spectrum): number[] | null => {
+	const bytes = Uint8Array.from(atob(spectrum.data), (c) => c.charCodeAt(0));
+	const view = new DataView(bytes.buffer);
+	const bytesPerValue = { f32: 4, f16: 2, u8: 1 }[spectrum.format];
+	if (!bytesPerValue || bytes.length !== spectrum.bins * bytesPerValue) {
+		return null;
+	}
+	return Array.from({ length: spectrum.bins }, (_, i) => {
+		switch (spectrum.format) {
+			case "f32":
+				return view.getFloat32(i * 4, true);
+			case "f16":
+				return halfToFloat(view.getUint16(i * 2, true));
+			case "u8":
+				return (bytes[i] / 255) * spectrum.peak;
+		}
+	});
+};
+
+const halfToFloat = (bits: number): number => {
+	const sign = bits & 0x8000 ? -1 : 1;
+	const exp = (bits >> 10) & 0x1f;
+	const mant = bits & 0x3ff;
+	if (exp === 0) return sign * 2 ** -14 * (mant / 1024);
+	if (exp === 0x1f) return mant ? Number.NaN : sign * Number.POSITIVE_INFINITY;
+	return sign * 2 ** (exp - 15) * (1 + mant / 1024);
+};
 
 export const WebSocketClientContext: FC = () => {
 	const store = useStore();
@@ -49,6 +142,8 @@ export const WebSocketClientContext: FC = () => {
 	const lastServerProgress = useRef<number>(0);
 	const lastServerUpdateTime = useRef<number>(0);
 	const isPlayingRef = useRef<boolean>(false);
+	const clockSamplesRef = useRef<ClockSample[]>([]);
+	const clockSyncTimerRef = useRef<number | undefined>(undefined);
 	const setMusicPlayingPosition = useSetAtom(musicPlayingPositionAtom);
 
 	const [rawLyricInput, setRawLyricInput] = useState<{
@@ -73,6 +168,25 @@ export const WebSocketClientContext: FC = () => {
 		}
 	};
 
+	const stopClockSync = () => {
+		window.clearInterval(clockSyncTimerRef.current);
+		clockSyncTimerRef.current = undefined;
+	};
+
+	const startClockSync = () => {
+		stopClockSync();
+		const request = () =>
+			send({
+				type: "timeSyncRequest",
+				value: { clientSendTime: Date.now() },
+			});
+		request();
+		clockSyncTimerRef.current = window.setInterval(
+			request,
+			CLOCK_SYNC_INTERVAL,
+		);
+	};
+
 	useLayoutEffect(() => {
 		let isUnmounted = false;
 
@@ -95,13 +209,23 @@ export const WebSocketClientContext: FC = () => {
 				console.log("[WS] Connected");
 				setStatus(WebSocketConnectionStatus.Connected);
 				connectErrorRef.current = false;
-				send({ type: "initialize" });
+				clockSamplesRef.current = [];
+				send({
+					type: "initialize",
+					value: {
+						clientName: "AMLL Player",
+						clientVersion: commit,
+						protocolVersion: PROTOCOL_VERSION,
+						capabilities: CLIENT_CAPABILITIES,
+					},
+				});
 			};
 
 			ws.onclose = () => {
 				console.log("[WS] Closed");
 
 				wsRef.current = null;
+				stopClockSync();
 
 				if (!connectErrorRef.current) {
 					setStatus(WebSocketConnectionStatus.Disconnected);
@@ -132,6 +256,7 @@ export const WebSocketClientContext: FC = () => {
 
 		return () => {
 			isUnmounted = true;
+			stopClockSync();
 			if (wsRef.current) wsRef.current.close();
 		};
 	}, [setStatus, savedWsUrl]);
@@ -144,6 +269,57 @@ export const WebSocketClientContext: FC = () => {
 				send({ type: "pong" });
 				break;
 
+			case "initializeAck":
+				console.log(
+					"[WS] Handshake completed",
+					payload.value.serverName,
+					payload.value.serverVersion,
+					payload.value.capabilities,
+				);
+				if (payload.value.capabilities.includes("clockSync")) {
+					startClockSync();
+				}
+				break;
+
+			case "timeSyncRequest": {
+				const receiveTime = Date.now();
+				send({
+					type: "timeSyncResponse",
+					value: {
+						clientSendTime: payload.value.clientSendTime,
+						serverReceiveTime: receiveTime,
+						serverSendTime: Date.now(),
+					},
+				});
+				break;
+			}
+
+			case "timeSyncResponse": {
+				const samples = clockSamplesRef.current;
+				samples.push(sampleClock(payload.value, Date.now()));
+				if (samples.length > MAX_CLOCK_SAMPLES) samples.shift();
+				break;
+			}
+
+			case "commandResult":
+				if (payload.value.status === "error") {
+					console.warn(
+						"[WS] Command failed",
+						payload.value.requestId,
+						payload.value.code,
+						payload.value.message,
+					);
+				}
+				break;
+
+			case "error":
+				console.warn(
+					"[WS] Server reported an error",
+					payload.value.code,
+					payload.value.message,
+				);
+				break;
+
 			case "state": {
 				const update = payload.value;
 				switch (update.update) {
@@ -172,6 +348,7 @@ export const WebSocketClientContext: FC = () => {
 					}
 
 					case "setLyric": {
+						store.set(musicLyricOffsetAtom, update.offset ?? 0);
 						if (update.format === "structured") {
 							store.set(musicLyricLinesAtom, update.lines);
 							store.set(hideLyricViewAtom, false);
@@ -184,15 +361,25 @@ export const WebSocketClientContext: FC = () => {
 								lyric: update.data,
 								format: update.extraFormat,
 							});
+							store.set(hideLyricViewAtom, false);
 						}
 						break;
 					}
 
-					case "progress":
-						lastServerProgress.current = update.progress;
+					case "progress": {
+						// 补偿进度从服务端发出到现在经过的时间
+						const progress = positionAt(
+							clockSamplesRef.current,
+							update.progress,
+							update.timestamp,
+							!isPlayingRef.current,
+							Date.now(),
+						);
+						lastServerProgress.current = progress;
 						lastServerUpdateTime.current = performance.now();
-						setMusicPlayingPosition(update.progress);
+						setMusicPlayingPosition(progress);
 						break;
+					}
 
 					case "paused":
 						store.set(musicPlayingAtom, false);
@@ -218,6 +405,12 @@ export const WebSocketClientContext: FC = () => {
 						store.set(isShuffleActiveAtom, update.shuffle);
 						break;
 					}
+
+					case "spectrum": {
+						const data = decodeSpectrum(update);
+						if (data) store.set(fftDataAtom, data);
+						break;
+					}
 				}
 				break;
 			}
@@ -14,6 +14,16 @@ export interface MusicInfo {
 	albumName: string;
 	artists: Artist[];
 	duration: number;
+	albumArtist?: string;
+	trackNumber?: number;
+	discNumber?: number;
+	year?: number;
+	genres?: string[];
+	isrc?: string;
+	explicit?: boolean;
+	coverHash?: string;
+	/** 歌词的时间偏移，单位为毫秒，正数会让歌词延后显示 */
+	lyricOffset?: number;
 }
 
 export interface ImageData {
@@ -25,10 +35,13 @@ export type AlbumCover =
 	| { source: "Uri"; url: string }
 	| { source: "Data"; image: ImageData };
 
-export type LyricContent =
+export type LyricFormat = "lrc" | "eslrc" | "yrc" | "qrc" | "lys" | "ttml";
+
+export type LyricContent = (
 	| {
 			format: "structured";
 			lines: LyricLine[];
+			metadata?: [string, string[]][];
 	  }
 	| {
 			format: "ttml";
@@ -37,8 +50,12 @@ export type LyricContent =
 	| {
 			format: "raw";
 			data: string;
-			extraFormat: "lrc" | "yrc" | "qrc" | "eslrc" | "lys";
-	  };
+			extraFormat: LyricFormat;
+	  }
+) & {
+	/** 歌词的时间偏移，单位为毫秒，正数会让歌词延后显示 */
+	offset?: number;
+};
 
 export type Command =
 	| { command: "pause" }
@@ -48,24 +65,126 @@ export type Command =
 	| { command: "setVolume"; volume: number }
 	| { command: "seekPlayProgress"; progress: number }
 	| { command: "setRepeatMode"; mode: RepeatMode }
-	| { command: "setShuffleMode"; enabled: boolean };
+	| { command: "setShuffleMode"; enabled: boolean }
+	| { command: "jumpToSong"; songIndex: number }
+	| { command: "enqueue"; entries: MusicInfo[]; index?: number }
+	| { command: "removeFromQueue"; index: number };
+
+export type ErrorCode =
+	| "malformedMessage"
+	| "unsupportedMessage"
+	| "unsupportedCommand"
+	| "missingCapability"
+	| "invalidArgument"
+	| "unavailable"
+	| "internal";
+
+export interface ErrorInfo {
+	code: ErrorCode;
+	message: string;
+}
+
+export type CommandResult = { requestId: number } & (
+	| { status: "ok" }
+	| ({ status: "error" } & ErrorInfo)
+);
+
+export type Capability =
+	| "audioData"
+	| "coverData"
+	| "playbackMode"
+	| "clockSync"
+	| "queue"
+	| "spectrum";
+
+export interface Initialize {
+	clientName: string;
+	clientVersion: string;
+	protocolVersion: number;
+	capabilities: Capability[];
+}
+
+export interface InitializeAck {
+	serverName: string;
+	serverVersion: string;
+	protocolVersion: number;
+	capabilities: Capability[];
+}
+
+export type SampleFormat = "u8" | "s16" | "u16" | "s24" | "s32" | "f32";
+
+export type AudioCodec = "pcm" | "opus" | "flac";
+
+export interface AudioStreamInfo {
+	sampleFormat: SampleFormat;
+	sampleRate: number;
+	channels: number;
+	interleaved?: boolean;
+	codec?: AudioCodec;
+}
+
+export type FrequencyScale = "linear" | "logarithmic";
+
+export type SpectrumFormat = "f32" | "f16" | "u8";
+
+export interface Spectrum {
+	minFrequency: number;
+	maxFrequency: number;
+	scale?: FrequencyScale;
+	format: SpectrumFormat;
+	peak: number;
+	bins: number;
+	/** Base64 编码的各个频段的幅值，多字节格式均为小端序 */
+	data: string;
+}
+
+export interface TimeSyncRequest {
+	clientSendTime: number;
+}
+
+export interface TimeSyncResponse {
+	clientSendTime: number;
+	serverReceiveTime: number;
+	serverSendTime: number;
+}
 
 export type StateUpdate =
 	| ({ update: "setMusic" } & MusicInfo)
 	| ({ update: "setCover" } & AlbumCover)
 	| ({ update: "setLyric" } & LyricContent)
-	| { update: "progress"; progress: number }
+	| { update: "progress"; progress: number; timestamp?: number }
 	| { update: "volume"; volume: number }
-	| { update: "paused" }
-	| { update: "resumed" }
-	| { update: "audioData"; data: number[] }
-	| { update: "modeChanged"; repeat: RepeatMode; shuffle: boolean };
+	| { update: "paused"; timestamp?: number }
+	| { update: "resumed"; timestamp?: number }
+	| {
+			update: "audioData";
+			data: number[];
+			sequence?: number;
+			timestamp?: number;
+	  }
+	| ({ update: "audioFormat" } & AudioStreamInfo)
+	| ({ update: "spectrum" } & Spectrum)
+	| { update: "modeChanged"; repeat: RepeatMode; shuffle: boolean }
+	| {
+			update: "setQueue";
+			entries: MusicInfo[];
+			currentIndex: number | null;
+	  }
+	| { update: "queueInsert"; index: number; entries: MusicInfo[] }
+	| { update: "queueRemove"; index: number }
+	| { update: "queueMove"; from: number; to: number }
+	| { update: "queueIndex"; currentIndex: number | null };
 
 export type Payload =
-	| { type: "initialize" }
+	| { type: "initialize"; value: Initialize }
+	| { type: "initializeAck"; value: InitializeAck }
 	| { type: "ping" }
 	| { type: "pong" }
-	| { type: "command"; value: Command }
-	| { type: "state"; value: StateUpdate };
+	| { type: "command"; value: Command & { requestId?: number } }
+	| { type: "state"; value: StateUpdate }
+	| { type: "timeSyncRequest"; value: TimeSyncRequest }
+	| { type: "timeSyncResponse"; value: TimeSyncResponse }
+	| { type: "commandResult"; value: CommandResult }
+	| { type: "error"; value: ErrorInfo };
 
 export type MessageV2 = Payload;
@@ -93,13 +93,21 @@ impl AMLLWebSocketServer {
         // sender.emit_all("on-client-connected", addr)?;
         conn_addrs.lock().unwrap().insert(addr.to_owned());
 
-        let (write, read) = wss.split();
+        let (write, mut read) = wss.split();
 
         conns.lock().await.push(write);
 
-        let mut read = read.try_filter(|x| future::ready(x.is_binary()));
-
+        // 无法解析的消息只会被跳过，不会断开连接
         while let Some(Ok(data)) = read.next().await {
+            if data.is_text() {
+                warn!(
+                    "WebSocket 客户端 {addr} 发送了文本消息，本播放器只支持 BinaryV1 协议，已跳过"
+                );
+                continue;
+            }
+            if !data.is_binary() {
+                continue;
+            }
             match ws_protocol::parse_body(&data.into_data()) {
                 Ok(Body::OnPlayProgress { progress }) => {
                     sender.send(Body::OnPlayProgress { progress }).await?;
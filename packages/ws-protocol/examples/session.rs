@@ -0,0 +1,101 @@
+//! 录制和回放 WebSocket 会话，用于制作歌词播放器的回归测试数据
+//!
+//! ```text
+//! # 在指定地址上等待播放软件连接，将收到的消息录制到文件中，连接断开后结束录制
+//! cargo run -p ws-protocol --example session -- record session.jsonl 127.0.0.1:11444
+//! # 连接到歌词播放器，以两倍速回放录制的会话
+//! cargo run -p ws-protocol --example session -- replay session.jsonl ws://localhost:11444 2
+//! ```
+
+use std::{fs::File, io::BufReader, net::TcpListener, time::Duration};
+
+use tungstenite::{Bytes, Message};
+use ws_protocol::{
+    record::{FrameData, Recorder, read_recording, replay_delay},
+    v2,
+};
+
+const DEFAULT_ADDR: &str = "127.0.0.1:11444";
+const DEFAULT_URL: &str = "ws://localhost:11444";
+
+fn main() -> anyhow::Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let arg = |i: usize| args.get(i).map(String::as_str);
+    match (arg(0), arg(1)) {
+        (Some("record"), Some(path)) => record(path, arg(2).unwrap_or(DEFAULT_ADDR)),
+        (Some("replay"), Some(path)) => replay(
+            path,
+            arg(2).unwrap_or(DEFAULT_URL),
+            arg(3).map(str::parse).transpose()?.unwrap_or(1.0),
+        ),
+        _ => {
+            eprintln!("用法: session record <文件> [监听地址]");
+            eprintln!("      session replay <文件> [服务端地址] [回放速度]");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn record(path: &str, addr: &str) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    println!("正在 {addr} 上等待连接");
+    let (stream, peer) = listener.accept()?;
+    let mut ws = tungstenite::accept(stream)?;
+    println!("{peer} 已连接，开始录制到 {path}");
+
+    let mut recorder = Recorder::new(File::create(path)?);
+    let mut count = 0;
+    loop {
+        let message = match ws.read() {
+            Ok(message) => message,
+            Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => break,
+            Err(err) => return Err(err.into()),
+        };
+        let data = match message {
+            Message::Text(text) => {
+                // 回应握手，让 HybridV2 客户端发送所有可选能力的消息
+                if let Ok(v2::MessageV2 {
+                    payload: v2::Payload::Initialize(init),
+                }) = serde_json::from_str(&text)
+                    && !init.is_legacy()
+                {
+                    let ack = init.accept("ws-protocol-recorder", "0.0.0", v2::Capability::ALL);
+                    let reply = serde_json::to_string(&v2::Payload::InitializeAck(ack))?;
+                    ws.send(Message::text(reply))?;
+                }
+                FrameData::Text(text.to_string())
+            }
+            Message::Binary(data) => FrameData::Binary(data.to_vec()),
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        recorder.record(data)?;
+        count += 1;
+    }
+    println!("录制结束，共 {count} 条消息");
+    Ok(())
+}
+
+fn replay(path: &str, url: &str, speed: f64) -> anyhow::Result<()> {
+    let (mut ws, _res) = tungstenite::connect(url)?;
+    println!("已连接到 {url}，以 {speed} 倍速回放 {path}");
+
+    let mut previous = 0;
+    let mut count = 0;
+    for frame in read_recording(BufReader::new(File::open(path)?)) {
+        let frame = frame?;
+        std::thread::sleep(Duration::from_millis(replay_delay(
+            previous, frame.time, speed,
+        )));
+        previous = frame.time;
+        let message = match frame.data {
+            FrameData::Text(text) => Message::text(text),
+            FrameData::Binary(data) => Message::Binary(Bytes::from(data)),
+        };
+        ws.send(message)?;
+        count += 1;
+    }
+    println!("回放结束，共 {count} 条消息");
+    ws.close(None)?;
+    Ok(())
+}
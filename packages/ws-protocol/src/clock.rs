@@ -0,0 +1,189 @@
+//! 时间同步与播放进度补偿
+//!
+//! 使用类似 NTP 的方式估算对端时钟相对本地时钟的偏移：
+//! 请求方在 `t0` 发送 [`TimeSyncRequest`]，回应方在 `t1` 收到并在 `t2` 发回
+//! [`TimeSyncResponse`]，请求方在 `t3` 收到回应，则
+//!
+//! - 往返时间 `rtt = (t3 - t0) - (t2 - t1)`
+//! - 时钟偏移 `offset = ((t1 - t0) + (t2 - t3)) / 2`，即对端时钟减去本地时钟
+//!
+//! 之后收到带有时间戳的进度更新时，即可将其换算为本地时钟下的“当前进度”。
+
+use std::collections::VecDeque;
+
+use crate::v2::{StateUpdate, TimeSyncRequest, TimeSyncResponse};
+
+/// 保留的最近采样数量
+const MAX_SAMPLES: usize = 8;
+
+/// 获取本地时钟的当前时间，即 Unix 时间戳，单位为毫秒
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|x| x.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// 获取本地时钟的当前时间，即 Unix 时间戳，单位为毫秒
+#[cfg(target_arch = "wasm32")]
+pub fn now_millis() -> u64 {
+    js_sys::Date::now() as u64
+}
+
+/// 一次时间同步的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSample {
+    /// 对端时钟减去本地时钟，单位为毫秒
+    pub offset: i64,
+    /// 往返时间，单位为毫秒
+    pub rtt: u64,
+}
+
+impl TimeSyncResponse {
+    /// 根据收到回应的本地时间计算一次采样
+    pub fn sample(&self, client_receive_time: u64) -> ClockSample {
+        let t0 = self.client_send_time as i64;
+        let t1 = self.server_receive_time as i64;
+        let t2 = self.server_send_time as i64;
+        let t3 = client_receive_time as i64;
+        ClockSample {
+            offset: ((t1 - t0) + (t2 - t3)) / 2,
+            rtt: ((t3 - t0) - (t2 - t1)).max(0) as u64,
+        }
+    }
+}
+
+/// 根据多次时间同步结果估算对端时钟，并补偿播放进度
+///
+/// 网络抖动会使单次采样不准确，因此会保留最近的若干次采样，
+/// 并使用其中往返时间最短的一次作为估算结果。
+#[derive(Debug, Clone, Default)]
+pub struct ClockSync {
+    samples: VecDeque<ClockSample>,
+}
+
+impl ClockSync {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 生成一个以当前时间为发送时间的请求
+    pub fn request(&self) -> TimeSyncRequest {
+        TimeSyncRequest {
+            client_send_time: now_millis(),
+        }
+    }
+
+    /// 记录一次回应，`receive_time` 为收到回应的本地时间
+    pub fn add_response(&mut self, response: &TimeSyncResponse, receive_time: u64) -> ClockSample {
+        let sample = response.sample(receive_time);
+        self.add_sample(sample);
+        sample
+    }
+
+    /// 记录一次采样
+    pub fn add_sample(&mut self, sample: ClockSample) {
+        if self.samples.len() >= MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// 当前最可信的采样，即往返时间最短的采样
+    pub fn best_sample(&self) -> Option<ClockSample> {
+        self.samples.iter().min_by_key(|x| x.rtt).copied()
+    }
+
+    /// 估算的时钟偏移，尚未同步时为 0
+    pub fn offset(&self) -> i64 {
+        self.best_sample().map(|x| x.offset).unwrap_or_default()
+    }
+
+    /// 估算的往返时间，尚未同步时为 `None`
+    pub fn rtt(&self) -> Option<u64> {
+        self.best_sample().map(|x| x.rtt)
+    }
+
+    /// 将对端时钟下的时间换算为本地时钟下的时间
+    pub fn to_local_time(&self, remote_time: u64) -> u64 {
+        (remote_time as i64 - self.offset()).max(0) as u64
+    }
+
+    /// 将收到的进度换算为本地时间 `now` 时的进度
+    ///
+    /// `timestamp` 为进度更新携带的对端时间戳，没有时间戳或处于暂停状态时原样返回进度。
+    pub fn position_at(
+        &self,
+        progress: u64,
+        timestamp: Option<u64>,
+        paused: bool,
+        now: u64,
+    ) -> u64 {
+        match timestamp {
+            Some(timestamp) if !paused => {
+                progress + now.saturating_sub(self.to_local_time(timestamp))
+            }
+            _ => progress,
+        }
+    }
+
+    /// 将收到的 [`StateUpdate::Progress`] 换算为当前的进度，其它更新返回 `None`
+    pub fn position_now(&self, update: &StateUpdate, paused: bool) -> Option<u64> {
+        match update {
+            StateUpdate::Progress {
+                progress,
+                timestamp,
+            } => Some(self.position_at(*progress, *timestamp, paused, now_millis())),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_sync_test() {
+        // 对端时钟比本地快 1000 毫秒，单程延迟 20 毫秒，对端处理耗时 5 毫秒
+        let response = TimeSyncResponse {
+            client_send_time: 10_000,
+            server_receive_time: 11_020,
+            server_send_time: 11_025,
+        };
+        let sample = response.sample(10_045);
+        assert_eq!(
+            sample,
+            ClockSample {
+                offset: 1000,
+                rtt: 40
+            }
+        );
+
+        let mut sync = ClockSync::new();
+        assert_eq!(sync.rtt(), None);
+        sync.add_sample(sample);
+        // 抖动较大的采样不会影响估算结果
+        sync.add_sample(ClockSample {
+            offset: 1200,
+            rtt: 400,
+        });
+        assert_eq!(sync.offset(), 1000);
+        assert_eq!(sync.rtt(), Some(40));
+
+        // 对端在其时钟 21_000 时报告进度 5000，本地时间 20_100 时应已播放 100 毫秒
+        assert_eq!(sync.to_local_time(21_000), 20_000);
+        assert_eq!(sync.position_at(5000, Some(21_000), false, 20_100), 5100);
+        assert_eq!(sync.position_at(5000, Some(21_000), true, 20_100), 5000);
+        assert_eq!(sync.position_at(5000, None, false, 20_100), 5000);
+
+        for _ in 0..MAX_SAMPLES {
+            sync.add_sample(ClockSample {
+                offset: 0,
+                rtt: 100,
+            });
+        }
+        assert_eq!(sync.offset(), 0);
+    }
+}
@@ -97,6 +97,36 @@ pub struct LyricWord {
     #[brw(ignore)]
     #[serde(default)]
     pub roman_word: NullString,
+    // 以下单词属性同样只能通过v2协议传输
+    /// 该单词是否为不雅用语
+    #[brw(ignore)]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub obscene: bool,
+    /// 该单词的空拍数量
+    #[brw(ignore)]
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub empty_beat: u32,
+    /// 其他自定义的单词属性，以 `[键, 值]` 的形式存储
+    #[brw(ignore)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<(String, String)>,
+    /// 单词的注音片段，单词本身即为注音的基文本
+    #[brw(ignore)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ruby: Vec<LyricRuby>,
+}
+
+/// 单词注音的一个片段
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LyricRuby {
+    pub start_time: u64,
+    pub end_time: u64,
+    pub word: String,
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
 }
 
 #[binrw]
@@ -126,3 +156,85 @@ pub struct LyricLine {
     #[bw(ignore)]
     pub is_duet: bool,
 }
+
+#[cfg(feature = "amll-lyric")]
+impl From<&amll_lyric::LyricWord<'_>> for LyricWord {
+    fn from(word: &amll_lyric::LyricWord<'_>) -> Self {
+        Self {
+            start_time: word.start_time,
+            end_time: word.end_time,
+            word: word.word.as_ref().into(),
+            roman_word: word.roman_word.as_ref().into(),
+            obscene: word.obscene,
+            empty_beat: word.empty_beat,
+            attributes: word
+                .attributes
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            ruby: word
+                .ruby
+                .iter()
+                .map(|ruby| LyricRuby {
+                    start_time: ruby.start_time,
+                    end_time: ruby.end_time,
+                    word: ruby.word.to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(feature = "amll-lyric")]
+impl From<&amll_lyric::LyricLine<'_>> for LyricLine {
+    fn from(line: &amll_lyric::LyricLine<'_>) -> Self {
+        Self {
+            start_time: line.start_time,
+            end_time: line.end_time,
+            words: line.words.iter().map(LyricWord::from).collect(),
+            translated_lyric: line.translated_lyric.as_ref().into(),
+            roman_lyric: line.roman_lyric.as_ref().into(),
+            is_bg: line.is_bg,
+            is_duet: line.is_duet,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_attributes_test() {
+        let word = LyricWord {
+            start_time: 0,
+            end_time: 500,
+            word: "oh".into(),
+            obscene: true,
+            empty_beat: 2,
+            attributes: vec![("x-style".into(), "whisper".into())],
+            ruby: vec![LyricRuby {
+                start_time: 0,
+                end_time: 500,
+                word: "おー".into(),
+            }],
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&word).unwrap();
+        assert_eq!(
+            json,
+            r#"{"startTime":0,"endTime":500,"word":"oh","romanWord":"","obscene":true,"emptyBeat":2,"attributes":[["x-style","whisper"]],"ruby":[{"startTime":0,"endTime":500,"word":"おー"}]}"#
+        );
+        assert_eq!(serde_json::from_str::<LyricWord>(&json).unwrap(), word);
+
+        // 二进制协议不会携带这些属性
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        word.write(&mut cursor).unwrap();
+        cursor.set_position(0);
+        let decoded = LyricWord::read(&mut cursor).unwrap();
+        assert!(!decoded.obscene);
+        assert_eq!(decoded.empty_beat, 0);
+        assert!(decoded.attributes.is_empty());
+        assert!(decoded.ruby.is_empty());
+    }
+}
@@ -4,7 +4,12 @@
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
+pub mod clock;
 pub mod common;
+#[cfg(feature = "tokio")]
+pub mod net;
+pub mod queue;
+pub mod record;
 pub mod translation;
 pub mod v1;
 pub mod v2;
@@ -0,0 +1,353 @@
+//! 基于 tokio 的 WebSocket 服务端和客户端实现，需要启用 `tokio` 功能
+//!
+//! 服务端会根据客户端发送的第一条消息识别其使用的协议：
+//! 二进制消息视为 BinaryV1 协议，`initialize` 文本消息视为 HybridV2 协议。
+//! 双方收发的消息都统一为 [`v2::Payload`]，与 BinaryV1 协议的转换由本模块自动完成。
+
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{v1, v2};
+
+pub mod client;
+pub mod server;
+
+pub use client::*;
+pub use server::*;
+
+/// 每个连接默认的发送队列长度
+pub const DEFAULT_QUEUE_SIZE: usize = 64;
+
+/// 连接使用的协议
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProtocolType {
+    BinaryV1,
+    HybridV2,
+}
+
+/// 根据连接的第一条消息识别协议
+///
+/// HybridV2 协议的第一条消息必须是 `initialize`，此时会一同返回握手信息。
+pub fn sniff_protocol(message: &Message) -> Option<(ProtocolType, Option<v2::Initialize>)> {
+    match message {
+        Message::Binary(_) => Some((ProtocolType::BinaryV1, None)),
+        Message::Text(text) => match serde_json::from_str::<v2::MessageV2>(text) {
+            Ok(v2::MessageV2 {
+                payload: v2::Payload::Initialize(init),
+            }) => Some((ProtocolType::HybridV2, Some(init))),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// 将消息编码为指定协议的 WebSocket 消息
+///
+/// 无法转换为 BinaryV1 协议的消息会返回错误。
+pub fn encode_payload(payload: &v2::Payload, protocol: ProtocolType) -> anyhow::Result<Message> {
+    Ok(match protocol {
+        ProtocolType::BinaryV1 => {
+            let body = v1::Body::try_from(payload.clone())?;
+            Message::binary(v1::to_body(&body)?)
+        }
+        ProtocolType::HybridV2 => Message::text(serde_json::to_string(payload)?),
+    })
+}
+
+/// 解码指定协议的 WebSocket 消息，控制帧等不携带数据的消息返回 `None`
+pub fn decode_message(
+    message: &Message,
+    protocol: ProtocolType,
+) -> anyhow::Result<Option<v2::Payload>> {
+    Ok(match (message, protocol) {
+        (Message::Binary(data), ProtocolType::BinaryV1) => Some(v1::parse_body(data)?.into()),
+        (Message::Binary(data), ProtocolType::HybridV2) => Some(v2::parse_binary_v2(data)?.into()),
+        (Message::Text(text), ProtocolType::HybridV2) => {
+            Some(serde_json::from_str::<v2::MessageV2>(text)?.payload)
+        }
+        _ => None,
+    })
+}
+
+/// 对端发送的需要自动回应的消息，返回回应的消息
+///
+/// 包括 Ping 和时间同步请求，`receive_time` 为收到消息的本地时间。
+pub(crate) fn auto_reply(payload: &v2::Payload, receive_time: u64) -> Option<v2::Payload> {
+    match payload {
+        v2::Payload::Ping => Some(v2::Payload::Pong),
+        v2::Payload::TimeSyncRequest(request) => {
+            Some(v2::Payload::TimeSyncResponse(request.respond(receive_time)))
+        }
+        _ => None,
+    }
+}
+
+/// 是否为应当忽略的消息，包括无法识别的消息和重复的握手消息
+pub(crate) fn is_ignored(payload: &v2::Payload) -> bool {
+    matches!(
+        payload,
+        v2::Payload::Unknown
+            | v2::Payload::Command(v2::CommandRequest {
+                command: v2::Command::Unknown,
+                ..
+            })
+            | v2::Payload::State(v2::StateUpdate::Unknown)
+            | v2::Payload::Initialize(_)
+    )
+}
+
+/// 服务端收到无法识别的消息时回应的错误
+///
+/// 带有请求 ID 的无法识别的指令会以 [`v2::Payload::CommandResult`] 回应。
+pub(crate) fn unsupported_reply(payload: &v2::Payload) -> Option<v2::Payload> {
+    Some(match payload {
+        v2::Payload::Command(
+            request @ v2::CommandRequest {
+                command: v2::Command::Unknown,
+                ..
+            },
+        ) => {
+            let error = v2::ErrorInfo::new(v2::ErrorCode::UnsupportedCommand, "无法识别的指令");
+            match request.respond(Err(error.clone())) {
+                Some(result) => v2::Payload::CommandResult(result),
+                None => v2::Payload::Error(error),
+            }
+        }
+        v2::Payload::Unknown | v2::Payload::State(v2::StateUpdate::Unknown) => v2::Payload::Error(
+            v2::ErrorInfo::new(v2::ErrorCode::UnsupportedMessage, "无法识别的消息"),
+        ),
+        _ => return None,
+    })
+}
+
+/// 消息无法解析时回应的错误
+pub(crate) fn malformed_reply(err: &anyhow::Error) -> v2::Payload {
+    v2::Payload::Error(v2::ErrorInfo::new(
+        v2::ErrorCode::MalformedMessage,
+        err.to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::v2::{Capability, Command, Initialize, Payload, StateUpdate};
+
+    async fn next_server_event(server: &mut Server) -> ServerEvent {
+        tokio::time::timeout(Duration::from_secs(5), server.next_event())
+            .await
+            .expect("timed out waiting for server event")
+            .expect("server closed")
+    }
+
+    async fn next_client_event(client: &mut Client) -> ClientEvent {
+        tokio::time::timeout(Duration::from_secs(5), client.next_event())
+            .await
+            .expect("timed out waiting for client event")
+            .expect("client closed")
+    }
+
+    #[tokio::test]
+    async fn loopback_v2_test() {
+        let mut server = Server::bind("127.0.0.1:0", ServerConfig::default())
+            .await
+            .unwrap();
+        let url = format!("ws://{}", server.local_addr());
+
+        let mut client = Client::connect(
+            &url,
+            Initialize::new("test", "0.0.0", &[Capability::ClockSync]),
+        )
+        .await
+        .unwrap();
+
+        let ServerEvent::Connected {
+            addr,
+            protocol,
+            client: init,
+        } = next_server_event(&mut server).await
+        else {
+            panic!("expected connected event");
+        };
+        assert_eq!(protocol, ProtocolType::HybridV2);
+        assert_eq!(init.unwrap().client_name, "test");
+        assert_eq!(server.connections().await, [addr]);
+
+        let ClientEvent::Acknowledged(ack) = next_client_event(&mut client).await else {
+            panic!("expected acknowledge event");
+        };
+        assert_eq!(ack.capabilities, [Capability::ClockSync]);
+
+        client
+            .send(&Payload::from(Command::SetVolume { volume: 0.5 }))
+            .await
+            .unwrap();
+        let ServerEvent::Message { payload, .. } = next_server_event(&mut server).await else {
+            panic!("expected message event");
+        };
+        assert_eq!(payload, Payload::from(Command::SetVolume { volume: 0.5 }));
+
+        // 未协商能力的消息不会被广播
+        server
+            .broadcast(&Payload::State(StateUpdate::ModeChanged {
+                repeat: v2::RepeatMode::All,
+                shuffle: true,
+            }))
+            .await;
+        server
+            .broadcast(&Payload::State(StateUpdate::Progress {
+                progress: 1000,
+                timestamp: Some(1),
+            }))
+            .await;
+        assert_eq!(
+            next_client_event(&mut client).await,
+            ClientEvent::Message(Payload::State(StateUpdate::Progress {
+                progress: 1000,
+                timestamp: Some(1),
+            }))
+        );
+
+        // 时间同步请求会被自动回应
+        client
+            .send(&Payload::TimeSyncRequest(v2::TimeSyncRequest {
+                client_send_time: 1,
+            }))
+            .await
+            .unwrap();
+        let ClientEvent::Message(Payload::TimeSyncResponse(response)) =
+            next_client_event(&mut client).await
+        else {
+            panic!("expected time sync response");
+        };
+        assert_eq!(response.client_send_time, 1);
+
+        drop(client);
+        assert_eq!(
+            next_server_event(&mut server).await,
+            ServerEvent::Disconnected { addr }
+        );
+        assert!(server.connections().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn loopback_v1_test() {
+        let mut server = Server::bind("127.0.0.1:0", ServerConfig::default())
+            .await
+            .unwrap();
+        let url = format!("ws://{}", server.local_addr());
+
+        let mut client = Client::connect_v1(&url).await.unwrap();
+        client
+            .send(&Payload::State(StateUpdate::Paused { timestamp: None }))
+            .await
+            .unwrap();
+
+        let ServerEvent::Connected { protocol, .. } = next_server_event(&mut server).await else {
+            panic!("expected connected event");
+        };
+        assert_eq!(protocol, ProtocolType::BinaryV1);
+        let ServerEvent::Message { payload, .. } = next_server_event(&mut server).await else {
+            panic!("expected message event");
+        };
+        assert_eq!(
+            payload,
+            Payload::State(StateUpdate::Paused { timestamp: None })
+        );
+
+        // 无法转换为 v1 协议的消息会被跳过
+        server
+            .broadcast(&Payload::TimeSyncRequest(v2::TimeSyncRequest {
+                client_send_time: 0,
+            }))
+            .await;
+        server.broadcast(&Payload::from(Command::Pause)).await;
+        assert_eq!(
+            next_client_event(&mut client).await,
+            ClientEvent::Message(Payload::from(Command::Pause))
+        );
+        assert!(
+            client
+                .send(&Payload::from(Command::SetShuffleMode { enabled: true }))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn malformed_message_test() {
+        use futures_util::{SinkExt, StreamExt};
+
+        let mut server = Server::bind("127.0.0.1:0", ServerConfig::default())
+            .await
+            .unwrap();
+        let url = format!("ws://{}", server.local_addr());
+        let (mut ws, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+
+        async fn next_payload(
+            ws: &mut tokio_tungstenite::WebSocketStream<
+                tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+            >,
+        ) -> Payload {
+            let message = tokio::time::timeout(Duration::from_secs(5), ws.next())
+                .await
+                .expect("timed out waiting for message")
+                .unwrap()
+                .unwrap();
+            decode_message(&message, ProtocolType::HybridV2)
+                .unwrap()
+                .unwrap()
+        }
+
+        // 第一条消息不是握手消息时会收到错误，但连接不会断开
+        ws.send(Message::text("hello")).await.unwrap();
+        let Payload::Error(error) = next_payload(&mut ws).await else {
+            panic!("expected error");
+        };
+        assert_eq!(error.code, v2::ErrorCode::MalformedMessage);
+
+        let init = Payload::Initialize(Initialize::new("test", "0.0.0", &[]));
+        ws.send(encode_payload(&init, ProtocolType::HybridV2).unwrap())
+            .await
+            .unwrap();
+        assert!(matches!(
+            next_payload(&mut ws).await,
+            Payload::InitializeAck(_)
+        ));
+        assert!(matches!(
+            next_server_event(&mut server).await,
+            ServerEvent::Connected { .. }
+        ));
+
+        ws.send(Message::text(
+            r#"{"type":"state","value":{"update":"progress"}}"#,
+        ))
+        .await
+        .unwrap();
+        let Payload::Error(error) = next_payload(&mut ws).await else {
+            panic!("expected error");
+        };
+        assert_eq!(error.code, v2::ErrorCode::MalformedMessage);
+
+        ws.send(Message::text(
+            r#"{"type":"command","value":{"command":"dance","requestId":5}}"#,
+        ))
+        .await
+        .unwrap();
+        let Payload::CommandResult(result) = next_payload(&mut ws).await else {
+            panic!("expected command result");
+        };
+        assert_eq!(result.request_id, 5);
+        assert!(!result.is_ok());
+
+        let command = Payload::Command(v2::CommandRequest::new(Command::Pause, 6));
+        ws.send(encode_payload(&command, ProtocolType::HybridV2).unwrap())
+            .await
+            .unwrap();
+        let ServerEvent::Message { payload, .. } = next_server_event(&mut server).await else {
+            panic!("expected message event");
+        };
+        assert_eq!(payload, command);
+    }
+}
@@ -0,0 +1,147 @@
+use futures_util::{SinkExt, StreamExt};
+use tokio::{sync::mpsc, task::JoinHandle};
+use tokio_tungstenite::tungstenite::Message;
+
+use super::{
+    DEFAULT_QUEUE_SIZE, ProtocolType, auto_reply, decode_message, encode_payload, is_ignored,
+};
+use crate::{clock, v2};
+
+/// 客户端事件
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientEvent {
+    /// 服务端回应了握手，旧版服务端不会发送此事件
+    Acknowledged(v2::InitializeAck),
+    /// 收到了服务端的消息，BinaryV1 协议的消息已转换为 HybridV2 协议
+    Message(v2::Payload),
+}
+
+/// WebSocket 客户端
+///
+/// 连接断开后 [`Client::next_event`] 会返回 `None`，客户端被丢弃时会断开连接。
+pub struct Client {
+    protocol: ProtocolType,
+    sender: mpsc::Sender<Message>,
+    events: mpsc::UnboundedReceiver<ClientEvent>,
+    task: JoinHandle<()>,
+}
+
+impl Client {
+    /// 以 HybridV2 协议连接到服务端，并发送握手信息
+    pub async fn connect(url: &str, init: v2::Initialize) -> anyhow::Result<Self> {
+        let client = Self::connect_with(url, ProtocolType::HybridV2).await?;
+        client.send(&v2::Payload::Initialize(init)).await?;
+        Ok(client)
+    }
+
+    /// 以 BinaryV1 协议连接到服务端
+    ///
+    /// 服务端会在收到第一条消息后才识别出协议，在此之前不会向客户端发送任何消息。
+    pub async fn connect_v1(url: &str) -> anyhow::Result<Self> {
+        Self::connect_with(url, ProtocolType::BinaryV1).await
+    }
+
+    async fn connect_with(url: &str, protocol: ProtocolType) -> anyhow::Result<Self> {
+        let (ws, _) = tokio_tungstenite::connect_async(url).await?;
+        let (sender, receiver) = mpsc::channel(DEFAULT_QUEUE_SIZE);
+        let (event_sender, events) = mpsc::unbounded_channel();
+        let task = tokio::spawn(run_connection(
+            ws,
+            protocol,
+            sender.clone(),
+            receiver,
+            event_sender,
+        ));
+        Ok(Self {
+            protocol,
+            sender,
+            events,
+            task,
+        })
+    }
+
+    /// 连接使用的协议
+    pub fn protocol(&self) -> ProtocolType {
+        self.protocol
+    }
+
+    /// 发送消息，消息无法以连接的协议编码或连接已断开时返回错误
+    pub async fn send(&self, payload: &v2::Payload) -> anyhow::Result<()> {
+        let message = encode_payload(payload, self.protocol)?;
+        self.sender.send(message).await?;
+        Ok(())
+    }
+
+    /// 等待下一个事件，连接断开后返回 `None`
+    pub async fn next_event(&mut self) -> Option<ClientEvent> {
+        self.events.recv().await
+    }
+
+    /// 事件的接收端，可用于和其它异步任务一同等待
+    pub fn events(&mut self) -> &mut mpsc::UnboundedReceiver<ClientEvent> {
+        &mut self.events
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn run_connection(
+    ws: tokio_tungstenite::WebSocketStream<
+        tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+    >,
+    protocol: ProtocolType,
+    sender: mpsc::Sender<Message>,
+    mut receiver: mpsc::Receiver<Message>,
+    events: mpsc::UnboundedSender<ClientEvent>,
+) {
+    let (mut sink, mut stream) = ws.split();
+
+    let writer = async {
+        while let Some(message) = receiver.recv().await {
+            if sink.send(message).await.is_err() {
+                break;
+            }
+        }
+        let _ = sink.close().await;
+    };
+
+    let reader = async {
+        while let Some(Ok(message)) = stream.next().await {
+            let receive_time = clock::now_millis();
+            let payload = match decode_message(&message, protocol) {
+                Ok(Some(payload)) => payload,
+                Ok(None) => continue,
+                Err(_err) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!("解析来自 WebSocket 服务端的消息失败: {_err:?}");
+                    continue;
+                }
+            };
+            if is_ignored(&payload) {
+                continue;
+            }
+            if let Some(reply) = auto_reply(&payload, receive_time) {
+                if let Ok(message) = encode_payload(&reply, protocol) {
+                    let _ = sender.try_send(message);
+                }
+                continue;
+            }
+            let event = match payload {
+                v2::Payload::InitializeAck(ack) => ClientEvent::Acknowledged(ack),
+                payload => ClientEvent::Message(payload),
+            };
+            if events.send(event).is_err() {
+                break;
+            }
+        }
+    };
+
+    tokio::select! {
+        _ = writer => {}
+        _ = reader => {}
+    }
+}
@@ -0,0 +1,336 @@
+use std::{collections::HashMap, io, net::SocketAddr, sync::Arc};
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::{
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{RwLock, mpsc},
+    task::{JoinHandle, JoinSet},
+};
+use tokio_tungstenite::tungstenite::Message;
+
+use super::{
+    DEFAULT_QUEUE_SIZE, ProtocolType, auto_reply, decode_message, encode_payload, is_ignored,
+    malformed_reply, sniff_protocol, unsupported_reply,
+};
+use crate::{clock, v2};
+
+/// 服务端配置
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// 握手回应中的服务端名称
+    pub name: String,
+    /// 握手回应中的服务端版本
+    pub version: String,
+    /// 服务端支持的可选能力
+    pub capabilities: Vec<v2::Capability>,
+    /// 每个连接的发送队列长度，队列已满时新的消息会被丢弃
+    pub queue_size: usize,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            name: env!("CARGO_PKG_NAME").into(),
+            version: env!("CARGO_PKG_VERSION").into(),
+            capabilities: v2::Capability::ALL.to_vec(),
+            queue_size: DEFAULT_QUEUE_SIZE,
+        }
+    }
+}
+
+/// 服务端事件
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerEvent {
+    /// 客户端已连接并识别出协议，HybridV2 协议的客户端会附带握手信息
+    Connected {
+        addr: SocketAddr,
+        protocol: ProtocolType,
+        client: Option<v2::Initialize>,
+    },
+    /// 收到了客户端的消息，BinaryV1 协议的消息已转换为 HybridV2 协议
+    Message {
+        addr: SocketAddr,
+        payload: v2::Payload,
+    },
+    /// 客户端已断开
+    Disconnected { addr: SocketAddr },
+}
+
+struct Connection {
+    protocol: ProtocolType,
+    /// 握手后协商的能力，BinaryV1 协议的连接为空
+    capabilities: Vec<v2::Capability>,
+    sender: mpsc::Sender<Message>,
+}
+
+type Connections = Arc<RwLock<HashMap<SocketAddr, Connection>>>;
+
+/// 同时支持 BinaryV1 和 HybridV2 协议的 WebSocket 服务端
+///
+/// 服务端被丢弃时会断开所有连接。
+pub struct Server {
+    local_addr: SocketAddr,
+    connections: Connections,
+    events: mpsc::UnboundedReceiver<ServerEvent>,
+    task: JoinHandle<()>,
+}
+
+impl Server {
+    /// 在指定地址上开启服务端
+    pub async fn bind(addr: impl ToSocketAddrs, config: ServerConfig) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let connections = Connections::default();
+        let (event_sender, events) = mpsc::unbounded_channel();
+        let task = tokio::spawn(accept_loop(
+            listener,
+            Arc::new(config),
+            connections.clone(),
+            event_sender,
+        ));
+        Ok(Self {
+            local_addr,
+            connections,
+            events,
+            task,
+        })
+    }
+
+    /// 服务端实际监听的地址
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// 等待下一个事件，服务端关闭后返回 `None`
+    pub async fn next_event(&mut self) -> Option<ServerEvent> {
+        self.events.recv().await
+    }
+
+    /// 事件的接收端，可用于和其它异步任务一同等待
+    pub fn events(&mut self) -> &mut mpsc::UnboundedReceiver<ServerEvent> {
+        &mut self.events
+    }
+
+    /// 当前已识别出协议的连接
+    pub async fn connections(&self) -> Vec<SocketAddr> {
+        self.connections.read().await.keys().copied().collect()
+    }
+
+    /// 向所有连接广播消息
+    ///
+    /// 消息会按照各个连接的协议编码，无法转换为 BinaryV1 协议的消息和需要未协商能力的消息会被跳过。
+    pub async fn broadcast(&self, payload: &v2::Payload) {
+        let v1_message = encode_payload(payload, ProtocolType::BinaryV1).ok();
+        let v2_message = encode_payload(payload, ProtocolType::HybridV2).ok();
+        for (addr, conn) in self.connections.read().await.iter() {
+            let message = match conn.protocol {
+                ProtocolType::BinaryV1 => v1_message.as_ref(),
+                ProtocolType::HybridV2 => match payload.required_capability() {
+                    Some(cap) if !conn.capabilities.contains(&cap) => None,
+                    _ => v2_message.as_ref(),
+                },
+            };
+            if let Some(message) = message {
+                enqueue(*addr, &conn.sender, message.clone());
+            }
+        }
+    }
+
+    /// 向指定连接发送消息，连接不存在或消息无法以该连接的协议编码时返回错误
+    ///
+    /// 发送队列已满时会等待队列空出位置。
+    pub async fn send_to(&self, addr: SocketAddr, payload: &v2::Payload) -> anyhow::Result<()> {
+        // 等待发送时不能持有连接表的锁，否则连接断开时无法将其从连接表中移除
+        let (protocol, sender) = {
+            let connections = self.connections.read().await;
+            let conn = connections
+                .get(&addr)
+                .ok_or_else(|| anyhow::anyhow!("连接 {addr} 不存在"))?;
+            (conn.protocol, conn.sender.clone())
+        };
+        let message = encode_payload(payload, protocol)?;
+        sender.send(message).await?;
+        Ok(())
+    }
+
+    /// 断开指定连接
+    pub async fn disconnect(&self, addr: SocketAddr) {
+        if let Some(conn) = self.connections.read().await.get(&addr) {
+            let _ = conn.sender.try_send(Message::Close(None));
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn enqueue(addr: SocketAddr, sender: &mpsc::Sender<Message>, message: Message) {
+    if let Err(mpsc::error::TrySendError::Full(_)) = sender.try_send(message) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!("WebSocket 客户端 {addr} 的发送队列已满，丢弃消息");
+        #[cfg(not(feature = "tracing"))]
+        let _ = addr;
+    }
+}
+
+async fn accept_loop(
+    listener: TcpListener,
+    config: Arc<ServerConfig>,
+    connections: Connections,
+    events: mpsc::UnboundedSender<ServerEvent>,
+) {
+    // 监听任务结束时，所有连接任务会随 JoinSet 一同被中止
+    let mut tasks = JoinSet::new();
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, addr)) => {
+                    tasks.spawn(handle_connection(
+                        stream,
+                        addr,
+                        config.clone(),
+                        connections.clone(),
+                        events.clone(),
+                    ));
+                }
+                Err(_err) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!("接受 WebSocket 连接失败: {_err:?}");
+                }
+            },
+            Some(_) = tasks.join_next() => {}
+        }
+    }
+}
+
+async fn handle_connection(
+    stream: TcpStream,
+    addr: SocketAddr,
+    config: Arc<ServerConfig>,
+    connections: Connections,
+    events: mpsc::UnboundedSender<ServerEvent>,
+) {
+    let Ok(ws) = tokio_tungstenite::accept_async(stream).await else {
+        return;
+    };
+    let (mut sink, mut stream) = ws.split();
+
+    // 根据第一条携带数据的消息识别协议
+    let (first_message, protocol, init) = loop {
+        match stream.next().await {
+            Some(Ok(message)) => {
+                if message.is_ping() || message.is_pong() {
+                    continue;
+                }
+                match sniff_protocol(&message) {
+                    Some((protocol, init)) => break (message, protocol, init),
+                    None => {
+                        // 告知客户端需要先发送握手消息，继续等待而不是直接断开
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!("WebSocket 客户端 {addr} 发送了无法识别的第一条消息");
+                        let error = v2::Payload::Error(v2::ErrorInfo::new(
+                            v2::ErrorCode::MalformedMessage,
+                            "第一条消息必须是 initialize",
+                        ));
+                        if let Ok(reply) = encode_payload(&error, ProtocolType::HybridV2)
+                            && sink.send(reply).await.is_err()
+                        {
+                            return;
+                        }
+                    }
+                }
+            }
+            _ => return,
+        }
+    };
+
+    let (sender, mut receiver) = mpsc::channel(config.queue_size.max(1));
+    let mut capabilities = Vec::new();
+    if let Some(init) = &init {
+        let ack = init.accept(&config.name, &config.version, &config.capabilities);
+        if !init.is_legacy()
+            && let Ok(message) = encode_payload(&v2::Payload::InitializeAck(ack.clone()), protocol)
+        {
+            let _ = sender.send(message).await;
+        }
+        capabilities = ack.capabilities;
+    }
+
+    connections.write().await.insert(
+        addr,
+        Connection {
+            protocol,
+            capabilities,
+            sender: sender.clone(),
+        },
+    );
+    let _ = events.send(ServerEvent::Connected {
+        addr,
+        protocol,
+        client: init,
+    });
+
+    let writer = async {
+        while let Some(message) = receiver.recv().await {
+            let is_close = message.is_close();
+            if sink.send(message).await.is_err() || is_close {
+                break;
+            }
+        }
+    };
+
+    let reader = async {
+        // BinaryV1 协议的第一条消息本身也是需要处理的数据
+        let mut pending = (protocol == ProtocolType::BinaryV1).then_some(first_message);
+        loop {
+            let message = match pending.take() {
+                Some(message) => message,
+                None => match stream.next().await {
+                    Some(Ok(message)) => message,
+                    _ => break,
+                },
+            };
+            let receive_time = clock::now_millis();
+            let payload = match decode_message(&message, protocol) {
+                Ok(Some(payload)) => payload,
+                Ok(None) => continue,
+                Err(err) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!("解析来自 WebSocket 客户端 {addr} 的消息失败: {err:?}");
+                    if let Ok(message) = encode_payload(&malformed_reply(&err), protocol) {
+                        enqueue(addr, &sender, message);
+                    }
+                    continue;
+                }
+            };
+            if let Some(reply) = unsupported_reply(&payload)
+                && let Ok(message) = encode_payload(&reply, protocol)
+            {
+                enqueue(addr, &sender, message);
+            }
+            if is_ignored(&payload) {
+                continue;
+            }
+            if let Some(reply) = auto_reply(&payload, receive_time) {
+                if let Ok(message) = encode_payload(&reply, protocol) {
+                    enqueue(addr, &sender, message);
+                }
+                continue;
+            }
+            if events.send(ServerEvent::Message { addr, payload }).is_err() {
+                break;
+            }
+        }
+    };
+
+    tokio::select! {
+        _ = writer => {}
+        _ = reader => {}
+    }
+
+    connections.write().await.remove(&addr);
+    let _ = events.send(ServerEvent::Disconnected { addr });
+}
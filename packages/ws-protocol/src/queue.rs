@@ -0,0 +1,166 @@
+//! 播放队列状态
+//!
+//! 接收方可以使用 [`Queue`] 依次应用收到的队列更新，以维护和发送方一致的播放队列，
+//! 用于展示“下一首”或预先加载歌词等。
+//!
+//! 队列中的位置与播放核心的播放列表下标一致，因此本地播放器可以直接将
+//! [`StateUpdate::SetQueue`] 和 [`Command::JumpToSong`](crate::v2::Command::JumpToSong)
+//! 与 `SetPlaylist`、`JumpToSong` 相互转换。
+
+use crate::v2::{MusicInfo, StateUpdate};
+
+/// 播放队列
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Queue {
+    /// 队列中的歌曲
+    pub entries: Vec<MusicInfo>,
+    /// 当前播放的歌曲在队列中的位置
+    pub current_index: Option<usize>,
+}
+
+impl Queue {
+    pub fn new(entries: Vec<MusicInfo>, current_index: Option<usize>) -> Self {
+        Self {
+            entries,
+            current_index,
+        }
+    }
+
+    /// 当前播放的歌曲
+    pub fn current(&self) -> Option<&MusicInfo> {
+        self.entries.get(self.current_index?)
+    }
+
+    /// 下一首歌曲
+    pub fn up_next(&self) -> Option<&MusicInfo> {
+        self.entries.get(self.current_index? + 1)
+    }
+
+    /// 生成完整队列的更新，可以在新的连接建立时发送
+    pub fn snapshot(&self) -> StateUpdate {
+        StateUpdate::SetQueue {
+            entries: self.entries.clone(),
+            current_index: self.current_index,
+        }
+    }
+
+    /// 应用一条队列更新，返回该更新是否为队列更新
+    ///
+    /// 超出范围的位置会被限制在队列范围内，当前播放的位置会随插入、移除和移动一同调整，
+    /// 当前播放的歌曲被移除时则会指向原位置的下一首歌曲。
+    pub fn apply(&mut self, update: &StateUpdate) -> bool {
+        match update {
+            StateUpdate::SetQueue {
+                entries,
+                current_index,
+            } => {
+                self.entries = entries.clone();
+                self.current_index = *current_index;
+            }
+            StateUpdate::QueueInsert { index, entries } => {
+                let index = (*index).min(self.entries.len());
+                self.entries.splice(index..index, entries.iter().cloned());
+                if let Some(current) = self.current_index.as_mut()
+                    && *current >= index
+                {
+                    *current += entries.len();
+                }
+            }
+            StateUpdate::QueueRemove { index } => {
+                if *index < self.entries.len() {
+                    self.entries.remove(*index);
+                    if let Some(current) = self.current_index {
+                        if current > *index {
+                            self.current_index = Some(current - 1);
+                        } else if current == *index && current >= self.entries.len() {
+                            self.current_index = None;
+                        }
+                    }
+                }
+            }
+            StateUpdate::QueueMove { from, to } => {
+                if *from < self.entries.len() {
+                    let to = (*to).min(self.entries.len() - 1);
+                    let entry = self.entries.remove(*from);
+                    self.entries.insert(to, entry);
+                    if let Some(current) = self.current_index.as_mut() {
+                        if *current == *from {
+                            *current = to;
+                        } else if *from < *current && *current <= to {
+                            *current -= 1;
+                        } else if to <= *current && *current < *from {
+                            *current += 1;
+                        }
+                    }
+                }
+            }
+            StateUpdate::QueueIndex { current_index } => {
+                self.current_index = *current_index;
+            }
+            _ => return false,
+        }
+        if self.current_index.is_some_and(|x| x >= self.entries.len()) {
+            self.current_index = None;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn music(id: &str) -> MusicInfo {
+        MusicInfo {
+            music_id: id.into(),
+            music_name: id.into(),
+            ..Default::default()
+        }
+    }
+
+    fn ids(queue: &Queue) -> Vec<&str> {
+        queue.entries.iter().map(|x| x.music_id.as_str()).collect()
+    }
+
+    #[test]
+    fn queue_test() {
+        let mut queue = Queue::default();
+        assert!(queue.apply(&StateUpdate::SetQueue {
+            entries: vec![music("a"), music("b"), music("c")],
+            current_index: Some(1),
+        }));
+        assert_eq!(queue.up_next().unwrap().music_id, "c");
+
+        queue.apply(&StateUpdate::QueueInsert {
+            index: 0,
+            entries: vec![music("x")],
+        });
+        assert_eq!(ids(&queue), ["x", "a", "b", "c"]);
+        assert_eq!(queue.current().unwrap().music_id, "b");
+
+        queue.apply(&StateUpdate::QueueMove { from: 2, to: 0 });
+        assert_eq!(ids(&queue), ["b", "x", "a", "c"]);
+        assert_eq!(queue.current_index, Some(0));
+        queue.apply(&StateUpdate::QueueMove { from: 3, to: 0 });
+        assert_eq!(ids(&queue), ["c", "b", "x", "a"]);
+        assert_eq!(queue.current_index, Some(1));
+
+        queue.apply(&StateUpdate::QueueRemove { index: 0 });
+        assert_eq!(queue.current().unwrap().music_id, "b");
+        queue.apply(&StateUpdate::QueueRemove { index: 0 });
+        assert_eq!(queue.current().unwrap().music_id, "x");
+
+        queue.apply(&StateUpdate::QueueIndex {
+            current_index: Some(1),
+        });
+        queue.apply(&StateUpdate::QueueRemove { index: 1 });
+        assert_eq!(queue.current_index, None);
+        assert!(!queue.apply(&StateUpdate::Paused { timestamp: None }));
+
+        let json = serde_json::to_string(&queue.snapshot()).unwrap();
+        assert_eq!(
+            json,
+            r#"{"update":"setQueue","entries":[{"musicId":"x","musicName":"x","albumId":"","albumName":"","artists":[],"duration":0}],"currentIndex":null}"#
+        );
+    }
+}
@@ -0,0 +1,121 @@
+//! 会话的录制与回放
+//!
+//! 录制文件为 JSON Lines 格式，每行是一条 [`RecordedFrame`]，记录了收到消息时
+//! 相对录制开始的时间，以及消息的原始内容。文本消息原样保存，二进制消息使用 Base64 编码：
+//!
+//! ```text
+//! {"time":0,"text":"{\"type\":\"initialize\"}"}
+//! {"time":16,"binary":"BQDoAwAAAAAAAA=="}
+//! ```
+//!
+//! 由于保存的是原始消息，BinaryV1 和 HybridV2 协议的会话都可以录制，
+//! 回放时按顺序重新发送即可复现原本的会话。
+
+use std::io::{BufRead, Write};
+
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+use crate::clock;
+
+/// 录制的一条消息
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+pub struct RecordedFrame {
+    /// 收到消息时相对录制开始的时间，单位为毫秒
+    pub time: u64,
+    #[serde(flatten)]
+    pub data: FrameData,
+}
+
+/// 消息的原始内容
+#[serde_as]
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub enum FrameData {
+    Text(String),
+    Binary(#[serde_as(as = "serde_with::base64::Base64")] Vec<u8>),
+}
+
+/// 会话录制器，将收到的消息逐行写入录制文件
+pub struct Recorder<W: Write> {
+    writer: W,
+    start: u64,
+}
+
+impl<W: Write> Recorder<W> {
+    /// 创建录制器，消息的时间相对于创建录制器的时间
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            start: clock::now_millis(),
+        }
+    }
+
+    /// 记录一条消息，每条消息写入后都会立即刷新，录制中断时已写入的内容仍然可用
+    pub fn record(&mut self, data: FrameData) -> anyhow::Result<()> {
+        let frame = RecordedFrame {
+            time: clock::now_millis().saturating_sub(self.start),
+            data,
+        };
+        serde_json::to_writer(&mut self.writer, &frame)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// 读取录制文件中的所有消息，空行会被跳过
+pub fn read_recording(reader: impl BufRead) -> impl Iterator<Item = anyhow::Result<RecordedFrame>> {
+    reader.lines().filter_map(|line| match line {
+        Ok(line) if line.trim().is_empty() => None,
+        Ok(line) => Some(serde_json::from_str(&line).map_err(Into::into)),
+        Err(err) => Some(Err(err.into())),
+    })
+}
+
+/// 回放时距离上一条消息需要等待的时间，单位为毫秒
+///
+/// `speed` 为回放速度的倍数，例如 `2.0` 表示以两倍速回放，不大于 0 时不等待。
+pub fn replay_delay(previous: u64, current: u64, speed: f64) -> u64 {
+    if speed <= 0.0 {
+        return 0;
+    }
+    (current.saturating_sub(previous) as f64 / speed) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_test() {
+        let mut recorder = Recorder::new(Vec::new());
+        recorder
+            .record(FrameData::Text(r#"{"type":"ping"}"#.into()))
+            .unwrap();
+        recorder.record(FrameData::Binary(vec![0, 0])).unwrap();
+        let data = recorder.into_inner();
+
+        let frames = read_recording(&data[..])
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].data, FrameData::Text(r#"{"type":"ping"}"#.into()));
+        assert_eq!(frames[1].data, FrameData::Binary(vec![0, 0]));
+
+        let line = r#"{"time":16,"binary":"AAE="}"#;
+        let frame: RecordedFrame = serde_json::from_str(line).unwrap();
+        assert_eq!(frame.time, 16);
+        assert_eq!(frame.data, FrameData::Binary(vec![0, 1]));
+        assert_eq!(serde_json::to_string(&frame).unwrap(), line);
+
+        assert_eq!(replay_delay(100, 300, 1.0), 200);
+        assert_eq!(replay_delay(100, 300, 4.0), 50);
+        assert_eq!(replay_delay(300, 100, 1.0), 0);
+        assert_eq!(replay_delay(100, 300, 0.0), 0);
+    }
+}
@@ -4,13 +4,13 @@ use anyhow::anyhow;
 impl From<v1::Body> for v2::Payload {
     fn from(body: v1::Body) -> Self {
         match body {
-            v1::Body::Pause => Self::Command(v2::Command::Pause),
-            v1::Body::Resume => Self::Command(v2::Command::Resume),
-            v1::Body::ForwardSong => Self::Command(v2::Command::ForwardSong),
-            v1::Body::BackwardSong => Self::Command(v2::Command::BackwardSong),
-            v1::Body::SetVolume { volume } => Self::Command(v2::Command::SetVolume { volume }),
+            v1::Body::Pause => v2::Command::Pause.into(),
+            v1::Body::Resume => v2::Command::Resume.into(),
+            v1::Body::ForwardSong => v2::Command::ForwardSong.into(),
+            v1::Body::BackwardSong => v2::Command::BackwardSong.into(),
+            v1::Body::SetVolume { volume } => v2::Command::SetVolume { volume }.into(),
             v1::Body::SeekPlayProgress { progress } => {
-                Self::Command(v2::Command::SeekPlayProgress { progress })
+                v2::Command::SeekPlayProgress { progress }.into()
             }
             v1::Body::SetMusicInfo {
                 music_id,
@@ -26,6 +26,7 @@ impl From<v1::Body> for v2::Payload {
                 album_name: album_name.to_string(),
                 artists,
                 duration,
+                ..Default::default()
             })),
             v1::Body::SetMusicAlbumCoverImageURI { img_url } => {
                 Self::State(v2::StateUpdate::SetCover(v2::AlbumCover::Uri {
@@ -43,20 +44,28 @@ impl From<v1::Body> for v2::Payload {
             v1::Body::SetLyric { data } => {
                 Self::State(v2::StateUpdate::SetLyric(v2::LyricContent::Structured {
                     lines: data,
+                    metadata: Vec::new(),
+                    offset: 0,
                 }))
             }
             v1::Body::SetLyricFromTTML { data } => {
                 Self::State(v2::StateUpdate::SetLyric(v2::LyricContent::Ttml {
                     data: data.to_string(),
+                    offset: 0,
                 }))
             }
-            v1::Body::OnPlayProgress { progress } => {
-                Self::State(v2::StateUpdate::Progress { progress })
-            }
+            v1::Body::OnPlayProgress { progress } => Self::State(v2::StateUpdate::Progress {
+                progress,
+                timestamp: None,
+            }),
             v1::Body::OnVolumeChanged { volume } => Self::State(v2::StateUpdate::Volume { volume }),
-            v1::Body::OnPaused => Self::State(v2::StateUpdate::Paused),
-            v1::Body::OnResumed => Self::State(v2::StateUpdate::Resumed),
-            v1::Body::OnAudioData { data } => Self::State(v2::StateUpdate::AudioData { data }),
+            v1::Body::OnPaused => Self::State(v2::StateUpdate::Paused { timestamp: None }),
+            v1::Body::OnResumed => Self::State(v2::StateUpdate::Resumed { timestamp: None }),
+            v1::Body::OnAudioData { data } => Self::State(v2::StateUpdate::AudioData {
+                data,
+                sequence: None,
+                timestamp: None,
+            }),
             v1::Body::Ping => Self::Ping,
             v1::Body::Pong => Self::Pong,
         }
@@ -69,7 +78,7 @@ impl TryFrom<v2::Payload> for v1::Body {
 
     fn try_from(payload: v2::Payload) -> Result<Self, Self::Error> {
         Ok(match payload {
-            v2::Payload::Command(cmd) => match cmd {
+            v2::Payload::Command(cmd) => match cmd.command {
                 v2::Command::Pause => Self::Pause,
                 v2::Command::Resume => Self::Resume,
                 v2::Command::ForwardSong => Self::ForwardSong,
@@ -79,6 +88,12 @@ impl TryFrom<v2::Payload> for v1::Body {
                 v2::Command::SetRepeatMode { .. } | v2::Command::SetShuffleMode { .. } => {
                     return Err(anyhow!("v1 协议不支持设置循环和随机播放模式"));
                 }
+                v2::Command::JumpToSong { .. }
+                | v2::Command::Enqueue { .. }
+                | v2::Command::RemoveFromQueue { .. } => {
+                    return Err(anyhow!("v1 协议不支持播放队列"));
+                }
+                v2::Command::Unknown => return Err(anyhow!("无法识别的指令无法转换为 v1 协议")),
             },
             v2::Payload::State(state) => match state {
                 v2::StateUpdate::SetMusic(info) => Self::SetMusicInfo {
@@ -97,30 +112,103 @@ impl TryFrom<v2::Payload> for v1::Body {
                         Self::SetMusicAlbumCoverImageData { data: image.data }
                     }
                 },
-                v2::StateUpdate::SetLyric(lyric) => match lyric {
-                    v2::LyricContent::Structured { lines } => Self::SetLyric { data: lines },
-                    v2::LyricContent::Ttml { data } => Self::SetLyricFromTTML { data: data.into() },
-                },
-                v2::StateUpdate::Progress { progress } => Self::OnPlayProgress { progress },
+                v2::StateUpdate::SetLyric(lyric) => lyric_to_v1(lyric)?,
+                v2::StateUpdate::Progress { progress, .. } => Self::OnPlayProgress { progress },
                 v2::StateUpdate::Volume { volume } => Self::OnVolumeChanged { volume },
-                v2::StateUpdate::Paused => Self::OnPaused,
-                v2::StateUpdate::Resumed => Self::OnResumed,
-                v2::StateUpdate::AudioData { data } => Self::OnAudioData { data },
+                v2::StateUpdate::Paused { .. } => Self::OnPaused,
+                v2::StateUpdate::Resumed { .. } => Self::OnResumed,
+                v2::StateUpdate::AudioData { data, .. } => Self::OnAudioData { data },
+                v2::StateUpdate::AudioFormat(_) => {
+                    return Err(anyhow!("v1 协议不支持描述音频格式"));
+                }
+                v2::StateUpdate::Spectrum(_) => {
+                    return Err(anyhow!("v1 协议不支持频谱数据"));
+                }
                 v2::StateUpdate::ModeChanged { .. } => {
                     return Err(anyhow!("v1 协议不支持设置循环和随机播放模式"));
                 }
+                v2::StateUpdate::SetQueue { .. }
+                | v2::StateUpdate::QueueInsert { .. }
+                | v2::StateUpdate::QueueRemove { .. }
+                | v2::StateUpdate::QueueMove { .. }
+                | v2::StateUpdate::QueueIndex { .. } => {
+                    return Err(anyhow!("v1 协议不支持播放队列"));
+                }
+                v2::StateUpdate::Unknown => {
+                    return Err(anyhow!("无法识别的更新无法转换为 v1 协议"));
+                }
             },
             v2::Payload::Ping => Self::Ping,
             v2::Payload::Pong => Self::Pong,
-            v2::Payload::Initialize => return Err(anyhow!("Initialize 消息无法转换为 v1 协议")),
+            v2::Payload::Initialize(_) | v2::Payload::InitializeAck(_) => {
+                return Err(anyhow!("握手消息无法转换为 v1 协议"));
+            }
+            v2::Payload::TimeSyncRequest(_) | v2::Payload::TimeSyncResponse(_) => {
+                return Err(anyhow!("v1 协议不支持时间同步"));
+            }
+            v2::Payload::CommandResult(_) | v2::Payload::Error(_) => {
+                return Err(anyhow!("v1 协议不支持回应指令和报告错误"));
+            }
+            v2::Payload::Unknown => return Err(anyhow!("无法识别的消息无法转换为 v1 协议")),
         })
     }
 }
 
+/// v1 协议不支持时间偏移，结构化歌词的偏移会直接应用到各个时间上
+///
+/// 启用 `amll-lyric` 功能后，其他格式的歌词会先解析为结构化歌词再转换。
+fn lyric_to_v1(lyric: v2::LyricContent) -> anyhow::Result<v1::Body> {
+    Ok(match lyric {
+        v2::LyricContent::Ttml { data, offset: 0 } => {
+            v1::Body::SetLyricFromTTML { data: data.into() }
+        }
+        v2::LyricContent::Structured {
+            mut lines, offset, ..
+        } => {
+            for line in &mut lines {
+                line.start_time = line.start_time.saturating_add_signed(offset);
+                line.end_time = line.end_time.saturating_add_signed(offset);
+                for word in &mut line.words {
+                    word.start_time = word.start_time.saturating_add_signed(offset);
+                    word.end_time = word.end_time.saturating_add_signed(offset);
+                    for ruby in &mut word.ruby {
+                        ruby.start_time = ruby.start_time.saturating_add_signed(offset);
+                        ruby.end_time = ruby.end_time.saturating_add_signed(offset);
+                    }
+                }
+            }
+            v1::Body::SetLyric { data: lines }
+        }
+        #[cfg(feature = "amll-lyric")]
+        lyric => return lyric_to_v1(lyric.into_structured()?),
+        #[cfg(not(feature = "amll-lyric"))]
+        v2::LyricContent::Ttml { .. } => {
+            return Err(anyhow!("v1 协议不支持带有时间偏移的 TTML 歌词"));
+        }
+        #[cfg(not(feature = "amll-lyric"))]
+        v2::LyricContent::Raw { .. } => {
+            return Err(anyhow!("v1 协议不支持未解析的歌词"));
+        }
+    })
+}
+
 impl From<v2::BinaryV2> for v2::Payload {
     fn from(binary: v2::BinaryV2) -> Self {
         match binary {
-            v2::BinaryV2::OnAudioData { data } => Self::State(v2::StateUpdate::AudioData { data }),
+            v2::BinaryV2::OnAudioData { data } => Self::State(v2::StateUpdate::AudioData {
+                data,
+                sequence: None,
+                timestamp: None,
+            }),
+            v2::BinaryV2::OnAudioChunk {
+                sequence,
+                timestamp,
+                data,
+            } => Self::State(v2::StateUpdate::AudioData {
+                data,
+                sequence: Some(sequence),
+                timestamp: Some(timestamp),
+            }),
             v2::BinaryV2::SetCoverData { data } => {
                 Self::State(v2::StateUpdate::SetCover(v2::AlbumCover::Data {
                     image: v2::ImageData {
@@ -129,6 +217,7 @@ impl From<v2::BinaryV2> for v2::Payload {
                     },
                 }))
             }
+            v2::BinaryV2::OnSpectrum(spectrum) => Self::State(v2::StateUpdate::Spectrum(spectrum)),
         }
     }
 }
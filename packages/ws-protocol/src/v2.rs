@@ -11,21 +11,375 @@ use wasm_bindgen::prelude::*;
 
 /// 顶层消息
 #[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
-#[serde(rename_all = "camelCase")]
+#[serde(transparent)]
 pub struct MessageV2 {
-    #[serde(flatten)]
     pub payload: Payload,
 }
 
+/// 当前实现的 HybridV2 协议修订版本
+///
+/// 只发送不带数据的 Initialize 消息的旧版客户端视为版本 0
+pub const PROTOCOL_VERSION: u32 = 1;
+
 /// 消息的主体，用于区分消息类型
-#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
-#[serde(rename_all = "camelCase", tag = "type", content = "value")]
+///
+/// 无法识别的消息类型会被解析为 [`Payload::Unknown`]，以便兼容更新版本的对端
+#[derive(Serialize, PartialEq, Debug, Clone)]
+#[serde(
+    remote = "Self",
+    rename_all = "camelCase",
+    tag = "type",
+    content = "value"
+)]
 pub enum Payload {
-    Initialize,
+    /// 由客户端在连接建立后发送的第一条消息
+    Initialize(Initialize),
+    /// 服务端对 [`Payload::Initialize`] 的回应，包含双方协商后的能力
+    InitializeAck(InitializeAck),
     Ping,
     Pong,
-    Command(Command),
+    Command(CommandRequest),
     State(StateUpdate),
+    /// 时间同步请求，接收方应当尽快回应 [`Payload::TimeSyncResponse`]
+    TimeSyncRequest(TimeSyncRequest),
+    /// 时间同步回应
+    TimeSyncResponse(TimeSyncResponse),
+    /// 对带有请求 ID 的指令的回应
+    CommandResult(CommandResult),
+    /// 协议层面的错误，例如无法解析或无法识别对端发送的消息
+    Error(ErrorInfo),
+    /// 无法识别的消息，通常由更新版本的对端发送，应当忽略
+    Unknown,
+}
+
+impl Serialize for Payload {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Payload::serialize(self, serializer)
+    }
+}
+
+/// 消息的 `type` 字段，解析 `value` 字段时按类型直接解析为对应的消息
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+enum PayloadKind {
+    Initialize,
+    InitializeAck,
+    Ping,
+    Pong,
+    Command,
+    State,
+    TimeSyncRequest,
+    TimeSyncResponse,
+    CommandResult,
+    Error,
+    #[serde(other)]
+    Unknown,
+}
+
+impl PayloadKind {
+    /// 消息不带 `value` 字段时的内容
+    fn without_value<E: serde::de::Error>(self) -> Result<Payload, E> {
+        match self {
+            // 旧版客户端发送的 Initialize 消息不携带任何数据
+            Self::Initialize => Ok(Payload::Initialize(Initialize::default())),
+            Self::Ping => Ok(Payload::Ping),
+            Self::Pong => Ok(Payload::Pong),
+            Self::Unknown => Ok(Payload::Unknown),
+            _ => Err(E::missing_field("value")),
+        }
+    }
+}
+
+impl<'de> serde::de::DeserializeSeed<'de> for PayloadKind {
+    type Value = Payload;
+
+    fn deserialize<D: serde::Deserializer<'de>>(self, value: D) -> Result<Payload, D::Error> {
+        use serde::de::IgnoredAny;
+        Ok(match self {
+            Self::Initialize => {
+                Payload::Initialize(Option::deserialize(value)?.unwrap_or_default())
+            }
+            Self::InitializeAck => Payload::InitializeAck(Deserialize::deserialize(value)?),
+            Self::Ping => {
+                IgnoredAny::deserialize(value)?;
+                Payload::Ping
+            }
+            Self::Pong => {
+                IgnoredAny::deserialize(value)?;
+                Payload::Pong
+            }
+            Self::Command => Payload::Command(Deserialize::deserialize(value)?),
+            Self::State => Payload::State(Deserialize::deserialize(value)?),
+            Self::TimeSyncRequest => Payload::TimeSyncRequest(Deserialize::deserialize(value)?),
+            Self::TimeSyncResponse => Payload::TimeSyncResponse(Deserialize::deserialize(value)?),
+            Self::CommandResult => Payload::CommandResult(Deserialize::deserialize(value)?),
+            Self::Error => Payload::Error(Deserialize::deserialize(value)?),
+            Self::Unknown => {
+                IgnoredAny::deserialize(value)?;
+                Payload::Unknown
+            }
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Payload {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::{DeserializeSeed, Error, IgnoredAny, MapAccess};
+
+        #[derive(Deserialize)]
+        #[serde(field_identifier, rename_all = "camelCase")]
+        enum Field {
+            Type,
+            Value,
+            #[serde(other)]
+            Other,
+        }
+
+        struct PayloadVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for PayloadVisitor {
+            type Value = Payload;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a message with `type` and `value` fields")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Payload, A::Error> {
+                let mut kind: Option<PayloadKind> = None;
+                let mut payload = None;
+                // `value` 出现在 `type` 之前时只能先缓存，知道消息类型后再解析
+                let mut buffered: Option<serde_json::Value> = None;
+                while let Some(field) = map.next_key()? {
+                    match field {
+                        Field::Type if kind.is_some() => {
+                            return Err(A::Error::duplicate_field("type"));
+                        }
+                        Field::Type => kind = Some(map.next_value()?),
+                        Field::Value if payload.is_some() || buffered.is_some() => {
+                            return Err(A::Error::duplicate_field("value"));
+                        }
+                        Field::Value => match kind {
+                            Some(kind) => payload = Some(map.next_value_seed(kind)?),
+                            None => buffered = Some(map.next_value()?),
+                        },
+                        Field::Other => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+                let kind = kind.ok_or_else(|| A::Error::missing_field("type"))?;
+                match (payload, buffered) {
+                    (Some(payload), _) => Ok(payload),
+                    (None, Some(value)) => kind.deserialize(value).map_err(A::Error::custom),
+                    (None, None) => kind.without_value(),
+                }
+            }
+        }
+
+        deserializer.deserialize_map(PayloadVisitor)
+    }
+}
+
+impl Payload {
+    /// 为没有时间戳的进度、暂停、恢复和音频数据更新填充发送时间
+    pub fn stamp(&mut self, now: u64) {
+        if let Self::State(
+            StateUpdate::Progress { timestamp, .. }
+            | StateUpdate::Paused { timestamp }
+            | StateUpdate::Resumed { timestamp }
+            | StateUpdate::AudioData { timestamp, .. },
+        ) = self
+        {
+            timestamp.get_or_insert(now);
+        }
+    }
+
+    /// 发送该消息所需要对端支持的可选能力，返回 `None` 表示所有对端都支持
+    pub fn required_capability(&self) -> Option<Capability> {
+        match self {
+            Self::State(StateUpdate::AudioData { .. } | StateUpdate::AudioFormat(_)) => {
+                Some(Capability::AudioData)
+            }
+            Self::State(StateUpdate::SetCover(AlbumCover::Data { .. })) => {
+                Some(Capability::CoverData)
+            }
+            Self::TimeSyncRequest(_) | Self::TimeSyncResponse(_) => Some(Capability::ClockSync),
+            Self::State(StateUpdate::Spectrum(_)) => Some(Capability::Spectrum),
+            Self::State(
+                StateUpdate::SetQueue { .. }
+                | StateUpdate::QueueInsert { .. }
+                | StateUpdate::QueueRemove { .. }
+                | StateUpdate::QueueMove { .. }
+                | StateUpdate::QueueIndex { .. },
+            )
+            | Self::Command(CommandRequest {
+                command:
+                    Command::JumpToSong { .. }
+                    | Command::Enqueue { .. }
+                    | Command::RemoveFromQueue { .. },
+                ..
+            }) => Some(Capability::Queue),
+            Self::State(StateUpdate::ModeChanged { .. })
+            | Self::Command(CommandRequest {
+                command: Command::SetRepeatMode { .. } | Command::SetShuffleMode { .. },
+                ..
+            }) => Some(Capability::PlaybackMode),
+            _ => None,
+        }
+    }
+}
+
+/// 可选的协议能力，在握手时协商
+#[derive(Deserialize, Serialize, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum Capability {
+    /// 传输音频数据，即 [`StateUpdate::AudioData`]、[`StateUpdate::AudioFormat`]、
+    /// [`BinaryV2::OnAudioData`] 和 [`BinaryV2::OnAudioChunk`]
+    AudioData,
+    /// 以原始数据传输专辑封面，即 [`AlbumCover::Data`] 和 [`BinaryV2::SetCoverData`]
+    CoverData,
+    /// 循环和随机播放模式，即 [`StateUpdate::ModeChanged`] 及相关的指令
+    PlaybackMode,
+    /// 时间同步，即 [`Payload::TimeSyncRequest`] 和 [`Payload::TimeSyncResponse`]
+    ClockSync,
+    /// 播放队列，即 [`StateUpdate::SetQueue`] 等队列更新及相关的指令
+    Queue,
+    /// 频谱数据，即 [`StateUpdate::Spectrum`] 和 [`BinaryV2::OnSpectrum`]
+    Spectrum,
+    /// 无法识别的能力，协商时会被忽略
+    #[serde(other)]
+    Unknown,
+}
+
+impl Capability {
+    /// 当前版本支持的所有能力
+    pub const ALL: &[Capability] = &[
+        Self::AudioData,
+        Self::CoverData,
+        Self::PlaybackMode,
+        Self::ClockSync,
+        Self::Queue,
+        Self::Spectrum,
+    ];
+
+    /// 握手出现之前就已存在的能力，旧版客户端视为支持这些能力
+    pub const LEGACY: &[Capability] = &[Self::AudioData, Self::CoverData, Self::PlaybackMode];
+}
+
+/// 客户端的握手信息
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Initialize {
+    /// 客户端名称
+    pub client_name: String,
+    /// 客户端版本
+    pub client_version: String,
+    /// 客户端实现的协议修订版本，参见 [`PROTOCOL_VERSION`]
+    pub protocol_version: u32,
+    /// 客户端支持的可选能力
+    pub capabilities: Vec<Capability>,
+}
+
+impl Initialize {
+    pub fn new(
+        client_name: impl Into<String>,
+        client_version: impl Into<String>,
+        capabilities: &[Capability],
+    ) -> Self {
+        Self {
+            client_name: client_name.into(),
+            client_version: client_version.into(),
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: capabilities.to_vec(),
+        }
+    }
+
+    /// 是否为只发送不带数据的 Initialize 消息的旧版客户端
+    ///
+    /// 旧版客户端无法识别 [`Payload::InitializeAck`]，不应向其回应，
+    /// 并且应当认为它支持所有在握手出现之前就已存在的能力
+    pub fn is_legacy(&self) -> bool {
+        self.protocol_version == 0
+    }
+
+    /// 根据服务端支持的能力生成握手回应
+    ///
+    /// 协商后的协议版本为双方版本的较小值，能力为双方都支持的能力，
+    /// 旧版客户端则视为支持 [`Capability::LEGACY`]
+    pub fn accept(
+        &self,
+        server_name: impl Into<String>,
+        server_version: impl Into<String>,
+        supported: &[Capability],
+    ) -> InitializeAck {
+        let client_capabilities = if self.is_legacy() {
+            Capability::LEGACY
+        } else {
+            &self.capabilities
+        };
+        InitializeAck {
+            server_name: server_name.into(),
+            server_version: server_version.into(),
+            protocol_version: self.protocol_version.min(PROTOCOL_VERSION),
+            capabilities: supported
+                .iter()
+                .copied()
+                .filter(|x| *x != Capability::Unknown && client_capabilities.contains(x))
+                .collect(),
+        }
+    }
+}
+
+/// 服务端对握手的回应
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct InitializeAck {
+    /// 服务端名称
+    pub server_name: String,
+    /// 服务端版本
+    pub server_version: String,
+    /// 协商后的协议修订版本
+    pub protocol_version: u32,
+    /// 协商后双方都支持的可选能力
+    pub capabilities: Vec<Capability>,
+}
+
+/// 时间同步请求，由需要估算时钟偏移的一方发送
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeSyncRequest {
+    /// 请求方时钟下的发送时间，单位为毫秒
+    pub client_send_time: u64,
+}
+
+/// 时间同步回应
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeSyncResponse {
+    /// 原样返回的请求方发送时间
+    pub client_send_time: u64,
+    /// 回应方时钟下收到请求的时间，单位为毫秒
+    pub server_receive_time: u64,
+    /// 回应方时钟下发送回应的时间，单位为毫秒
+    pub server_send_time: u64,
+}
+
+impl TimeSyncRequest {
+    /// 生成回应，`receive_time` 为收到请求的时间，发送时间取当前时间
+    pub fn respond(&self, receive_time: u64) -> TimeSyncResponse {
+        TimeSyncResponse {
+            client_send_time: self.client_send_time,
+            server_receive_time: receive_time,
+            server_send_time: crate::clock::now_millis().max(receive_time),
+        }
+    }
+}
+
+impl InitializeAck {
+    /// 是否协商了指定的能力
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy)]
@@ -44,13 +398,154 @@ pub enum Command {
     Resume,
     ForwardSong,
     BackwardSong,
-    SetVolume { volume: f64 },
-    SeekPlayProgress { progress: u64 },
-    SetRepeatMode { mode: RepeatMode },
-    SetShuffleMode { enabled: bool },
+    SetVolume {
+        volume: f64,
+    },
+    SeekPlayProgress {
+        progress: u64,
+    },
+    SetRepeatMode {
+        mode: RepeatMode,
+    },
+    SetShuffleMode {
+        enabled: bool,
+    },
+    /// 跳转到播放队列中的指定歌曲，对应播放核心的 `JumpToSong`
+    #[serde(rename_all = "camelCase")]
+    JumpToSong {
+        song_index: usize,
+    },
+    /// 将歌曲加入播放队列，`index` 为空时加入到队列末尾
+    Enqueue {
+        entries: Vec<MusicInfo>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        index: Option<usize>,
+    },
+    /// 从播放队列中移除指定位置的歌曲
+    RemoveFromQueue {
+        index: usize,
+    },
+    /// 无法识别的指令，通常由更新版本的对端发送，应当忽略
+    #[serde(other)]
+    Unknown,
+}
+
+impl From<Command> for Payload {
+    fn from(command: Command) -> Self {
+        Self::Command(command.into())
+    }
+}
+
+/// 指令消息，可以附带请求 ID
+///
+/// 接收方应当对带有请求 ID 的指令回应 [`Payload::CommandResult`]，不带请求 ID 的指令无需回应。
+/// 请求 ID 与指令的字段位于同一层级，旧版的接收方会直接忽略它。
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandRequest {
+    #[serde(flatten)]
+    pub command: Command,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<u32>,
+}
+
+impl CommandRequest {
+    pub fn new(command: Command, request_id: u32) -> Self {
+        Self {
+            command,
+            request_id: Some(request_id),
+        }
+    }
+
+    /// 根据执行结果生成回应，不带请求 ID 的指令返回 `None`
+    pub fn respond(&self, result: Result<(), ErrorInfo>) -> Option<CommandResult> {
+        Some(CommandResult {
+            request_id: self.request_id?,
+            status: match result {
+                Ok(()) => CommandStatus::Ok,
+                Err(error) => CommandStatus::Error(error),
+            },
+        })
+    }
+}
+
+impl From<Command> for CommandRequest {
+    fn from(command: Command) -> Self {
+        Self {
+            command,
+            request_id: None,
+        }
+    }
+}
+
+/// 指令的执行结果
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandResult {
+    /// 对应指令的请求 ID
+    pub request_id: u32,
+    #[serde(flatten)]
+    pub status: CommandStatus,
+}
+
+impl CommandResult {
+    pub fn is_ok(&self) -> bool {
+        self.status == CommandStatus::Ok
+    }
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "camelCase", tag = "status")]
+pub enum CommandStatus {
+    Ok,
+    Error(ErrorInfo),
+}
+
+/// 错误信息
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorInfo {
+    pub code: ErrorCode,
+    /// 供人阅读的错误描述
+    #[serde(default)]
+    pub message: String,
+}
+
+impl ErrorInfo {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// 错误类型
+#[derive(Deserialize, Serialize, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorCode {
+    /// 消息无法解析
+    MalformedMessage,
+    /// 无法识别的消息类型
+    UnsupportedMessage,
+    /// 无法识别或不支持的指令，例如播放器无法跳转播放进度
+    UnsupportedCommand,
+    /// 消息需要未协商的能力
+    MissingCapability,
+    /// 指令的参数无效，例如超出范围的播放队列位置
+    InvalidArgument,
+    /// 当前状态下无法执行，例如没有下一首歌曲
+    Unavailable,
+    /// 执行时发生了其他错误
+    Internal,
+    /// 无法识别的错误类型，通常由更新版本的对端发送
+    #[serde(other)]
+    Unknown,
 }
 
 /// 从播放器发送到 Player 的更新
+// 歌曲信息更新并不频繁，为了方便构造不对其装箱
+#[allow(clippy::large_enum_variant)]
 #[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
 #[serde(rename_all = "camelCase", tag = "update")]
 pub enum StateUpdate {
@@ -59,25 +554,79 @@ pub enum StateUpdate {
     SetLyric(LyricContent),
     Progress {
         progress: u64,
+        /// 发送方时钟下的发送时间，单位为毫秒，参见 [`crate::clock`]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timestamp: Option<u64>,
     },
     Volume {
         volume: f64,
     },
-    Paused,
-    Resumed,
+    Paused {
+        /// 发送方时钟下的暂停时间，单位为毫秒
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timestamp: Option<u64>,
+    },
+    Resumed {
+        /// 发送方时钟下的恢复时间，单位为毫秒
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timestamp: Option<u64>,
+    },
+    /// 音频数据，格式由最近一次收到的 [`StateUpdate::AudioFormat`] 描述
     AudioData {
         #[serde(with = "serde_bytes")]
         data: Vec<u8>,
+        /// 音频数据块的序号，每发送一块递增 1，溢出后从 0 开始，可用于检测丢失的数据块
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sequence: Option<u32>,
+        /// 发送方时钟下的发送时间，单位为毫秒
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timestamp: Option<u64>,
     },
+    /// 之后发送的音频数据的格式
+    AudioFormat(AudioStreamInfo),
+    /// 当前播放的音频的频谱，可用于在不传输音频数据的情况下显示可视化效果
+    Spectrum(Spectrum),
     ModeChanged {
         repeat: RepeatMode,
         shuffle: bool,
     },
+    /// 完整的播放队列，对应播放核心的 `SetPlaylist`
+    #[serde(rename_all = "camelCase")]
+    SetQueue {
+        entries: Vec<MusicInfo>,
+        /// 当前播放的歌曲在队列中的位置
+        current_index: Option<usize>,
+    },
+    /// 在播放队列的指定位置插入歌曲
+    QueueInsert {
+        index: usize,
+        entries: Vec<MusicInfo>,
+    },
+    /// 从播放队列中移除指定位置的歌曲
+    QueueRemove {
+        index: usize,
+    },
+    /// 将播放队列中的歌曲从 `from` 移动到 `to`
+    QueueMove {
+        from: usize,
+        to: usize,
+    },
+    /// 当前播放的歌曲在队列中的位置发生了变化
+    #[serde(rename_all = "camelCase")]
+    QueueIndex {
+        current_index: Option<usize>,
+    },
+    /// 无法识别的更新，通常由更新版本的对端发送，应当忽略
+    #[serde(other)]
+    Unknown,
 }
 
 // --- 数据结构 ---
 
-#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+/// 歌曲信息
+///
+/// `duration` 之后的字段均为可选字段，旧版的发送方不会发送它们。
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct MusicInfo {
     pub music_id: String,
@@ -86,6 +635,30 @@ pub struct MusicInfo {
     pub album_name: String,
     pub artists: Vec<Artist>,
     pub duration: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album_artist: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub track_number: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disc_number: Option<u32>,
+    /// 发行年份
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub year: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub genres: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub isrc: Option<String>,
+    /// 是否含有不雅内容
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub explicit: bool,
+    /// 封面的哈希值，接收方可以据此判断封面是否与之前的相同，以复用已缓存的封面
+    ///
+    /// 哈希算法由发送方决定，接收方只应比较它是否相等。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover_hash: Option<String>,
+    /// 歌词的时间偏移，单位为毫秒，含义与 [`LyricContent`] 中的 `offset` 相同
+    #[serde(default, skip_serializing_if = "is_zero_offset")]
+    pub lyric_offset: i64,
 }
 
 #[serde_as]
@@ -104,11 +677,412 @@ pub enum AlbumCover {
     Data { image: ImageData },
 }
 
+/// 音频采样格式，多字节格式均为小端序
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum SampleFormat {
+    U8,
+    S16,
+    U16,
+    S24,
+    S32,
+    F32,
+}
+
+impl SampleFormat {
+    /// 每个采样占用的字节数
+    pub fn bytes_per_sample(self) -> usize {
+        match self {
+            Self::U8 => 1,
+            Self::S16 | Self::U16 => 2,
+            Self::S24 => 3,
+            Self::S32 | Self::F32 => 4,
+        }
+    }
+}
+
+/// 音频数据的编码
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum AudioCodec {
+    /// 未经压缩的 PCM 数据
+    #[default]
+    Pcm,
+    /// 每个数据块为一个或多个 Opus 帧
+    Opus,
+    /// 每个数据块为一个或多个 FLAC 帧
+    Flac,
+    /// 无法识别的编码，接收方应当忽略音频数据
+    #[serde(other)]
+    Unknown,
+}
+
+/// 音频流的格式描述
+///
+/// 未收到格式描述时，应当认为音频数据为 48000 Hz 双声道交错的 U16 PCM 数据，即 v1 协议中约定的格式。
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioStreamInfo {
+    /// 采样格式，对于压缩编码则为解码后的采样格式
+    pub sample_format: SampleFormat,
+    /// 采样率，单位为赫兹
+    pub sample_rate: u32,
+    /// 声道数量
+    pub channels: u16,
+    /// 各个声道的采样是否交错排列，否则依次存放每个声道的全部采样
+    #[serde(default = "default_interleaved")]
+    pub interleaved: bool,
+    /// 音频数据的编码
+    #[serde(default)]
+    pub codec: AudioCodec,
+}
+
+fn default_interleaved() -> bool {
+    true
+}
+
+impl Default for AudioStreamInfo {
+    fn default() -> Self {
+        Self {
+            sample_format: SampleFormat::U16,
+            sample_rate: 48000,
+            channels: 2,
+            interleaved: true,
+            codec: AudioCodec::Pcm,
+        }
+    }
+}
+
+impl AudioStreamInfo {
+    /// 一帧（即所有声道各一个采样）PCM 数据占用的字节数
+    pub fn bytes_per_frame(&self) -> usize {
+        self.sample_format.bytes_per_sample() * self.channels as usize
+    }
+
+    /// PCM 数据块包含的时长，单位为毫秒，压缩编码的数据块无法直接计算，返回 `None`
+    pub fn duration_of(&self, data_len: usize) -> Option<f64> {
+        if self.codec != AudioCodec::Pcm || self.bytes_per_frame() == 0 || self.sample_rate == 0 {
+            return None;
+        }
+        Some((data_len / self.bytes_per_frame()) as f64 * 1000.0 / self.sample_rate as f64)
+    }
+}
+
+/// 根据音频数据块的序号检测丢失的数据块
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AudioSequenceTracker {
+    last: Option<u32>,
+}
+
+impl AudioSequenceTracker {
+    /// 记录收到的序号，返回自上一块以来丢失的数据块数量
+    ///
+    /// 序号小于等于上一块（即乱序或重复的数据块）时视为没有丢失。
+    pub fn push(&mut self, sequence: u32) -> u32 {
+        let lost = match self.last {
+            Some(last) => {
+                let gap = sequence.wrapping_sub(last);
+                if gap == 0 || gap > u32::MAX / 2 {
+                    return 0;
+                }
+                gap - 1
+            }
+            None => 0,
+        };
+        self.last = Some(sequence);
+        lost
+    }
+
+    /// 音频流重新开始时调用，例如收到新的 [`StateUpdate::AudioFormat`]
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+/// 频谱中各个频段在频率上的分布方式
+#[binrw]
+#[brw(repr = u8)]
+#[derive(Deserialize, Serialize, PartialEq, Eq, Hash, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum FrequencyScale {
+    /// 各个频段的频率等距分布
+    #[default]
+    Linear = 0,
+    /// 各个频段的频率按对数等距分布
+    Logarithmic = 1,
+    /// 无法识别的分布方式，通常由更新版本的对端发送
+    #[serde(other)]
+    Unknown = 255,
+}
+
+/// 频谱中幅值的编码格式，多字节格式均为小端序
+#[binrw]
+#[brw(repr = u8)]
+#[derive(Deserialize, Serialize, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum SpectrumFormat {
+    F32 = 0,
+    /// 半精度浮点数
+    F16 = 1,
+    /// 0 至 255 线性对应 0 至 [`Spectrum::peak`]
+    U8 = 2,
+    /// 无法识别的格式，通常由更新版本的对端发送
+    #[serde(other)]
+    Unknown = 255,
+}
+
+impl SpectrumFormat {
+    pub fn bytes_per_value(&self) -> usize {
+        match self {
+            Self::F32 => 4,
+            Self::F16 => 2,
+            Self::U8 | Self::Unknown => 1,
+        }
+    }
+}
+
+/// 频谱数据
+#[binrw]
+#[brw(little)]
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Spectrum {
+    /// 第一个频段的频率，单位为 Hz
+    pub min_frequency: f32,
+    /// 最后一个频段的频率，单位为 Hz
+    pub max_frequency: f32,
+    #[serde(default)]
+    pub scale: FrequencyScale,
+    pub format: SpectrumFormat,
+    /// 所有频段中最大的幅值
+    pub peak: f32,
+    /// 频段数量
+    pub bins: u32,
+    #[bw(try_calc = u32::try_from(data.len()))]
+    size: u32,
+    /// 按照 `format` 编码的各个频段的幅值
+    #[br(count = size)]
+    #[serde(with = "serde_with::As::<serde_with::base64::Base64>")]
+    pub data: Vec<u8>,
+}
+
+impl Spectrum {
+    /// 以指定格式编码各个频段的幅值，格式为 [`SpectrumFormat::Unknown`] 时返回 `None`
+    pub fn encode(
+        values: &[f32],
+        format: SpectrumFormat,
+        min_frequency: f32,
+        max_frequency: f32,
+        scale: FrequencyScale,
+    ) -> Option<Self> {
+        if format == SpectrumFormat::Unknown {
+            return None;
+        }
+        let peak = values.iter().copied().fold(0.0, f32::max);
+        let mut data = Vec::with_capacity(values.len() * format.bytes_per_value());
+        for &value in values {
+            match format {
+                SpectrumFormat::F32 => data.extend_from_slice(&value.to_le_bytes()),
+                SpectrumFormat::F16 => data.extend_from_slice(&f32_to_f16(value).to_le_bytes()),
+                SpectrumFormat::U8 => {
+                    let value = if peak > 0.0 { value / peak } else { 0.0 };
+                    data.push((value.clamp(0.0, 1.0) * 255.0).round() as u8);
+                }
+                // 已在前面排除
+                SpectrumFormat::Unknown => unreachable!(),
+            }
+        }
+        Some(Self {
+            min_frequency,
+            max_frequency,
+            scale,
+            format,
+            peak,
+            bins: values.len() as u32,
+            data,
+        })
+    }
+
+    /// 解码各个频段的幅值，格式无法识别或数据长度与频段数量不符时返回 `None`
+    pub fn decode(&self) -> Option<Vec<f32>> {
+        // 在 32 位平台上频段数量乘以每个值的字节数可能溢出
+        let len = (self.bins as usize).checked_mul(self.format.bytes_per_value())?;
+        if self.data.len() != len {
+            return None;
+        }
+        Some(match self.format {
+            SpectrumFormat::F32 => self
+                .data
+                .chunks_exact(4)
+                .map(|x| f32::from_le_bytes([x[0], x[1], x[2], x[3]]))
+                .collect(),
+            SpectrumFormat::F16 => self
+                .data
+                .chunks_exact(2)
+                .map(|x| f16_to_f32(u16::from_le_bytes([x[0], x[1]])))
+                .collect(),
+            SpectrumFormat::U8 => self
+                .data
+                .iter()
+                .map(|&x| x as f32 / 255.0 * self.peak)
+                .collect(),
+            SpectrumFormat::Unknown => return None,
+        })
+    }
+}
+
+/// 将单精度浮点数转换为半精度浮点数的二进制表示，超出范围的值会变为无穷大或 0
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let mant = bits & 0x7f_ffff;
+    if exp == 0xff {
+        return sign | 0x7c00 | if mant != 0 { 0x200 } else { 0 };
+    }
+    let exp = exp - 127 + 15;
+    if exp >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exp <= 0 {
+        // 半精度的非规格化数
+        if exp < -10 {
+            return sign;
+        }
+        let mant = mant | 0x80_0000;
+        let shift = (14 - exp) as u32;
+        let round = (mant >> (shift - 1)) & 1;
+        return sign | ((mant >> shift) + round) as u16;
+    }
+    // 舍入产生的进位会正确地进入指数部分
+    let round = (mant >> 12) & 1;
+    sign | ((((exp as u32) << 10) | (mant >> 13)) + round) as u16
+}
+
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = ((half & 0x8000) as u32) << 16;
+    let exp = ((half >> 10) & 0x1f) as u32;
+    let mant = (half & 0x3ff) as u32;
+    let bits = match exp {
+        0 => {
+            let value = mant as f32 / (1 << 24) as f32;
+            return if sign != 0 { -value } else { value };
+        }
+        0x1f => sign | 0x7f80_0000 | (mant << 13),
+        _ => sign | ((exp + 112) << 23) | (mant << 13),
+    };
+    f32::from_bits(bits)
+}
+
+/// 歌词内容
+///
+/// 所有格式都可以附带 `offset` 字段，单位为毫秒，显示时应将歌词的所有时间加上该值，
+/// 即正数会让歌词延后显示。
 #[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
 #[serde(rename_all = "camelCase", tag = "format")]
 pub enum LyricContent {
-    Structured { lines: Vec<LyricLine> },
-    Ttml { data: String },
+    Structured {
+        lines: Vec<LyricLine>,
+        /// 歌词的元数据，以 `[键, 值数组]` 的形式存储，与 TTML 歌词中的元数据一致
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        metadata: Vec<(String, Vec<String>)>,
+        #[serde(default, skip_serializing_if = "is_zero_offset")]
+        offset: i64,
+    },
+    Ttml {
+        data: String,
+        #[serde(default, skip_serializing_if = "is_zero_offset")]
+        offset: i64,
+    },
+    /// 未经解析的其他格式的歌词，由接收方自行解析
+    ///
+    /// 启用 `amll-lyric` 功能后可以通过 [`LyricContent::into_structured`] 解析。
+    #[serde(rename_all = "camelCase")]
+    Raw {
+        #[serde(rename = "extraFormat")]
+        format: LyricFormat,
+        data: String,
+        #[serde(default, skip_serializing_if = "is_zero_offset")]
+        offset: i64,
+    },
+}
+
+fn is_zero_offset(offset: &i64) -> bool {
+    *offset == 0
+}
+
+/// [`LyricContent::Raw`] 中歌词的格式
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum LyricFormat {
+    Lrc,
+    Eslrc,
+    Yrc,
+    Qrc,
+    Lys,
+    Ttml,
+    /// 无法识别的格式，接收方应当忽略这份歌词
+    #[serde(other)]
+    Unknown,
+}
+
+impl LyricContent {
+    /// 歌词的时间偏移，单位为毫秒
+    pub fn offset(&self) -> i64 {
+        match self {
+            Self::Structured { offset, .. }
+            | Self::Ttml { offset, .. }
+            | Self::Raw { offset, .. } => *offset,
+        }
+    }
+
+    /// 将歌词解析为 [`LyricContent::Structured`]，时间偏移会原样保留，需要启用 `amll-lyric` 功能
+    ///
+    /// TTML 歌词中的元数据会一并保留，其他格式的元数据会被忽略。
+    #[cfg(feature = "amll-lyric")]
+    pub fn into_structured(self) -> anyhow::Result<Self> {
+        use amll_lyric::*;
+
+        let convert = |lines: Vec<LyricLine<'_>>| lines.iter().map(Into::into).collect();
+        let (lines, metadata, offset) = match self {
+            Self::Structured { .. } => return Ok(self),
+            Self::Ttml { data, offset }
+            | Self::Raw {
+                format: LyricFormat::Ttml,
+                data,
+                offset,
+            } => {
+                let ttml = ttml::parse_ttml(data.as_bytes())?;
+                let metadata = ttml
+                    .metadata
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.iter().map(|x| x.to_string()).collect()))
+                    .collect();
+                (convert(ttml.lines), metadata, offset)
+            }
+            Self::Raw {
+                format,
+                data,
+                offset,
+            } => {
+                let lines = match format {
+                    LyricFormat::Lrc => lrc::parse_lrc(&data),
+                    LyricFormat::Eslrc => eslrc::parse_eslrc(&data),
+                    LyricFormat::Yrc => yrc::parse_yrc(&data),
+                    LyricFormat::Qrc => qrc::parse_qrc(&data),
+                    LyricFormat::Lys => lys::parse_lys(&data),
+                    LyricFormat::Ttml => unreachable!(),
+                    LyricFormat::Unknown => anyhow::bail!("无法识别的歌词格式"),
+                };
+                (convert(lines), Vec::new(), offset)
+            }
+        };
+        Ok(Self::Structured {
+            lines,
+            metadata,
+            offset,
+        })
+    }
 }
 
 /// 通过二进制通道传输的负载，可用于降低通过互联网传输数据的延迟。
@@ -116,7 +1090,7 @@ pub enum LyricContent {
 /// 不建议在本地环境中使用它们
 #[binrw]
 #[brw(little)]
-#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", tag = "type", content = "value")]
 pub enum BinaryV2 {
     #[brw(magic(0u16))]
@@ -135,6 +1109,20 @@ pub enum BinaryV2 {
         #[serde(with = "serde_bytes")]
         data: Vec<u8>,
     },
+    /// 带有序号和发送时间戳的音频数据，参见 [`StateUpdate::AudioData`]
+    #[brw(magic(2u16))]
+    OnAudioChunk {
+        sequence: u32,
+        timestamp: u64,
+        #[bw(try_calc = u32::try_from(data.len()))]
+        size: u32,
+        #[br(count = size)]
+        #[serde(with = "serde_bytes")]
+        data: Vec<u8>,
+    },
+    /// 频谱数据，参见 [`StateUpdate::Spectrum`]
+    #[brw(magic(3u16))]
+    OnSpectrum(Spectrum),
 }
 
 pub fn parse_binary_v2(data: &[u8]) -> anyhow::Result<BinaryV2> {
@@ -170,3 +1158,394 @@ pub fn to_binary_v2_js(payload: JsValue) -> Result<Box<[u8]>, JsValue> {
         Err(err) => Err(err.into()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handshake_test() {
+        // 旧版客户端只发送消息类型
+        let legacy: MessageV2 = serde_json::from_str(r#"{"type":"initialize"}"#).unwrap();
+        let Payload::Initialize(legacy) = legacy.payload else {
+            panic!("expected initialize");
+        };
+        assert!(legacy.is_legacy());
+        let ack = legacy.accept("server", "1.0", Capability::ALL);
+        assert_eq!(ack.protocol_version, 0);
+        assert_eq!(ack.capabilities, Capability::LEGACY);
+
+        let init: Payload = serde_json::from_str(
+            r#"{"type":"initialize","value":{"clientName":"client","clientVersion":"0.1","protocolVersion":99,"capabilities":["coverData","playbackMode","hologram"]}}"#,
+        )
+        .unwrap();
+        let Payload::Initialize(init) = init else {
+            panic!("expected initialize");
+        };
+        assert_eq!(init.client_name, "client");
+        assert_eq!(init.capabilities[2], Capability::Unknown);
+
+        let ack = init.accept("server", "1.0", Capability::ALL);
+        assert_eq!(ack.protocol_version, PROTOCOL_VERSION);
+        assert_eq!(
+            ack.capabilities,
+            [Capability::CoverData, Capability::PlaybackMode]
+        );
+        assert!(!ack.supports(Capability::AudioData));
+
+        let payload = Payload::InitializeAck(ack);
+        let json = serde_json::to_string(&payload).unwrap();
+        assert_eq!(serde_json::from_str::<Payload>(&json).unwrap(), payload);
+    }
+
+    #[test]
+    fn timestamp_test() {
+        let mut payload: Payload =
+            serde_json::from_str(r#"{"type":"state","value":{"update":"paused"}}"#).unwrap();
+        assert_eq!(
+            payload,
+            Payload::State(StateUpdate::Paused { timestamp: None })
+        );
+        payload.stamp(1234);
+        payload.stamp(5678);
+        assert_eq!(
+            serde_json::to_string(&payload).unwrap(),
+            r#"{"type":"state","value":{"update":"paused","timestamp":1234}}"#
+        );
+
+        let request = TimeSyncRequest {
+            client_send_time: 100,
+        };
+        let response = request.respond(200);
+        assert_eq!(response.client_send_time, 100);
+        assert!(response.server_send_time >= 200);
+    }
+
+    #[test]
+    fn audio_test() {
+        let info: AudioStreamInfo =
+            serde_json::from_str(r#"{"sampleFormat":"f32","sampleRate":44100,"channels":2}"#)
+                .unwrap();
+        assert!(info.interleaved);
+        assert_eq!(info.codec, AudioCodec::Pcm);
+        assert_eq!(info.bytes_per_frame(), 8);
+        assert_eq!(info.duration_of(44100 * 8), Some(1000.0));
+
+        let mut tracker = AudioSequenceTracker::default();
+        assert_eq!(tracker.push(u32::MAX - 1), 0);
+        assert_eq!(tracker.push(1), 2);
+        assert_eq!(tracker.push(0), 0);
+        assert_eq!(tracker.push(2), 0);
+
+        let chunk = BinaryV2::OnAudioChunk {
+            sequence: 7,
+            timestamp: 1234,
+            data: vec![1, 2, 3, 4],
+        };
+        let data = to_binary_v2(&chunk).unwrap();
+        assert_eq!(&data[..2], &[2, 0]);
+        assert_eq!(parse_binary_v2(&data).unwrap(), chunk);
+        assert_eq!(
+            Payload::from(chunk),
+            Payload::State(StateUpdate::AudioData {
+                data: vec![1, 2, 3, 4],
+                sequence: Some(7),
+                timestamp: Some(1234),
+            })
+        );
+    }
+
+    #[test]
+    fn music_info_test() {
+        // 旧版发送方的歌曲信息不包含可选字段
+        let src = r#"{"musicId":"1","musicName":"a","albumId":"","albumName":"b","artists":[],"duration":1000}"#;
+        let info: MusicInfo = serde_json::from_str(src).unwrap();
+        assert_eq!(info.track_number, None);
+        assert!(!info.explicit);
+        assert_eq!(serde_json::to_string(&info).unwrap(), src);
+
+        let info = MusicInfo {
+            album_artist: Some("c".into()),
+            track_number: Some(3),
+            genres: vec!["Pop".into()],
+            explicit: true,
+            lyric_offset: 250,
+            ..info
+        };
+        let json = serde_json::to_string(&info).unwrap();
+        assert!(json.contains(r#""trackNumber":3"#));
+        assert!(json.contains(r#""lyricOffset":250"#));
+        assert_eq!(serde_json::from_str::<MusicInfo>(&json).unwrap(), info);
+    }
+
+    #[test]
+    fn spectrum_test() {
+        let values = [0.0, 0.5, 1.0, 2.0];
+        let spectrum = Spectrum::encode(
+            &values,
+            SpectrumFormat::U8,
+            80.0,
+            2000.0,
+            FrequencyScale::Linear,
+        )
+        .unwrap();
+        assert_eq!(spectrum.bins, 4);
+        assert_eq!(spectrum.peak, 2.0);
+        assert_eq!(spectrum.data, [0, 64, 128, 255]);
+        let decoded = spectrum.decode().unwrap();
+        assert!(
+            values
+                .iter()
+                .zip(&decoded)
+                .all(|(a, b)| (a - b).abs() < 0.01)
+        );
+
+        let payload = Payload::State(StateUpdate::Spectrum(spectrum.clone()));
+        assert_eq!(payload.required_capability(), Some(Capability::Spectrum));
+        let json = serde_json::to_string(&payload).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"state","value":{"update":"spectrum","minFrequency":80.0,"maxFrequency":2000.0,"scale":"linear","format":"u8","peak":2.0,"bins":4,"data":"AECA/w=="}}"#
+        );
+        assert_eq!(serde_json::from_str::<Payload>(&json).unwrap(), payload);
+
+        for format in [SpectrumFormat::F32, SpectrumFormat::F16] {
+            let spectrum =
+                Spectrum::encode(&values, format, 80.0, 2000.0, FrequencyScale::Linear).unwrap();
+            assert_eq!(spectrum.data.len(), 4 * format.bytes_per_value());
+            assert_eq!(spectrum.decode().unwrap(), values);
+        }
+
+        let binary = BinaryV2::OnSpectrum(spectrum);
+        let data = to_binary_v2(&binary).unwrap();
+        assert_eq!(&data[..2], &[3, 0]);
+        assert_eq!(parse_binary_v2(&data).unwrap(), binary);
+        assert_eq!(Payload::from(binary), payload);
+
+        let mut invalid = Spectrum::encode(
+            &values,
+            SpectrumFormat::F32,
+            80.0,
+            2000.0,
+            FrequencyScale::Linear,
+        )
+        .unwrap();
+        invalid.bins = u32::MAX;
+        assert_eq!(invalid.decode(), None);
+        assert_eq!(
+            Spectrum::encode(
+                &values,
+                SpectrumFormat::Unknown,
+                80.0,
+                2000.0,
+                FrequencyScale::Linear
+            ),
+            None
+        );
+
+        for value in [0.0, -2.5, 0.1, 65504.0, 1e-5, f32::INFINITY] {
+            let converted = f16_to_f32(f32_to_f16(value));
+            assert!(
+                (converted - value).abs() <= value.abs() * 1e-3 + 1e-7 || converted == value,
+                "{value} -> {converted}"
+            );
+        }
+        assert_eq!(f16_to_f32(f32_to_f16(1e6)), f32::INFINITY);
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+    }
+
+    #[test]
+    fn lyric_content_test() {
+        let lyric: LyricContent =
+            serde_json::from_str(r#"{"format":"raw","extraFormat":"lrc","data":"[00:01.00]a"}"#)
+                .unwrap();
+        assert_eq!(
+            lyric,
+            LyricContent::Raw {
+                format: LyricFormat::Lrc,
+                data: "[00:01.00]a".into(),
+                offset: 0,
+            }
+        );
+        assert_eq!(
+            serde_json::to_string(&lyric).unwrap(),
+            r#"{"format":"raw","extraFormat":"lrc","data":"[00:01.00]a"}"#
+        );
+
+        let lyric: LyricContent = serde_json::from_str(
+            r#"{"format":"structured","lines":[],"metadata":[["musicName",["a"]]],"offset":-500}"#,
+        )
+        .unwrap();
+        assert_eq!(lyric.offset(), -500);
+
+        let lyric: LyricContent =
+            serde_json::from_str(r#"{"format":"raw","extraFormat":"krc","data":""}"#).unwrap();
+        assert!(matches!(
+            lyric,
+            LyricContent::Raw {
+                format: LyricFormat::Unknown,
+                ..
+            }
+        ));
+    }
+
+    #[cfg(feature = "amll-lyric")]
+    #[test]
+    fn parse_raw_lyric_test() {
+        let lyric = LyricContent::Raw {
+            format: LyricFormat::Lrc,
+            data: "[00:01.00]hello\n[00:02.50]world".into(),
+            offset: 100,
+        };
+        let LyricContent::Structured {
+            lines,
+            metadata,
+            offset,
+        } = lyric.into_structured().unwrap()
+        else {
+            panic!("expected structured lyric");
+        };
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].start_time, 1000);
+        assert_eq!(lines[1].words[0].word.as_str(), "world");
+        assert!(metadata.is_empty());
+        assert_eq!(offset, 100);
+
+        let lyric = LyricContent::Ttml {
+            data: r#"<tt xmlns="http://www.w3.org/ns/ttml" xmlns:amll="http://www.example.com/ns/amll"><head><metadata><amll:meta key="musicName" value="test"/></metadata></head><body><div><p begin="00:01.000" end="00:02.000"><span begin="00:01.000" end="00:02.000">hi</span></p></div></body></tt>"#.into(),
+            offset: 0,
+        };
+        let LyricContent::Structured {
+            lines, metadata, ..
+        } = lyric.into_structured().unwrap()
+        else {
+            panic!("expected structured lyric");
+        };
+        assert_eq!(lines[0].words[0].word.as_str(), "hi");
+        assert_eq!(metadata, [("musicName".into(), vec!["test".into()])]);
+
+        assert!(
+            LyricContent::Raw {
+                format: LyricFormat::Unknown,
+                data: String::new(),
+                offset: 0,
+            }
+            .into_structured()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn command_result_test() {
+        let request = CommandRequest::new(Command::ForwardSong, 3);
+        let json = serde_json::to_string(&Payload::Command(request.clone())).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"command","value":{"command":"forwardSong","requestId":3}}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Payload>(&json).unwrap(),
+            Payload::Command(request.clone())
+        );
+        // 不带请求 ID 的指令与旧版一致
+        assert_eq!(
+            serde_json::to_string(&Payload::from(Command::Pause)).unwrap(),
+            r#"{"type":"command","value":{"command":"pause"}}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Payload>(
+                r#"{"type":"command","value":{"command":"dance","speed":2,"requestId":4}}"#
+            )
+            .unwrap(),
+            Payload::Command(CommandRequest::new(Command::Unknown, 4))
+        );
+
+        let result = request
+            .respond(Err(ErrorInfo::new(
+                ErrorCode::Unavailable,
+                "没有下一首歌曲",
+            )))
+            .unwrap();
+        assert!(!result.is_ok());
+        let json = serde_json::to_string(&Payload::CommandResult(result.clone())).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"commandResult","value":{"requestId":3,"status":"error","code":"unavailable","message":"没有下一首歌曲"}}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Payload>(&json).unwrap(),
+            Payload::CommandResult(result)
+        );
+        assert!(request.respond(Ok(())).unwrap().is_ok());
+        assert!(
+            CommandRequest::from(Command::Pause)
+                .respond(Ok(()))
+                .is_none()
+        );
+
+        assert_eq!(
+            serde_json::from_str::<Payload>(
+                r#"{"type":"error","value":{"code":"somethingNew","message":"?"}}"#
+            )
+            .unwrap(),
+            Payload::Error(ErrorInfo::new(ErrorCode::Unknown, "?"))
+        );
+    }
+
+    #[test]
+    fn unknown_message_test() {
+        for src in [
+            r#"{"type":"hologram","value":{"x":1}}"#,
+            r#"{"type":"hologram"}"#,
+        ] {
+            assert_eq!(
+                serde_json::from_str::<MessageV2>(src).unwrap().payload,
+                Payload::Unknown
+            );
+        }
+        assert_eq!(
+            serde_json::from_str::<Payload>(
+                r#"{"type":"command","value":{"command":"dance","speed":2}}"#
+            )
+            .unwrap(),
+            Payload::from(Command::Unknown)
+        );
+        assert_eq!(
+            serde_json::from_str::<Payload>(r#"{"value":{"update":"hologram"},"type":"state"}"#)
+                .unwrap(),
+            Payload::State(StateUpdate::Unknown)
+        );
+        // 已知类型的消息格式错误时仍然应当报错
+        assert!(
+            serde_json::from_str::<Payload>(
+                r#"{"type":"command","value":{"command":"setVolume"}}"#
+            )
+            .is_err()
+        );
+        for src in [
+            r#"{"value":{"command":"pause"}}"#,
+            r#"{"type":"ping","type":"pong"}"#,
+            r#"{"type":"state"}"#,
+        ] {
+            assert!(serde_json::from_str::<Payload>(src).is_err());
+        }
+    }
+
+    #[test]
+    fn payload_fields_test() {
+        for (src, payload) in [
+            (r#"{"type":"ping"}"#, Payload::Ping),
+            (r#"{"type":"pong","value":null}"#, Payload::Pong),
+            (
+                r#"{"type":"initialize","value":null}"#,
+                Payload::Initialize(Initialize::default()),
+            ),
+            (
+                r#"{"id":1,"value":{"command":"pause"},"type":"command"}"#,
+                Payload::from(Command::Pause),
+            ),
+        ] {
+            assert_eq!(serde_json::from_str::<Payload>(src).unwrap(), payload);
+        }
+    }
+}